@@ -0,0 +1,17 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate chip8;
+
+use chip8::Opcode;
+
+// Every word either fails to decode or encodes back to itself
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let word = ((data[0] as u16) << 8) | data[1] as u16;
+    if let Ok(op) = Opcode::decode_op(word) {
+        assert_eq!(op.encode(), word);
+    }
+});
@@ -0,0 +1,34 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate chip8;
+
+use chip8::Chip8;
+use chip8::Platform;
+use chip8::Rng;
+
+const PLATFORMS: [Platform; 3] =
+    [Platform::Chip8, Platform::SuperChip, Platform::XoChip];
+const FRAMES: u32 = 60;
+const CYCLES_PER_FRAME: u32 = 100;
+
+// The first byte picks the platform and whether to cache decoded
+// instructions, the rest is the rom. Running it may fail but must not panic.
+fuzz_target!(|data: &[u8]| {
+    let (settings, rom) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let platform = PLATFORMS[*settings as usize % PLATFORMS.len()];
+    let quirks = platform.default_quirks();
+    let mut chip8 = match Chip8::new(rom, platform, quirks, Rng::new(0)) {
+        Ok(chip8) => chip8,
+        Err(_) => return,
+    };
+    chip8.set_decode_cache(settings & 0x80 != 0);
+    for _ in 0..FRAMES {
+        if chip8.run_frame(CYCLES_PER_FRAME).is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,649 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use num::ToPrimitive;
+
+use nybble::Nybble;
+use nybble::ThreeNybbles;
+use nybble::TwoNybbles;
+use opcode::NoArg;
+use opcode::OneArg;
+use opcode::Opcode;
+use opcode::ThreeArg;
+use opcode::TwoArg;
+
+const ROM_START: u16 = 0x200;
+// Highest address a 12 bit jump, call or I load can reach
+const ADDR_LIMIT: u16 = 0xFFF;
+
+#[derive(Debug)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Error for AsmError {}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// Assembles Octo source into a rom that starts at 0x200. Supports labels,
+// :alias, :const, :call, :byte, loop/again, raw bytes and every instruction
+// Octo has a statement for.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let tokens = source
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            code.split_whitespace().map(move |text| Token {
+                text,
+                line: index + 1,
+            })
+        })
+        .collect();
+    let mut asm = Assembler::new(tokens);
+    while asm.pos < asm.tokens.len() {
+        asm.statement()?;
+    }
+    asm.finish()
+}
+
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+// A reference to a label, patched once the label is known
+struct Fixup {
+    offset: usize,
+    label: String,
+    line: usize,
+    // Long references fill a whole word instead of the low 12 bits
+    long: bool,
+}
+
+enum Target {
+    Addr(u16),
+    Label(String),
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    rom: Vec<u8>,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, i32>,
+    aliases: HashMap<String, u8>,
+    fixups: Vec<Fixup>,
+    loops: Vec<u16>,
+    // Like Octo, the first word jumps to main, unless main comes first
+    jump_to_main: bool,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(tokens: Vec<Token<'a>>) -> Assembler<'a> {
+        let mut asm = Assembler {
+            tokens,
+            pos: 0,
+            rom: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            fixups: Vec::new(),
+            loops: Vec::new(),
+            jump_to_main: true,
+        };
+        let line = asm.tokens.first().map_or(1, |token| token.line);
+        asm.emit_target(
+            ThreeArg::JumpToAddr,
+            Target::Label("main".to_string()),
+            line,
+        );
+        asm
+    }
+
+    fn here(&self) -> u16 {
+        ROM_START + self.rom.len() as u16
+    }
+
+    fn error<T>(&self, line: usize, message: String) -> Result<T, AsmError> {
+        Err(AsmError { line, message })
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, AsmError> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(*token)
+            }
+            None => {
+                let line = self.tokens.last().map_or(1, |token| token.line);
+                self.error(line, "Unexpected end of source".to_string())
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|token| token.text)
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if token.text != text {
+            return self.error(
+                token.line,
+                format!("Expected '{}', found '{}'", text, token.text),
+            );
+        }
+        Ok(())
+    }
+
+    fn emit_word(&mut self, word: u16) {
+        self.rom.push((word >> 8) as u8);
+        self.rom.push(word as u8);
+    }
+
+    fn emit(&mut self, op: Opcode) -> Result<(), AsmError> {
+        self.emit_word(op.encode());
+        Ok(())
+    }
+
+    // Emits an instruction taking an address, which may be a label that is
+    // only defined further down.
+    fn emit_target<F>(&mut self, op: F, target: Target, line: usize)
+    where
+        F: Fn(ThreeNybbles) -> ThreeArg,
+    {
+        let addr = match target {
+            Target::Addr(addr) => addr,
+            Target::Label(label) => {
+                self.fixups.push(Fixup {
+                    offset: self.rom.len(),
+                    label,
+                    line,
+                    long: false,
+                });
+                0
+            }
+        };
+        self.emit_word(Opcode::ThreeArg(op(ThreeNybbles::from(addr))).encode());
+    }
+
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+        match token.text {
+            ":" => self.label(),
+            ":alias" => {
+                let name = self.name()?;
+                let reg = self.register()?;
+                self.aliases.insert(name, reg);
+                Ok(())
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.number()?;
+                self.constants.insert(name, value);
+                Ok(())
+            }
+            ":call" => {
+                let (target, line) = self.target()?;
+                self.emit_target(ThreeArg::CallSubAt, target, line);
+                Ok(())
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.rom.push(byte);
+                Ok(())
+            }
+            "clear" => self.emit(Opcode::NoArg(NoArg::ClearScreen)),
+            "return" | ";" => self.emit(Opcode::NoArg(NoArg::ReturnSubrt)),
+            "exit" => self.emit(Opcode::NoArg(NoArg::Exit)),
+            "lores" => self.emit(Opcode::NoArg(NoArg::LowRes)),
+            "hires" => self.emit(Opcode::NoArg(NoArg::HighRes)),
+            "scroll-left" => self.emit(Opcode::NoArg(NoArg::ScrollLeft)),
+            "scroll-right" => self.emit(Opcode::NoArg(NoArg::ScrollRight)),
+            "audio" => self.emit(Opcode::NoArg(NoArg::LoadAudio)),
+            "scroll-down" => {
+                let n = self.nybble()?;
+                self.emit(Opcode::OneArg(OneArg::ScrollDown(n)))
+            }
+            "scroll-up" => {
+                let n = self.nybble()?;
+                self.emit(Opcode::OneArg(OneArg::ScrollUp(n)))
+            }
+            "plane" => {
+                let n = self.nybble()?;
+                self.emit(Opcode::OneArg(OneArg::SelectPlane(n)))
+            }
+            "jump" => {
+                let (target, line) = self.target()?;
+                self.emit_target(ThreeArg::JumpToAddr, target, line);
+                Ok(())
+            }
+            "jump0" => {
+                let (target, line) = self.target()?;
+                self.emit_target(ThreeArg::PCEqNNNPlusV0, target, line);
+                Ok(())
+            }
+            "loop" => {
+                let here = self.here();
+                self.loops.push(here);
+                Ok(())
+            }
+            "again" => match self.loops.pop() {
+                Some(start) => {
+                    self.emit_target(
+                        ThreeArg::JumpToAddr,
+                        Target::Addr(start),
+                        token.line,
+                    );
+                    Ok(())
+                }
+                None => self.error(
+                    token.line,
+                    "'again' without a matching 'loop'".to_string(),
+                ),
+            },
+            "i" => self.index(),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = Nybble::new([self.register()?]);
+                self.emit(Opcode::OneArg(match token.text {
+                    "delay" => OneArg::SetDT(x),
+                    "buzzer" => OneArg::SetST(x),
+                    _ => OneArg::SetPitch(x),
+                }))
+            }
+            "if" => self.condition(),
+            "save" | "load" => {
+                let x = self.register()?;
+                if self.peek() == Some("-") {
+                    self.pos += 1;
+                    let xy = xy(x, self.register()?);
+                    return self.emit(Opcode::TwoArg(match token.text {
+                        "save" => TwoArg::SaveVxVy(xy),
+                        _ => TwoArg::LoadVxVy(xy),
+                    }));
+                }
+                let x = Nybble::new([x]);
+                self.emit(Opcode::OneArg(match token.text {
+                    "save" => OneArg::StoreV0Vx(x),
+                    _ => OneArg::ReadV0Vx(x),
+                }))
+            }
+            "saveflags" | "loadflags" | "bcd" => {
+                let x = Nybble::new([self.register()?]);
+                self.emit(Opcode::OneArg(match token.text {
+                    "saveflags" => OneArg::StoreRplVx(x),
+                    "loadflags" => OneArg::ReadRplVx(x),
+                    _ => OneArg::StoreDecVx(x),
+                }))
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nybble()?;
+                let xyn = ThreeNybbles::new([x, (y << 4) | n_value(&n)]);
+                self.emit(Opcode::ThreeArg(ThreeArg::DrawVxVyNib(xyn)))
+            }
+            text if self.register_of(text).is_some() => {
+                let x = self.register_of(text).expect("Checked above");
+                self.assignment(x)
+            }
+            text if is_number(text) || self.constants.contains_key(text) => {
+                self.pos -= 1;
+                let byte = self.byte()?;
+                self.rom.push(byte);
+                Ok(())
+            }
+            // Anything else names a subroutine to call
+            text if is_name(text) => {
+                let target = self.label_target(text);
+                self.emit_target(ThreeArg::CallSubAt, target, token.line);
+                Ok(())
+            }
+            text => self.error(token.line, format!("Unexpected '{}'", text)),
+        }
+    }
+
+    fn label(&mut self) -> Result<(), AsmError> {
+        let line = self.tokens[self.pos - 1].line;
+        let name = self.name()?;
+        if self.labels.contains_key(&name) {
+            return self.error(line, format!("Label {} defined twice", name));
+        }
+        // main right at the start needs no jump to reach it
+        if name == "main"
+            && self.jump_to_main
+            && self.rom.len() == 2
+            && self.labels.is_empty()
+        {
+            self.rom.clear();
+            self.fixups.clear();
+            self.jump_to_main = false;
+        }
+        let here = self.here();
+        self.labels.insert(name, here);
+        Ok(())
+    }
+
+    fn index(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+        match token.text {
+            "+=" => {
+                let x = Nybble::new([self.register()?]);
+                self.emit(Opcode::OneArg(OneArg::SetI(x)))
+            }
+            ":=" => match self.peek() {
+                Some("hex") | Some("bighex") => {
+                    let big = self.next()?.text == "bighex";
+                    let x = Nybble::new([self.register()?]);
+                    self.emit(Opcode::OneArg(if big {
+                        OneArg::SetBigSpriteI(x)
+                    } else {
+                        OneArg::SetSpriteI(x)
+                    }))
+                }
+                Some("long") => {
+                    self.pos += 1;
+                    let (target, line) = self.target_long()?;
+                    self.emit(Opcode::NoArg(NoArg::LoadILong))?;
+                    let addr = match target {
+                        Target::Addr(addr) => addr,
+                        Target::Label(label) => {
+                            self.fixups.push(Fixup {
+                                offset: self.rom.len(),
+                                label,
+                                line,
+                                long: true,
+                            });
+                            0
+                        }
+                    };
+                    self.emit_word(addr);
+                    Ok(())
+                }
+                _ => {
+                    let (target, line) = self.target()?;
+                    self.emit_target(ThreeArg::SetIToNNN, target, line);
+                    Ok(())
+                }
+            },
+            text => self.error(
+                token.line,
+                format!("Expected ':=' or '+=' after i, found '{}'", text),
+            ),
+        }
+    }
+
+    fn assignment(&mut self, x: u8) -> Result<(), AsmError> {
+        let op = self.next()?;
+        let rhs = self.next()?;
+        if let Some(y) = self.register_of(rhs.text) {
+            let xy = xy(x, y);
+            let arg = match op.text {
+                ":=" => TwoArg::VxEqVy(xy),
+                "|=" => TwoArg::VxOREqVy(xy),
+                "&=" => TwoArg::VxANDEqVy(xy),
+                "^=" => TwoArg::VxXOREqVy(xy),
+                "+=" => TwoArg::VxPlusEqVySetF(xy),
+                "-=" => TwoArg::VxSubEqVySetF(xy),
+                "=-" => TwoArg::VxEqVySubVxSetF(xy),
+                ">>=" => TwoArg::ShiftVxR(xy),
+                "<<=" => TwoArg::ShiftVxL(xy),
+                text => {
+                    return self
+                        .error(op.line, format!("Unknown operator '{}'", text))
+                }
+            };
+            return self.emit(Opcode::TwoArg(arg));
+        }
+        let nx = Nybble::new([x]);
+        match (op.text, rhs.text) {
+            (":=", "key") => self.emit(Opcode::OneArg(OneArg::WaitForKey(nx))),
+            (":=", "delay") => self.emit(Opcode::OneArg(OneArg::SetVxDT(nx))),
+            (":=", "random") => {
+                let kk = self.byte()?;
+                let arg = ThreeNybbles::new([x, kk]);
+                self.emit(Opcode::ThreeArg(ThreeArg::VxEqRandANDKK(arg)))
+            }
+            (":=", _) | ("+=", _) | ("-=", _) => {
+                self.pos -= 1;
+                let kk = self.byte()?;
+                self.emit(Opcode::ThreeArg(match op.text {
+                    ":=" => ThreeArg::SetVxKK(ThreeNybbles::new([x, kk])),
+                    "+=" => ThreeArg::VxEqVxPlusKK(ThreeNybbles::new([x, kk])),
+                    _ => ThreeArg::VxEqVxPlusKK(ThreeNybbles::new([
+                        x,
+                        kk.wrapping_neg(),
+                    ])),
+                }))
+            }
+            (text, _) => {
+                self.error(op.line, format!("Unknown operator '{}'", text))
+            }
+        }
+    }
+
+    // Octo's conditions give when the next instruction runs, so each one
+    // assembles to the skip with the opposite test.
+    fn condition(&mut self) -> Result<(), AsmError> {
+        let x = self.register()?;
+        let op = self.next()?;
+        let arg = match op.text {
+            "key" => Opcode::OneArg(OneArg::SkipIfNVx(Nybble::new([x]))),
+            "-key" => Opcode::OneArg(OneArg::SkipIfVx(Nybble::new([x]))),
+            "==" | "!=" => {
+                let rhs = self.next()?;
+                let equal = op.text == "==";
+                match self.register_of(rhs.text) {
+                    Some(y) if equal => {
+                        Opcode::TwoArg(TwoArg::SkipVxNEqVy(xy(x, y)))
+                    }
+                    Some(y) => Opcode::TwoArg(TwoArg::SkipEqVxVy(xy(x, y))),
+                    None => {
+                        self.pos -= 1;
+                        let xkk = ThreeNybbles::new([x, self.byte()?]);
+                        Opcode::ThreeArg(if equal {
+                            ThreeArg::SkipVxNEqKK(xkk)
+                        } else {
+                            ThreeArg::SkipVxEqKK(xkk)
+                        })
+                    }
+                }
+            }
+            text => {
+                return self.error(
+                    op.line,
+                    format!("Unsupported condition '{}'", text),
+                )
+            }
+        };
+        self.expect("then")?;
+        self.emit(arg)
+    }
+
+    fn name(&mut self) -> Result<String, AsmError> {
+        let token = self.next()?;
+        if !is_name(token.text) {
+            return self
+                .error(token.line, format!("'{}' is not a name", token.text));
+        }
+        Ok(token.text.to_string())
+    }
+
+    fn register_of(&self, text: &str) -> Option<u8> {
+        if let Some(reg) = self.aliases.get(text) {
+            return Some(*reg);
+        }
+        let mut chars = text.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('v'), Some(digit), None) | (Some('V'), Some(digit), None) => {
+                digit.to_digit(16).map(|reg| reg as u8)
+            }
+            _ => None,
+        }
+    }
+
+    fn register(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        match self.register_of(token.text) {
+            Some(reg) => Ok(reg),
+            None => self.error(
+                token.line,
+                format!("Expected a register, found '{}'", token.text),
+            ),
+        }
+    }
+
+    fn number(&mut self) -> Result<i32, AsmError> {
+        let token = self.next()?;
+        if let Some(value) = self.constants.get(token.text) {
+            return Ok(*value);
+        }
+        match parse_number(token.text) {
+            Some(value) => Ok(value),
+            None => self.error(
+                token.line,
+                format!("Expected a number, found '{}'", token.text),
+            ),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, AsmError> {
+        let line = self.tokens[self.pos.min(self.tokens.len() - 1)].line;
+        let value = self.number()?;
+        if !(-128..=255).contains(&value) {
+            return self.error(line, format!("{} does not fit a byte", value));
+        }
+        Ok(value as u8)
+    }
+
+    fn nybble(&mut self) -> Result<Nybble, AsmError> {
+        let line = self.tokens[self.pos.min(self.tokens.len() - 1)].line;
+        let value = self.number()?;
+        if !(0..=15).contains(&value) {
+            return self
+                .error(line, format!("{} does not fit a nybble", value));
+        }
+        Ok(Nybble::new([value as u8]))
+    }
+
+    fn label_target(&self, name: &str) -> Target {
+        match self.labels.get(name) {
+            Some(addr) => Target::Addr(*addr),
+            None => Target::Label(name.to_string()),
+        }
+    }
+
+    fn target_with_limit(
+        &mut self,
+        limit: u16,
+    ) -> Result<(Target, usize), AsmError> {
+        let token = self.next()?;
+        if self.constants.contains_key(token.text) || is_number(token.text) {
+            self.pos -= 1;
+            let value = self.number()?;
+            if value < 0 || value > limit as i32 {
+                return self.error(
+                    token.line,
+                    format!("Address {:#X} is out of range", value),
+                );
+            }
+            return Ok((Target::Addr(value as u16), token.line));
+        }
+        if !is_name(token.text) {
+            return self.error(
+                token.line,
+                format!("Expected an address, found '{}'", token.text),
+            );
+        }
+        Ok((self.label_target(token.text), token.line))
+    }
+
+    fn target(&mut self) -> Result<(Target, usize), AsmError> {
+        self.target_with_limit(ADDR_LIMIT)
+    }
+
+    fn target_long(&mut self) -> Result<(Target, usize), AsmError> {
+        self.target_with_limit(u16::MAX)
+    }
+
+    // Resolves every forward reference and hands out the finished rom
+    fn finish(mut self) -> Result<Vec<u8>, AsmError> {
+        if let Some(start) = self.loops.pop() {
+            let line = self.tokens.last().map_or(1, |token| token.line);
+            return self.error(
+                line,
+                format!("'loop' at {:#X} is never closed by 'again'", start),
+            );
+        }
+        for fixup in &self.fixups {
+            let addr = match self.labels.get(&fixup.label) {
+                Some(addr) => *addr,
+                None => {
+                    return self.error(
+                        fixup.line,
+                        format!("Undefined label {}", fixup.label),
+                    )
+                }
+            };
+            if fixup.long {
+                self.rom[fixup.offset] = (addr >> 8) as u8;
+                self.rom[fixup.offset + 1] = addr as u8;
+            } else if addr > ADDR_LIMIT {
+                return self.error(
+                    fixup.line,
+                    format!("Label {} is out of 12 bit range", fixup.label),
+                );
+            } else {
+                self.rom[fixup.offset] |= (addr >> 8) as u8;
+                self.rom[fixup.offset + 1] = addr as u8;
+            }
+        }
+        Ok(self.rom)
+    }
+}
+
+fn xy(x: u8, y: u8) -> TwoNybbles {
+    TwoNybbles::from(((x as u16) << 8) | ((y as u16) << 4))
+}
+
+fn n_value(n: &Nybble) -> u8 {
+    n.to_u8().expect("Check u8")
+}
+
+fn parse_number(text: &str) -> Option<i32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i32::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i32::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse::<i32>().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn is_number(text: &str) -> bool {
+    parse_number(text).is_some()
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) if first.is_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,187 @@
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+use cpu::AUDIO_PATTERN_LEN;
+use platform::Platform;
+use Chip8;
+use FRAME_RATE;
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+pub const DEFAULT_VOLUME: f32 = 0.25;
+// Beeps fade in and out over this long instead of starting and stopping on
+// a sample, which is what makes speakers click
+const FADE_SECONDS: f32 = 0.005;
+// XO-CHIP plays its 128 bit pattern at 4000 bits per second at pitch 64,
+// and every 48 steps of pitch double or halve that.
+const PATTERN_BITS: f32 = 128.0;
+const PATTERN_RATE: f32 = 4000.0;
+const PATTERN_PITCH: f32 = 64.0;
+const PITCH_PER_OCTAVE: f32 = 48.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+impl Waveform {
+    // The waveform's value at a point in its cycle, phase runs from 0 to 1
+    fn sample(&self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+        }
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Waveform, String> {
+        match s.to_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" | "saw" => Ok(Waveform::Sawtooth),
+            "sine" => Ok(Waveform::Sine),
+            _ => Err(format!("Unknown waveform: {}", s)),
+        }
+    }
+}
+
+// Turns the sound timer into mono PCM samples between -1 and 1. It only
+// reads the machine, so it works the same with or without an audio device.
+pub struct Beeper {
+    sample_rate: u32,
+    frequency: f32,
+    waveform: Waveform,
+    volume: f32,
+    muted: bool,
+    // Position in the current cycle of the tone, or in the XO-CHIP pattern
+    phase: f32,
+    // How loud the beep currently is, between 0 and 1
+    envelope: f32,
+    // XO-CHIP's pattern and pitch as of the last frame, None plays the tone
+    pattern: Option<([u8; AUDIO_PATTERN_LEN], u8)>,
+    // Samples owed to the next frame when frames don't hold a whole number
+    sample_remainder: u32,
+}
+
+impl Beeper {
+    pub fn new(sample_rate: u32) -> Beeper {
+        Beeper {
+            sample_rate: sample_rate.max(1),
+            frequency: DEFAULT_FREQUENCY,
+            waveform: Waveform::Square,
+            volume: DEFAULT_VOLUME,
+            muted: false,
+            phase: 0.0,
+            envelope: 0.0,
+            pattern: None,
+            sample_remainder: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.max(0.0);
+    }
+
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    // Renders the samples for the frame the machine just ran
+    pub fn render_frame(&mut self, emu: &Chip8, out: &mut Vec<f32>) {
+        let total = self.sample_rate + self.sample_remainder;
+        let count = total / FRAME_RATE;
+        self.sample_remainder = total % FRAME_RATE;
+
+        self.pattern = if emu.cpu.platform == Platform::XoChip {
+            Some((emu.cpu.audio_pattern, emu.cpu.pitch))
+        } else {
+            None
+        };
+        self.render(count, emu.cpu.regs.sound > 0, out);
+    }
+
+    // Lets a beep die away when the machine stops running frames, instead of
+    // cutting it off. Renders nothing once the beep is already silent.
+    pub fn render_fade_out(&mut self, out: &mut Vec<f32>) {
+        let count = (self.envelope / self.fade_step()).ceil() as u32;
+        self.render(count, false, out);
+        self.envelope = 0.0;
+    }
+
+    fn fade_step(&self) -> f32 {
+        1.0 / (FADE_SECONDS * self.sample_rate as f32)
+    }
+
+    fn render(&mut self, count: u32, sounding: bool, out: &mut Vec<f32>) {
+        let fade_step = self.fade_step();
+        let volume = if self.muted { 0.0 } else { self.volume };
+
+        for _ in 0..count {
+            self.envelope = if sounding {
+                (self.envelope + fade_step).min(1.0)
+            } else {
+                (self.envelope - fade_step).max(0.0)
+            };
+            let sample = match self.pattern {
+                Some((bits, pitch)) => self.pattern_sample(&bits, pitch),
+                None => self.tone_sample(),
+            };
+            out.push(sample * self.envelope * volume);
+        }
+    }
+
+    fn tone_sample(&mut self) -> f32 {
+        let sample = self.waveform.sample(self.phase);
+        self.phase = (self.phase + self.frequency / self.sample_rate as f32)
+            .fract();
+        sample
+    }
+
+    fn pattern_sample(&mut self, bits: &[u8], pitch: u8) -> f32 {
+        let bit = self.phase as usize;
+        let set = bits[bit / 8] & (0x80 >> (bit % 8)) != 0;
+        let rate = PATTERN_RATE
+            * 2f32.powf((pitch as f32 - PATTERN_PITCH) / PITCH_PER_OCTAVE);
+        self.phase =
+            (self.phase + rate / self.sample_rate as f32) % PATTERN_BITS;
+        if set {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
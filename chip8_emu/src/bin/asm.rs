@@ -0,0 +1,17 @@
+extern crate chip8;
+
+use chip8::assemble;
+use std::env;
+use std::error::Error;
+use std::fs;
+
+// Usage: asm <source> <rom>
+fn main() -> Result<(), Box<dyn Error>> {
+    let source_path = env::args().nth(1).ok_or("Did not get a source file")?;
+    let rom_path = env::args().nth(2).ok_or("Did not get an output path")?;
+    let source = fs::read_to_string(&source_path)?;
+    let rom =
+        assemble(&source).map_err(|err| format!("{}:{}", source_path, err))?;
+    fs::write(rom_path, rom)?;
+    Ok(())
+}
@@ -1,4 +1,6 @@
+use error::Fault;
 use nybble::Nybble;
+use nybble::TwoNybbles;
 use opcode::Opcode;
 use opcode::InvalidOpcode;
 use opcode::NoArg;
@@ -7,7 +9,13 @@ use opcode::TwoArg;
 use opcode::ThreeArg;
 use screen::Screen;
 use keyboard::Keyboard;
+use platform::Platform;
+use quirks::IndexIncrement;
+use quirks::Quirks;
+use rng::Rng;
+use std::cell::RefCell;
 use std::fmt;
+use std::mem;
 use num::ToPrimitive;
 
 const SPR_ZERO_START: u16 = 0000;
@@ -25,7 +33,7 @@ const SPR_B_START: u16 = 0055;
 const SPR_C_START: u16 = 0060;
 const SPR_D_START: u16 = 0065;
 const SPR_E_START: u16 = 0070;
-const SPR_F_START: u16 = 1075;
+const SPR_F_START: u16 = 0075;
 
 const SPR_ZERO: [u8; 5] = [0xF0, 0x90, 0x90, 0x90, 0xF0];
 const SPR_ONE: [u8; 5] = [0x20, 0x60, 0x20, 0x20, 0x70];
@@ -43,43 +51,209 @@ const SPR_C: [u8; 5] = [0xF0, 0x80, 0x80, 0x80, 0xF0];
 const SPR_D: [u8; 5] = [0xE0, 0x90, 0x90, 0x90, 0xE0];
 const SPR_E: [u8; 5] = [0xF0, 0x80, 0xF0, 0x80, 0xF0];
 const SPR_F: [u8; 5] = [0xF0, 0x80, 0xF0, 0x80, 0x80];
+// The small font in digit order
+pub const FONT: [[u8; 5]; 16] = [
+    SPR_ZERO, SPR_ONE, SPR_TWO, SPR_THREE, SPR_FOUR, SPR_FIVE, SPR_SIX,
+    SPR_SEVEN, SPR_EIGHT, SPR_NINE, SPR_A, SPR_B, SPR_C, SPR_D, SPR_E, SPR_F,
+];
+
+// SUPER-CHIP's 8x10 digits, stored right after the small font
+const SPR_BIG_START: u16 = 80;
+const SPR_BIG_LEN: u16 = 10;
+const SPR_BIG: [[u8; 10]; 16] = [
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF],
+    [0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+    [0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03],
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18],
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+    [0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3],
+    [0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC],
+    [0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C],
+    [0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC],
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0],
+];
+
+pub const RPL_FLAGS: usize = 16;
+pub const AUDIO_PATTERN_LEN: usize = 16;
+// XO-CHIP's default pitch, which plays the pattern buffer back at 4000 Hz
+const DEFAULT_PITCH: u8 = 64;
+// Where roms are loaded and execution starts, below it are the fonts
+const PROGRAM_START: usize = 0x200;
 
 const FLAG_REG: usize = 0xF;
+// Highest address reachable by a 12 bit index register
+const I_REG_LIMIT: u16 = 0xFFF;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Cpu {
     pub regs: Registers,
     pub ram: Ram,
     pub stack: Stack,
     pub screen: Screen,
     pub keyboard: Keyboard,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub rng: Rng,
+    // Set when a draw has to wait for the next vertical blank
+    pub vblank_wait: bool,
+    pub rpl_flags: [u8; RPL_FLAGS],
+    pub audio_pattern: [u8; AUDIO_PATTERN_LEN],
+    pub pitch: u8,
+    pub exited: bool,
+    // Timer ticks owed, in 1/FRAME_RATE of a tick, for timer rates that
+    // don't divide evenly into frames
+    pub timer_phase: u32,
 }
 
 impl Cpu {
-    pub fn new(bytes: &[u8]) -> Cpu {
-        Cpu {
+    pub fn new(
+        bytes: &[u8],
+        platform: Platform,
+        quirks: Quirks,
+        rng: Rng,
+    ) -> Result<Cpu, RomError> {
+        Ok(Cpu {
             regs: Registers::new(),
-            ram: Ram::initialize_ram(bytes),
+            ram: Ram::initialize_ram(bytes, platform.ram_size())?,
             stack: Stack::new(),
             keyboard: Keyboard::new(),
-            screen: Screen::new(),
-        }
+            screen: Screen::new(quirks.clipping),
+            platform,
+            quirks,
+            rng,
+            vblank_wait: false,
+            rpl_flags: [0; RPL_FLAGS],
+            audio_pattern: [0; AUDIO_PATTERN_LEN],
+            pitch: DEFAULT_PITCH,
+            exited: false,
+            timer_phase: 0,
+        })
     }
 
-    fn fetch_opcode(&self) -> u16 {
-        let l_byte: u8 = self.ram.0[self.regs.pc.get_addr() as usize];
-        let r_byte: u8 = self.ram.0[(self.regs.pc.get_addr() + 1) as usize];
+    pub fn fetch_opcode(&self) -> u16 {
+        self.fetch_word(self.regs.pc.get_addr())
+    }
+
+    fn fetch_word(&self, addr: u16) -> u16 {
+        let l_byte: u8 = self.ram.peek(addr as usize);
+        let r_byte: u8 = self.ram.peek(addr.wrapping_add(1) as usize);
         ((l_byte as u16) << 8) | (r_byte as u16)
     }
 
-    pub fn step(&mut self) -> Result<(), InvalidOpcode> {
-        self.execute(Opcode::decode_op(self.fetch_opcode())?)
+    // Reads the word at addr, failing if any of it lies past the end of memory
+    fn fetch_checked(&self, addr: u16) -> Result<u16, String> {
+        let last = addr as usize + 1;
+        if last >= self.ram.len() {
+            return Err(out_of_memory(last));
+        }
+        Ok(self.fetch_word(addr))
+    }
+
+    // The value 8XY6/8XYE shift, which is VY unless the shift quirk is set
+    fn shift_source(&self, arg: TwoNybbles) -> u8 {
+        let reg = if self.quirks.shift { arg.x() } else { arg.y() };
+        self.regs.v_regs[reg.to_usize().expect("Check usize")]
+    }
+
+    // Whether the key named by VX is held down
+    fn key_state(&self, x: Nybble) -> Result<bool, String> {
+        let key = self.regs.v_regs[x.to_usize().expect("Check usize")];
+        self.keyboard
+            .key_buffer
+            .get(key as usize)
+            .cloned()
+            .ok_or(format!("Key {:#X} does not exist", key))
+    }
+
+    fn increment_i_after_load_store(&mut self, x: Nybble) {
+        let x = x.to_u16().expect("Check u16");
+        match self.quirks.load_store {
+            IndexIncrement::Unchanged => {}
+            IndexIncrement::X => {
+                self.regs.i_reg = self.regs.i_reg.wrapping_add(x)
+            }
+            IndexIncrement::XPlusOne => {
+                self.regs.i_reg = self.regs.i_reg.wrapping_add(x + 1)
+            }
+        }
+    }
+
+    // Skips over the instruction following the current one, which on XO-CHIP
+    // may be a two word long I load.
+    fn skip_next(&mut self) {
+        let next = self.fetch_word(self.regs.pc.get_addr().wrapping_add(2));
+        let len = match Opcode::decode_op(next) {
+            Ok(ref op) if self.platform.supports(op) => op.byte_len(),
+            _ => 2,
+        };
+        self.regs.pc.advance(len);
+    }
+
+    pub fn step(&mut self) -> Result<(), Fault> {
+        let pc = self.regs.pc();
+        let result = self
+            .fetch_current()
+            .and_then(Opcode::decode_op)
+            .and_then(|op| self.run(op));
+        self.locate(pc, result)
+    }
+
+    // Moves past a faulting instruction as if it had done nothing
+    pub fn skip_fault(&mut self, fault: &Fault) {
+        let len = fault.op.as_ref().map_or(2, Opcode::byte_len);
+        self.regs.pc.set_addr(fault.pc.wrapping_add(len));
+    }
+
+    // Adds where an instruction failed to the reason it failed
+    fn locate(
+        &self,
+        pc: u16,
+        result: Result<(), InvalidOpcode>,
+    ) -> Result<(), Fault> {
+        result.map_err(|cause| Fault::new(pc, self.fetch_word(pc), cause))
+    }
+
+    fn fetch_current(&self) -> Result<u16, InvalidOpcode> {
+        let pc = self.regs.pc();
+        self.fetch_checked(pc)
+            .map_err(|err| InvalidOpcode::PcOutOfBounds(err, pc))
+    }
+
+    // Same as step, but reuses the instruction decoded the last time this
+    // address ran, unless memory there has been written since.
+    pub fn step_cached(&mut self) -> Result<(), Fault> {
+        let pc = self.regs.pc();
+        let op = match self.ram.decoded(pc) {
+            Some(op) => Ok(op),
+            None => self
+                .fetch_current()
+                .and_then(Opcode::decode_op)
+                .inspect(|op| self.ram.remember_decoded(pc, op.clone())),
+        };
+        let result = op.and_then(|op| self.run(op));
+        self.locate(pc, result)
+    }
+
+    fn run(&mut self, op: Opcode) -> Result<(), InvalidOpcode> {
+        match self.platform.resolve(&op) {
+            Some(op) => self.execute(op),
+            None => Err(InvalidOpcode::UnsupportedOnPlatform(
+                format!("Not available on {}", self.platform),
+                op,
+            )),
+        }
     }
 
     fn execute(&mut self, op: Opcode) -> Result<(), InvalidOpcode> {
         match op {
             Opcode::NoArg(NoArg::ClearScreen) => {
-                self.screen.buffer.iter_mut().for_each(|inner_array| {
-                    inner_array.iter_mut().for_each(|pixel| *pixel = false)
-                });
+                self.screen.clear();
                 self.regs.pc.update();
                 Ok(())
             }
@@ -93,27 +267,153 @@ impl Cpu {
                     Err(err) => Err(InvalidOpcode::StackUnderflow(err, op)),
                 }
             }
-
-            Opcode::OneArg(OneArg::SkipIfVx(arg)) => {
-                if self.keyboard.key_buffer[self.regs.v_regs
-                    [arg.to_usize().expect("Check usize")]
-                    as usize]
-                {
-                    self.regs.pc.update();
+            Opcode::NoArg(NoArg::ScrollRight) => {
+                self.screen.scroll_right();
+                self.regs.pc.update();
+                Ok(())
+            }
+            Opcode::NoArg(NoArg::ScrollLeft) => {
+                self.screen.scroll_left();
+                self.regs.pc.update();
+                Ok(())
+            }
+            Opcode::NoArg(NoArg::Exit) => {
+                self.exited = true;
+                Ok(())
+            }
+            Opcode::NoArg(NoArg::LowRes) => {
+                self.screen.set_hires(false);
+                self.regs.pc.update();
+                Ok(())
+            }
+            Opcode::NoArg(NoArg::HighRes) => {
+                self.screen.set_hires(true);
+                self.regs.pc.update();
+                Ok(())
+            }
+            Opcode::OneArg(OneArg::ScrollDown(arg)) => {
+                self.screen
+                    .scroll_down(arg.to_usize().expect("Check usize"));
+                self.regs.pc.update();
+                Ok(())
+            }
+            Opcode::OneArg(OneArg::SetBigSpriteI(arg)) => {
+                match i_eq_big_spr_digit_vx(
+                    self.regs.v_regs[arg.to_usize().expect("Check usize")],
+                    &mut self.regs.i_reg,
+                ) {
+                    Ok(_) => {
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::NoSuchDigitSprite(err, op)),
                 }
+            }
+            Opcode::OneArg(OneArg::StoreRplVx(arg)) => {
+                let count = arg.to_usize().expect("Check usize") + 1;
+                self.rpl_flags[..count]
+                    .copy_from_slice(&self.regs.v_regs[..count]);
                 self.regs.pc.update();
                 Ok(())
             }
-            Opcode::OneArg(OneArg::SkipIfNVx(arg)) => {
-                if !self.keyboard.key_buffer[self.regs.v_regs
-                    [arg.to_usize().expect("Check usize")]
-                    as usize]
+            Opcode::NoArg(NoArg::LoadILong) => {
+                let addr = self.regs.pc.get_addr().wrapping_add(2);
+                match self.fetch_checked(addr) {
+                    Ok(word) => {
+                        self.regs.i_reg = word;
+                        self.regs.pc.advance(op.byte_len());
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::OutOfBoundsAddress(err, op)),
+                }
+            }
+            Opcode::NoArg(NoArg::LoadAudio) => {
+                match self.ram.retrieve_range(self.regs.i_reg, AUDIO_PATTERN_LEN)
                 {
-                    self.regs.pc.update();
+                    Ok(pattern) => {
+                        self.audio_pattern.copy_from_slice(pattern);
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::OutOfBoundsAddress(err, op)),
+                }
+            }
+            Opcode::OneArg(OneArg::ScrollUp(arg)) => {
+                self.screen.scroll_up(arg.to_usize().expect("Check usize"));
+                self.regs.pc.update();
+                Ok(())
+            }
+            Opcode::OneArg(OneArg::SelectPlane(arg)) => {
+                self.screen.select_planes(arg.to_u8().expect("Check u8"));
+                self.regs.pc.update();
+                Ok(())
+            }
+            Opcode::OneArg(OneArg::SetPitch(arg)) => {
+                self.pitch =
+                    self.regs.v_regs[arg.to_usize().expect("Check usize")];
+                self.regs.pc.update();
+                Ok(())
+            }
+            Opcode::TwoArg(TwoArg::SaveVxVy(arg)) => {
+                let values: Vec<u8> = reg_range(arg.x(), arg.y())
+                    .map(|reg| self.regs.v_regs[reg])
+                    .collect();
+                match self.ram.write_range(self.regs.i_reg, &values) {
+                    Ok(_) => {
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::OutOfBoundsAddress(err, op)),
                 }
+            }
+            Opcode::TwoArg(TwoArg::LoadVxVy(arg)) => {
+                let regs = reg_range(arg.x(), arg.y());
+                let len = arg.x().to_usize().expect("Check usize").abs_diff(
+                    arg.y().to_usize().expect("Check usize"),
+                ) + 1;
+                match self.ram.retrieve_range(self.regs.i_reg, len) {
+                    Ok(values) => {
+                        for (reg, value) in regs.zip(values) {
+                            self.regs.v_regs[reg] = *value;
+                        }
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::OutOfBoundsAddress(err, op)),
+                }
+            }
+            Opcode::OneArg(OneArg::ReadRplVx(arg)) => {
+                let count = arg.to_usize().expect("Check usize") + 1;
+                self.regs.v_regs[..count]
+                    .copy_from_slice(&self.rpl_flags[..count]);
                 self.regs.pc.update();
                 Ok(())
             }
+
+            Opcode::OneArg(OneArg::SkipIfVx(arg)) => {
+                match self.key_state(arg) {
+                    Ok(pressed) => {
+                        if pressed {
+                            self.skip_next();
+                        }
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::NoSuchKey(err, op)),
+                }
+            }
+            Opcode::OneArg(OneArg::SkipIfNVx(arg)) => {
+                match self.key_state(arg) {
+                    Ok(pressed) => {
+                        if !pressed {
+                            self.skip_next();
+                        }
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::NoSuchKey(err, op)),
+                }
+            }
             Opcode::OneArg(OneArg::SetVxDT(arg)) => {
                 self.regs.v_regs[arg.to_usize().expect("Check usize")] =
                     self.regs.delay;
@@ -138,9 +438,14 @@ impl Cpu {
                 Ok(())
             }
             Opcode::OneArg(OneArg::SetI(arg)) => {
-                self.regs.i_reg += (self.regs.v_regs
-                    [arg.to_usize().expect("Check usize")])
-                    as u16;
+                self.regs.i_reg = self.regs.i_reg.wrapping_add(
+                    self.regs.v_regs[arg.to_usize().expect("Check usize")]
+                        as u16,
+                );
+                if self.quirks.i_overflow {
+                    self.regs.v_regs[FLAG_REG] =
+                        (self.regs.i_reg > I_REG_LIMIT) as u8;
+                }
                 self.regs.pc.update();
 
                 Ok(())
@@ -158,34 +463,45 @@ impl Cpu {
             Opcode::OneArg(OneArg::StoreDecVx(arg)) => {
                 let tmp =
                     self.regs.v_regs[arg.to_usize().expect("Check usize")];
-                self.ram.0[self.regs.i_reg as usize] = tmp / 100;
-                self.ram.0[(self.regs.i_reg + 1) as usize] = (tmp % 100) / 10;
-                self.ram.0[(self.regs.i_reg + 2) as usize] = tmp % 10;
-                self.regs.pc.update();
-                Ok(())
+                let digits = [tmp / 100, (tmp % 100) / 10, tmp % 10];
+                match self.ram.write_range(self.regs.i_reg, &digits) {
+                    Ok(_) => {
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::OutOfBoundsAddress(err, op)),
+                }
             }
             Opcode::OneArg(OneArg::StoreV0Vx(arg)) => {
-                for index in 0..=arg.to_u64().expect("Check to_u64") {
-                    self.ram.0[(self.regs.i_reg + index as u16) as usize] =
-                        self.regs.v_regs[index as usize]
+                let count = arg.to_usize().expect("Check usize") + 1;
+                let values = self.regs.v_regs;
+                match self.ram.write_range(self.regs.i_reg, &values[..count]) {
+                    Ok(_) => {
+                        self.increment_i_after_load_store(arg);
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::OutOfBoundsAddress(err, op)),
                 }
-                self.regs.pc.update();
-                Ok(())
             }
             Opcode::OneArg(OneArg::ReadV0Vx(arg)) => {
-                for index in 0..=arg.to_usize().expect("Check usize") {
-                    self.regs.v_regs[index as usize] =
-                        self.ram.0[(self.regs.i_reg + index as u16) as usize];
+                let count = arg.to_usize().expect("Check usize") + 1;
+                match self.ram.retrieve_range(self.regs.i_reg, count) {
+                    Ok(values) => {
+                        self.regs.v_regs[..count].copy_from_slice(values);
+                        self.increment_i_after_load_store(arg);
+                        self.regs.pc.update();
+                        Ok(())
+                    }
+                    Err(err) => Err(InvalidOpcode::OutOfBoundsAddress(err, op)),
                 }
-                self.regs.pc.update();
-                Ok(())
             }
             Opcode::TwoArg(TwoArg::SkipEqVxVy(arg)) => {
                 if self.regs.v_regs[arg.x().to_usize().expect("Check usize")]
                     == self.regs.v_regs
                         [arg.y().to_usize().expect("Check usize")]
                 {
-                    self.regs.pc.update();
+                    self.skip_next();
                 }
 
                 self.regs.pc.update();
@@ -200,18 +516,27 @@ impl Cpu {
             Opcode::TwoArg(TwoArg::VxOREqVy(arg)) => {
                 self.regs.v_regs[arg.x().to_usize().expect("Check usize")] |=
                     self.regs.v_regs[arg.y().to_usize().expect("Check usize")];
+                if self.quirks.vf_reset {
+                    self.regs.v_regs[FLAG_REG] = 0;
+                }
                 self.regs.pc.update();
                 Ok(())
             }
             Opcode::TwoArg(TwoArg::VxANDEqVy(arg)) => {
                 self.regs.v_regs[arg.x().to_usize().expect("Check usize")] &=
                     self.regs.v_regs[arg.y().to_usize().expect("Check usize")];
+                if self.quirks.vf_reset {
+                    self.regs.v_regs[FLAG_REG] = 0;
+                }
                 self.regs.pc.update();
                 Ok(())
             }
             Opcode::TwoArg(TwoArg::VxXOREqVy(arg)) => {
                 self.regs.v_regs[arg.x().to_usize().expect("Check usize")] ^=
                     self.regs.v_regs[arg.y().to_usize().expect("Check usize")];
+                if self.quirks.vf_reset {
+                    self.regs.v_regs[FLAG_REG] = 0;
+                }
                 self.regs.pc.update();
                 Ok(())
             }
@@ -238,14 +563,10 @@ impl Cpu {
                 Ok(())
             }
             Opcode::TwoArg(TwoArg::ShiftVxR(arg)) => {
-                self.regs.v_regs[FLAG_REG] = (0b00000001
-                    & self.regs.v_regs
-                        [arg.x().to_usize().expect("Check usize")]
-                    == 0b00000001)
-                    as u8;
+                let source = self.shift_source(arg);
                 self.regs.v_regs[arg.x().to_usize().expect("Check usize")] =
-                    self.regs.v_regs[arg.x().to_usize().expect("Check usize")]
-                        >> 1;
+                    source >> 1;
+                self.regs.v_regs[FLAG_REG] = source & 0b00000001;
                 self.regs.pc.update();
                 Ok(())
             }
@@ -261,13 +582,10 @@ impl Cpu {
                 Ok(())
             }
             Opcode::TwoArg(TwoArg::ShiftVxL(arg)) => {
-                self.regs.v_regs[FLAG_REG] = (0b10000000
-                    & self.regs.v_regs
-                        [arg.x().to_usize().expect("Check usize")])
-                    >> 7 as u8;
+                let source = self.shift_source(arg);
                 self.regs.v_regs[arg.x().to_usize().expect("Check usize")] =
-                    self.regs.v_regs[arg.x().to_usize().expect("Check usize")]
-                        << 1;
+                    source << 1;
+                self.regs.v_regs[FLAG_REG] = source >> 7;
                 self.regs.pc.update();
                 Ok(())
             }
@@ -276,7 +594,7 @@ impl Cpu {
                     != self.regs.v_regs
                         [arg.y().to_usize().expect("Check usize")]
                 {
-                    self.regs.pc.update();
+                    self.skip_next();
                 }
 
                 self.regs.pc.update();
@@ -303,7 +621,7 @@ impl Cpu {
                 if self.regs.v_regs[arg.x().to_usize().expect("Check usize")]
                     == arg.get_byte()
                 {
-                    self.regs.pc.update();
+                    self.skip_next();
                 }
 
                 self.regs.pc.update();
@@ -313,7 +631,7 @@ impl Cpu {
                 if self.regs.v_regs[arg.x().to_usize().expect("Check usize")]
                     != arg.get_byte()
                 {
-                    self.regs.pc.update();
+                    self.skip_next();
                 }
 
                 self.regs.pc.update();
@@ -340,44 +658,72 @@ impl Cpu {
                 Ok(())
             }
             Opcode::ThreeArg(ThreeArg::PCEqNNNPlusV0(arg)) => {
-                let sum =
-                    (self.regs.v_regs[0] as usize) + arg.to_addr() as usize;
-                if sum > 0xffe || sum < 0x200 {
-                    return Err(InvalidOpcode::OutOfBoundsAddress(
-                        "Out of bounds program counter".to_string(),
-                        op,
-                    ));
-                }
-                self.regs.pc.set_addr(sum as u16);
+                let offset_reg = if self.quirks.jump {
+                    arg.x().to_usize().expect("Check usize")
+                } else {
+                    0
+                };
+                // Like every other jump, a target past the end of memory
+                // faults when it is fetched
+                let sum = self.regs.v_regs[offset_reg] as u16 + arg.to_addr();
+                self.regs.pc.set_addr(sum);
                 Ok(())
             }
             Opcode::ThreeArg(ThreeArg::VxEqRandANDKK(arg)) => {
                 self.regs.v_regs[arg.x().to_usize().expect("Check usize")] =
-                    arg.get_byte() & rand::random::<u8>();
+                    arg.get_byte() & self.rng.next_byte(&self.ram);
                 self.regs.pc.update();
                 Ok(())
             }
             Opcode::ThreeArg(ThreeArg::DrawVxVyNib(arg)) => {
-                match self.screen.draw_nybble(
-                    self.regs.v_regs[arg.x().to_usize().expect("Check usize")],
-                    self.regs.v_regs[arg.y().to_usize().expect("Check usize")],
-                    self.regs.i_reg,
-                    Nybble::new([arg.last_nybble()]),
-                    &mut self.regs.v_regs[FLAG_REG],
-                    &mut self.ram,
-                ) {
+                let x =
+                    self.regs.v_regs[arg.x().to_usize().expect("Check usize")];
+                let y =
+                    self.regs.v_regs[arg.y().to_usize().expect("Check usize")];
+                let drawn = if arg.last_nybble() != 0
+                    || self.platform == Platform::Chip8
+                {
+                    self.screen.draw_nybble(
+                        x,
+                        y,
+                        self.regs.i_reg,
+                        Nybble::new([arg.last_nybble()]),
+                        &mut self.regs.v_regs[FLAG_REG],
+                        &self.ram,
+                    )
+                } else if self.screen.hires
+                    || self.platform == Platform::XoChip
+                {
+                    self.screen.draw_large(
+                        x,
+                        y,
+                        self.regs.i_reg,
+                        &mut self.regs.v_regs[FLAG_REG],
+                        &self.ram,
+                    )
+                } else {
+                    self.screen.draw_tall(
+                        x,
+                        y,
+                        self.regs.i_reg,
+                        &mut self.regs.v_regs[FLAG_REG],
+                        &self.ram,
+                    )
+                };
+                match drawn {
                     Ok(_) => {
+                        self.vblank_wait = self.quirks.display_wait;
                         self.regs.pc.update();
                         Ok(())
                     }
-                    Err(err) => Err(InvalidOpcode::OutOfScreenBounds(err, op)),
+                    Err(err) => Err(InvalidOpcode::OutOfBoundsAddress(err, op)),
                 }
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Registers {
     pc: ProgramCounter,
     pub delay: u8,
@@ -388,6 +734,18 @@ pub struct Registers {
 }
 
 impl Registers {
+    pub fn pc(&self) -> u16 {
+        self.pc.get_addr()
+    }
+
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
+
     fn new() -> Registers {
         let chip_8_adrr = 0x200;
         Registers {
@@ -401,63 +759,210 @@ impl Registers {
     }
 }
 
-#[derive(Clone)]
-pub struct Ram(Box<[u8]>);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub addr: u16,
+    pub access: Access,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Ram {
+    bytes: Box<[u8]>,
+    // Reads and writes made by instructions, only logged while a debugger is
+    // watching memory. Instruction fetches are never logged.
+    #[serde(skip)]
+    accesses: RefCell<Option<Vec<MemoryAccess>>>,
+    // Instructions decoded by Cpu::step_cached, by address. Empty until the
+    // first one is remembered, and writes drop any instruction they overlap.
+    #[serde(skip)]
+    decoded: Vec<Option<Opcode>>,
+}
 
 impl fmt::Debug for Ram {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut ram_string = "".to_string();
-        for index in 0..0xFFF {
+        for index in 0..self.bytes.len() {
             if index % 64 == 0 {
                 ram_string.push_str("\n");
             }
-            ram_string.push_str(&self.0[index].to_string());
+            ram_string.push_str(&self.bytes[index].to_string());
         }
         write!(f, "Ram Dump\n{}", ram_string)
     }
 }
 
 impl Ram {
-    pub fn initialize_ram(bytes: &[u8]) -> Ram {
+    pub fn initialize_ram(bytes: &[u8], size: usize) -> Result<Ram, RomError> {
+        let max = size.saturating_sub(PROGRAM_START);
+        if bytes.len() > max {
+            return Err(RomError::TooLarge {
+                size: bytes.len(),
+                max,
+            });
+        }
         let mut ram = Ram {
-            0: Box::new([0; 0xFFF]),
+            bytes: vec![0; size].into_boxed_slice(),
+            accesses: RefCell::new(None),
+            decoded: Vec::new(),
         };
         ram.load_digit_data();
-        ram.0[0x200..0x200 + bytes.len()].copy_from_slice(bytes);
-        ram
+        ram.bytes[PROGRAM_START..PROGRAM_START + bytes.len()]
+            .copy_from_slice(bytes);
+        Ok(ram)
     }
 
     fn load_digit_data(&mut self) {
-        self.0[0000..0005].copy_from_slice(&SPR_ZERO);
-        self.0[0005..0010].copy_from_slice(&SPR_ONE);
-        self.0[0010..0015].copy_from_slice(&SPR_TWO);
-        self.0[0015..0020].copy_from_slice(&SPR_THREE);
-        self.0[0020..0025].copy_from_slice(&SPR_FOUR);
-        self.0[0025..0030].copy_from_slice(&SPR_FIVE);
-        self.0[0030..0035].copy_from_slice(&SPR_SIX);
-        self.0[0035..0040].copy_from_slice(&SPR_SEVEN);
-        self.0[0040..0045].copy_from_slice(&SPR_EIGHT);
-        self.0[0045..0050].copy_from_slice(&SPR_NINE);
-        self.0[0050..0055].copy_from_slice(&SPR_A);
-        self.0[0055..0060].copy_from_slice(&SPR_B);
-        self.0[0060..0065].copy_from_slice(&SPR_C);
-        self.0[0065..0070].copy_from_slice(&SPR_D);
-        self.0[0070..0075].copy_from_slice(&SPR_E);
-        self.0[0075..0080].copy_from_slice(&SPR_F);
-    }
-
-    pub fn retrieve_bytes(&self, index: u16, amount: Nybble) -> &[u8] {
-        &self.0[index as usize
-            ..(index as usize) + amount.to_usize().expect("Can't fail")]
+        self.bytes[0000..0005].copy_from_slice(&SPR_ZERO);
+        self.bytes[0005..0010].copy_from_slice(&SPR_ONE);
+        self.bytes[0010..0015].copy_from_slice(&SPR_TWO);
+        self.bytes[0015..0020].copy_from_slice(&SPR_THREE);
+        self.bytes[0020..0025].copy_from_slice(&SPR_FOUR);
+        self.bytes[0025..0030].copy_from_slice(&SPR_FIVE);
+        self.bytes[0030..0035].copy_from_slice(&SPR_SIX);
+        self.bytes[0035..0040].copy_from_slice(&SPR_SEVEN);
+        self.bytes[0040..0045].copy_from_slice(&SPR_EIGHT);
+        self.bytes[0045..0050].copy_from_slice(&SPR_NINE);
+        self.bytes[0050..0055].copy_from_slice(&SPR_A);
+        self.bytes[0055..0060].copy_from_slice(&SPR_B);
+        self.bytes[0060..0065].copy_from_slice(&SPR_C);
+        self.bytes[0065..0070].copy_from_slice(&SPR_D);
+        self.bytes[0070..0075].copy_from_slice(&SPR_E);
+        self.bytes[0075..0080].copy_from_slice(&SPR_F);
+        for (digit, sprite) in SPR_BIG.iter().enumerate() {
+            let start = (SPR_BIG_START + digit as u16 * SPR_BIG_LEN) as usize;
+            self.bytes[start..start + sprite.len()].copy_from_slice(sprite);
+        }
+    }
+
+    pub fn retrieve_bytes(
+        &self,
+        index: u16,
+        amount: Nybble,
+    ) -> Result<&[u8], String> {
+        self.retrieve_range(index, amount.to_usize().expect("Can't fail"))
+    }
+
+    pub fn retrieve_range(
+        &self,
+        index: u16,
+        len: usize,
+    ) -> Result<&[u8], String> {
+        let range = index as usize..index as usize + len;
+        let bytes = self
+            .bytes
+            .get(range.clone())
+            .ok_or_else(|| out_of_memory(range.end - 1))?;
+        for addr in range {
+            self.log(addr, Access::Read);
+        }
+        Ok(bytes)
+    }
+
+    pub fn read(&self, addr: usize) -> Result<u8, String> {
+        let value = *self.bytes.get(addr).ok_or_else(|| out_of_memory(addr))?;
+        self.log(addr, Access::Read);
+        Ok(value)
+    }
+
+    // Writes all of the values or, if they don't fit, none of them
+    pub fn write_range(
+        &mut self,
+        index: u16,
+        values: &[u8],
+    ) -> Result<(), String> {
+        let start = index as usize;
+        if start + values.len() > self.bytes.len() {
+            return Err(out_of_memory(start + values.len() - 1));
+        }
+        for (offset, value) in values.iter().enumerate() {
+            self.write(start + offset, *value)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, addr: usize, value: u8) -> Result<(), String> {
+        if addr >= self.bytes.len() {
+            return Err(out_of_memory(addr));
+        }
+        self.log(addr, Access::Write);
+        self.bytes[addr] = value;
+        if !self.decoded.is_empty() {
+            // Both the instruction starting here and the one starting a byte
+            // earlier contain this byte
+            self.decoded[addr] = None;
+            if addr > 0 {
+                self.decoded[addr - 1] = None;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn decoded(&self, addr: u16) -> Option<Opcode> {
+        self.decoded.get(addr as usize).cloned().flatten()
+    }
+
+    pub fn remember_decoded(&mut self, addr: u16, op: Opcode) {
+        if self.decoded.is_empty() {
+            self.decoded = vec![None; self.bytes.len()];
+        }
+        if let Some(slot) = self.decoded.get_mut(addr as usize) {
+            *slot = Some(op);
+        }
+    }
+
+    // Reads a byte without it showing up as an access. Addresses past the end
+    // of memory read as 0.
+    pub fn peek(&self, addr: usize) -> u8 {
+        self.bytes.get(addr).cloned().unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn set_logging(&self, enabled: bool) {
+        *self.accesses.borrow_mut() =
+            if enabled { Some(Vec::new()) } else { None };
+    }
+
+    // Hands out the accesses logged so far and starts a fresh log
+    pub fn take_accesses(&self) -> Vec<MemoryAccess> {
+        self.accesses
+            .borrow_mut()
+            .as_mut()
+            .map(mem::take)
+            .unwrap_or_default()
+    }
+
+    fn log(&self, addr: usize, access: Access) {
+        if let Some(accesses) = self.accesses.borrow_mut().as_mut() {
+            accesses.push(MemoryAccess {
+                addr: addr as u16,
+                access,
+            });
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 struct ProgramCounter(u16);
 
 impl ProgramCounter {
     fn update(&mut self) {
-        self.0 += 2;
+        self.advance(2);
+    }
+    fn advance(&mut self, len: u16) {
+        self.0 = self.0.wrapping_add(len);
     }
     fn set_addr(&mut self, addr: u16) {
         self.0 = addr;
@@ -467,28 +972,38 @@ impl ProgramCounter {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Stack([u16; 16]);
+// Slot 0 is never used, leaving room for 15 return addresses
+pub const STACK_SLOTS: usize = 16;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Stack([u16; STACK_SLOTS]);
 
 impl Stack {
     fn new() -> Stack {
-        Stack { 0: [0; 16] }
+        Stack([0; STACK_SLOTS])
+    }
+
+    // The return addresses currently on the stack, oldest first
+    pub fn entries(&self, sp: u8) -> &[u16] {
+        &self.0[1..=(sp as usize).min(15)]
     }
     fn push(&mut self, sp: &mut u8, pc: &ProgramCounter) -> Result<(), String> {
-        *sp = *sp + 1;
-        if *sp > 15 {
+        if *sp as usize + 1 >= self.0.len() {
             return Err("Stack overflow".to_string());
         }
+        *sp += 1;
         self.0[*sp as usize] = pc.get_addr();
         Ok(())
     }
 
     fn pop(&self, sp: &mut u8) -> Result<ProgramCounter, String> {
-        let temp = ProgramCounter(self.0[*sp as usize]);
-        if *sp <= 0 {
+        if *sp == 0 {
             return Err("Stack pointer cannot go below 0".to_string());
         }
-        *sp = *sp - 1;
+        let temp = ProgramCounter(
+            *self.0.get(*sp as usize).ok_or("Stack pointer out of range")?,
+        );
+        *sp -= 1;
         Ok(temp)
     }
 }
@@ -514,3 +1029,47 @@ fn i_eq_spr_digit_vx(v_reg: u8, i_reg: &mut u16) -> Result<(), String> {
         _ => Err(format!("Value {} is not a valid digit sprite!", v_reg)),
     }
 }
+
+fn i_eq_big_spr_digit_vx(v_reg: u8, i_reg: &mut u16) -> Result<(), String> {
+    if v_reg as usize >= SPR_BIG.len() {
+        return Err(format!("Value {} is not a valid digit sprite!", v_reg));
+    }
+    *i_reg = SPR_BIG_START + v_reg as u16 * SPR_BIG_LEN;
+    Ok(())
+}
+
+// XO-CHIP's register range instructions work in either direction, saving or
+// loading vy first when y is below x.
+fn reg_range(x: Nybble, y: Nybble) -> Box<dyn Iterator<Item = usize>> {
+    let x = x.to_usize().expect("Check usize");
+    let y = y.to_usize().expect("Check usize");
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
+    }
+}
+
+fn out_of_memory(addr: usize) -> String {
+    format!("Address {:#X} is outside of memory", addr)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    // The rom doesn't fit in the memory above the fonts
+    TooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::TooLarge { size, max } => write!(
+                f,
+                "Rom is {} bytes, but at most {} bytes fit in memory",
+                size, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
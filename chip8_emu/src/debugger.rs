@@ -0,0 +1,407 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use cpu::Access;
+use cpu::MemoryAccess;
+use error::Fault;
+use opcode::Opcode;
+use opcode::ThreeArg;
+use Chip8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Delay,
+    Sound,
+    Sp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+// A test on a register's value, such as V3 == 0x10
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn new(
+        register: Register,
+        comparison: Comparison,
+        value: u16,
+    ) -> Condition {
+        Condition {
+            register,
+            comparison,
+            value,
+        }
+    }
+
+    pub fn holds(&self, emu: &Chip8) -> bool {
+        let regs = &emu.cpu.regs;
+        let current = match self.register {
+            Register::V(reg) => regs.v_regs[reg as usize & 0xF] as u16,
+            Register::I => regs.i_reg(),
+            Register::Delay => regs.delay as u16,
+            Register::Sound => regs.sound as u16,
+            Register::Sp => regs.sp() as u16,
+        };
+        match self.comparison {
+            Comparison::Equal => current == self.value,
+            Comparison::NotEqual => current != self.value,
+            Comparison::Less => current < self.value,
+            Comparison::LessOrEqual => current <= self.value,
+            Comparison::Greater => current > self.value,
+            Comparison::GreaterOrEqual => current >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let register = match self.register {
+            Register::V(reg) => format!("V{:X}", reg),
+            Register::I => "I".to_string(),
+            Register::Delay => "DT".to_string(),
+            Register::Sound => "ST".to_string(),
+            Register::Sp => "SP".to_string(),
+        };
+        let comparison = match self.comparison {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+        };
+        write!(f, "{} {} {:#X}", register, comparison, self.value)
+    }
+}
+
+// Parses conditions such as "V3 == 0x10", "DT<5" or "i >= 768"
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Condition, String> {
+        let split = s
+            .find(|c| "=!<>".contains(c))
+            .ok_or(format!("No comparison in condition: {}", s))?;
+        let (register, rest) = s.split_at(split);
+        let operator_len =
+            rest.find(|c| !"=!<>".contains(c)).unwrap_or(rest.len());
+        let (operator, value) = rest.split_at(operator_len);
+
+        let register = match register.trim().to_uppercase().as_str() {
+            "I" => Register::I,
+            "DT" => Register::Delay,
+            "ST" => Register::Sound,
+            "SP" => Register::Sp,
+            name if name.len() == 2 && name.starts_with('V') => {
+                let reg = u8::from_str_radix(&name[1..], 16)
+                    .map_err(|_| format!("Unknown register: {}", name))?;
+                Register::V(reg)
+            }
+            name => return Err(format!("Unknown register: {}", name)),
+        };
+        let comparison = match operator {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            _ => return Err(format!("Unknown comparison: {}", operator)),
+        };
+        let value = value.trim();
+        let value = if value.starts_with("0x") || value.starts_with("0X") {
+            u16::from_str_radix(&value[2..], 16)
+        } else {
+            value.parse::<u16>()
+        }
+        .map_err(|_| format!("Invalid value in condition: {}", value))?;
+        Ok(Condition::new(register, comparison, value))
+    }
+}
+
+// Stops the machine when an instruction touches any address in start..=end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub reads: bool,
+    pub writes: bool,
+}
+
+impl Watchpoint {
+    pub fn new(start: u16, end: u16, reads: bool, writes: bool) -> Watchpoint {
+        Watchpoint {
+            start,
+            end,
+            reads,
+            writes,
+        }
+    }
+
+    fn matches(&self, access: &MemoryAccess) -> bool {
+        let watched = match access.access {
+            Access::Read => self.reads,
+            Access::Write => self.writes,
+        };
+        watched && access.addr >= self.start && access.addr <= self.end
+    }
+}
+
+#[derive(Debug)]
+pub enum StopReason {
+    // A requested step finished
+    Step,
+    // About to execute the instruction at a breakpoint
+    Breakpoint(u16),
+    // The condition just became true
+    Condition(Condition),
+    // The instruction at pc made a watched access
+    Watchpoint { pc: u16, access: MemoryAccess },
+    Fault(Fault),
+    Exited,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Step => write!(f, "Step finished"),
+            StopReason::Breakpoint(addr) => {
+                write!(f, "Hit breakpoint at {:#05X}", addr)
+            }
+            StopReason::Condition(condition) => {
+                write!(f, "Condition {} became true", condition)
+            }
+            StopReason::Watchpoint { pc, access } => write!(
+                f,
+                "Instruction at {:#05X} {} {:#05X}",
+                pc,
+                match access.access {
+                    Access::Read => "read",
+                    Access::Write => "wrote",
+                },
+                access.addr
+            ),
+            StopReason::Fault(err) => write!(f, "Faulted: {}", err),
+            StopReason::Exited => write!(f, "Program exited"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Running,
+    Paused,
+    StepInto,
+    // Runs until the stack pointer drops to the given depth
+    UntilDepth(u8),
+}
+
+// Runs the machine on behalf of a front end, stopping it on breakpoints,
+// conditions, watchpoints and finished steps. A stopped debugger stays paused
+// until it is resumed or asked to step.
+pub struct Debugger {
+    mode: Mode,
+    breakpoints: BTreeMap<u16, Option<Condition>>,
+    // Each condition remembers whether it held the last time it was checked,
+    // so it only stops the machine when it becomes true.
+    conditions: Vec<(Condition, bool)>,
+    watchpoints: Vec<Watchpoint>,
+    // The breakpoint we stopped on, ignored once so we can move past it
+    resume_from: Option<u16>,
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            mode: Mode::Running,
+            breakpoints: BTreeMap::new(),
+            conditions: Vec::new(),
+            watchpoints: Vec::new(),
+            resume_from: None,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.mode == Mode::Paused
+    }
+
+    pub fn pause(&mut self) {
+        self.mode = Mode::Paused;
+    }
+
+    pub fn resume(&mut self) {
+        self.mode = Mode::Running;
+    }
+
+    pub fn step_into(&mut self) {
+        self.mode = Mode::StepInto;
+    }
+
+    // Steps over subroutine calls as if they were a single instruction
+    pub fn step_over(&mut self, emu: &Chip8) {
+        let op = Opcode::decode_op(emu.cpu.fetch_opcode());
+        self.mode = if let Ok(Opcode::ThreeArg(ThreeArg::CallSubAt(_))) = op {
+            Mode::UntilDepth(emu.cpu.regs.sp())
+        } else {
+            Mode::StepInto
+        };
+    }
+
+    // Runs until the current subroutine returns. Outside of any subroutine
+    // there is nothing to return from, so this just resumes.
+    pub fn step_out(&mut self, emu: &Chip8) {
+        self.mode = match emu.cpu.regs.sp() {
+            0 => Mode::Running,
+            sp => Mode::UntilDepth(sp - 1),
+        };
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr, None);
+    }
+
+    // Only stops at addr while the condition holds
+    pub fn add_conditional_breakpoint(
+        &mut self,
+        addr: u16,
+        condition: Condition,
+    ) {
+        self.breakpoints.insert(addr, Some(condition));
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr).is_some()
+    }
+
+    // Stops wherever the machine is as soon as the condition becomes true
+    pub fn break_when(&mut self, condition: Condition) {
+        self.conditions.push((condition, false));
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.conditions.clear();
+        self.watchpoints.clear();
+        self.resume_from = None;
+    }
+
+    // Starts a new frame and runs its cycles, unless the debugger is paused
+    pub fn run_frame(
+        &mut self,
+        emu: &mut Chip8,
+        cycles: u32,
+    ) -> Option<StopReason> {
+        if self.is_paused() {
+            return None;
+        }
+        emu.vblank();
+        self.run(emu, cycles)
+    }
+
+    // Runs up to `cycles` cycles. Returns why the machine stopped, or None if
+    // it is still running once the cycles are used up or it blocks on a key or
+    // the next frame. A single step that blocks stops with StopReason::Step.
+    pub fn run(&mut self, emu: &mut Chip8, cycles: u32) -> Option<StopReason> {
+        if self.is_paused() {
+            return None;
+        }
+        emu.cpu.ram.set_logging(!self.watchpoints.is_empty());
+        let stopped = self.run_cycles(emu, cycles);
+        emu.cpu.ram.set_logging(false);
+        if stopped.is_some() {
+            self.mode = Mode::Paused;
+        }
+        stopped
+    }
+
+    fn run_cycles(
+        &mut self,
+        emu: &mut Chip8,
+        cycles: u32,
+    ) -> Option<StopReason> {
+        for _ in 0..cycles {
+            if emu.cpu.exited {
+                return Some(StopReason::Exited);
+            }
+            if emu.is_blocked() {
+                // Nothing runs until the key or frame arrives, so a single
+                // step ends here rather than waiting for it
+                return match self.mode {
+                    Mode::StepInto => Some(StopReason::Step),
+                    _ => None,
+                };
+            }
+            let pc = emu.cpu.regs.pc();
+            if let Some(reason) = self.check_before(emu, pc) {
+                self.resume_from = Some(pc);
+                return Some(reason);
+            }
+            self.resume_from = None;
+
+            if let Err(err) = emu.run_cycle() {
+                return Some(StopReason::Fault(err));
+            }
+            let accesses = emu.cpu.ram.take_accesses();
+            if let Some(access) = accesses.into_iter().find(|access| {
+                self.watchpoints.iter().any(|watch| watch.matches(access))
+            }) {
+                return Some(StopReason::Watchpoint { pc, access });
+            }
+            match self.mode {
+                Mode::StepInto => return Some(StopReason::Step),
+                Mode::UntilDepth(depth) if emu.cpu.regs.sp() <= depth => {
+                    return Some(StopReason::Step)
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn check_before(&mut self, emu: &Chip8, pc: u16) -> Option<StopReason> {
+        let mut reason = None;
+        for (condition, held) in self.conditions.iter_mut() {
+            let holds = condition.holds(emu);
+            if holds && !*held && reason.is_none() {
+                reason = Some(StopReason::Condition(*condition));
+            }
+            *held = holds;
+        }
+        if reason.is_some() || self.resume_from == Some(pc) {
+            return reason;
+        }
+        match self.breakpoints.get(&pc) {
+            Some(None) => Some(StopReason::Breakpoint(pc)),
+            Some(Some(condition)) if condition.holds(emu) => {
+                Some(StopReason::Breakpoint(pc))
+            }
+            _ => None,
+        }
+    }
+}
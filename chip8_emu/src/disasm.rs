@@ -0,0 +1,508 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use num::ToPrimitive;
+
+use nybble::Nybble;
+use nybble::TwoNybbles;
+use opcode::NoArg;
+use opcode::OneArg;
+use opcode::Opcode;
+use opcode::ThreeArg;
+use opcode::TwoArg;
+use cpu::RomError;
+use platform::Platform;
+
+const ROM_START: u16 = 0x200;
+// Data bytes are listed this many to a line
+const BYTES_PER_LINE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    // The mnemonics from Cowgod's Chip-8 technical reference
+    Cowgod,
+    // Octo assembly, which Octo can assemble back into the same rom
+    Octo,
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Syntax, String> {
+        match s.to_lowercase().as_str() {
+            "cowgod" => Ok(Syntax::Cowgod),
+            "octo" => Ok(Syntax::Octo),
+            _ => Err(format!("Unknown syntax: {}", s)),
+        }
+    }
+}
+
+// A rom split into code and data. Code is everything reachable from 0x200 by
+// following jumps, calls and skips, everything else is taken to be data.
+pub struct Disassembly {
+    rom: Vec<u8>,
+    instructions: BTreeMap<u16, Opcode>,
+    labels: BTreeMap<u16, String>,
+}
+
+// Roms that don't fit in the platform's memory are refused, like the
+// emulator does
+pub fn disassemble(
+    rom: &[u8],
+    platform: Platform,
+) -> Result<Disassembly, RomError> {
+    let max = platform.ram_size().saturating_sub(ROM_START as usize);
+    if rom.len() > max {
+        return Err(RomError::TooLarge {
+            size: rom.len(),
+            max,
+        });
+    }
+    let mut instructions = BTreeMap::new();
+    let mut jumps = BTreeSet::new();
+    let mut calls = BTreeSet::new();
+    let mut pending = vec![ROM_START];
+
+    while let Some(addr) = pending.pop() {
+        if instructions.contains_key(&addr) {
+            continue;
+        }
+        let op = match decode_at(rom, addr, platform) {
+            Some(op) => op,
+            None => continue,
+        };
+        let next = addr.wrapping_add(op.byte_len());
+        match op {
+            Opcode::ThreeArg(ThreeArg::JumpToAddr(arg)) => {
+                jumps.insert(arg.to_addr());
+                pending.push(arg.to_addr());
+            }
+            Opcode::ThreeArg(ThreeArg::CallSubAt(arg)) => {
+                calls.insert(arg.to_addr());
+                pending.push(arg.to_addr());
+                pending.push(next);
+            }
+            // Only the start of a jump table is known without running it
+            Opcode::ThreeArg(ThreeArg::PCEqNNNPlusV0(arg)) => {
+                jumps.insert(arg.to_addr());
+                pending.push(arg.to_addr());
+            }
+            Opcode::NoArg(NoArg::ReturnSubrt) | Opcode::NoArg(NoArg::Exit) => {}
+            ref op if is_skip(op) => {
+                let skipped = decode_at(rom, next, platform)
+                    .map_or(2, |skipped| skipped.byte_len());
+                pending.push(next);
+                pending.push(next.wrapping_add(skipped));
+            }
+            _ => pending.push(next),
+        }
+        instructions.insert(addr, op);
+    }
+
+    // A target in the middle of an instruction can't be labelled. Addresses
+    // are widened, as the last instruction of a full 64 KiB can end past
+    // 0xFFFF.
+    let covered: BTreeSet<usize> = instructions
+        .iter()
+        .flat_map(|(&addr, op)| {
+            addr as usize + 1..addr as usize + op.byte_len() as usize
+        })
+        .collect();
+    let in_rom = |addr: &u16| {
+        *addr >= ROM_START
+            && ((addr - ROM_START) as usize) < rom.len()
+            && !covered.contains(&(*addr as usize))
+    };
+    let mut labels = BTreeMap::new();
+    for addr in jumps.iter().filter(|addr| in_rom(addr)) {
+        labels.insert(*addr, format!("label_{:03X}", addr));
+    }
+    for addr in calls.iter().filter(|addr| in_rom(addr)) {
+        labels.insert(*addr, format!("sub_{:03X}", addr));
+    }
+    // Octo starts executing programs at main
+    labels.insert(ROM_START, "main".to_string());
+
+    Ok(Disassembly {
+        rom: rom.to_vec(),
+        instructions,
+        labels,
+    })
+}
+
+// Guesses the platform a rom was written for from the instructions it uses,
+// picking the oldest one that has all of them. Roms too big for 4 KiB of
+// memory can only be XO-CHIP.
+pub fn detect_platform(rom: &[u8]) -> Platform {
+    if ROM_START as usize + rom.len() > Platform::SuperChip.ram_size() {
+        return Platform::XoChip;
+    }
+    let code = disassemble(rom, Platform::XoChip)
+        .expect("Roms that fit SUPER-CHIP fit XO-CHIP");
+    [Platform::Chip8, Platform::SuperChip]
+        .iter()
+        .find(|platform| {
+            code.instructions.values().all(|op| platform.supports(op))
+        })
+        .cloned()
+        .unwrap_or(Platform::XoChip)
+}
+
+impl Disassembly {
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.instructions.contains_key(&addr)
+    }
+
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(|label| label.as_str())
+    }
+
+    // One past the last address of the rom, which is 0x10000 for a rom that
+    // fills XO-CHIP's memory
+    fn end(&self) -> usize {
+        ROM_START as usize + self.rom.len()
+    }
+
+    pub fn render(&self, syntax: Syntax) -> String {
+        let mut out = String::new();
+        let mut next = ROM_START as usize;
+        while next < self.end() {
+            let addr = next as u16;
+            if let Some(label) = self.label(addr) {
+                match syntax {
+                    Syntax::Cowgod => writeln!(out, "{}:", label),
+                    Syntax::Octo => writeln!(out, ": {}", label),
+                }
+                .expect("Writing to a string can't fail");
+            }
+            let len = match self.instructions.get(&addr) {
+                Some(op) => {
+                    let line = self.render_op(addr, op, syntax);
+                    match syntax {
+                        Syntax::Cowgod => writeln!(
+                            out,
+                            "    {:03X}: {:04X}  {}",
+                            addr,
+                            self.word_at(addr),
+                            line
+                        ),
+                        Syntax::Octo => writeln!(out, "    {}", line),
+                    }
+                    .expect("Writing to a string can't fail");
+                    op.byte_len() as usize
+                }
+                None => self.render_data(&mut out, addr, syntax),
+            };
+            next += len;
+        }
+        out
+    }
+
+    // Lists the data starting at addr up to the next instruction or label
+    fn render_data(
+        &self,
+        out: &mut String,
+        addr: u16,
+        syntax: Syntax,
+    ) -> usize {
+        let mut len = 1;
+        while addr as usize + len < self.end()
+            && len < BYTES_PER_LINE
+            && !self.is_code(addr + len as u16)
+            && self.label(addr + len as u16).is_none()
+        {
+            len += 1;
+        }
+        let start = (addr - ROM_START) as usize;
+        let bytes = &self.rom[start..start + len];
+        let line = match syntax {
+            Syntax::Cowgod => {
+                let bytes: Vec<String> =
+                    bytes.iter().map(|byte| format!("#{:02X}", byte)).collect();
+                format!("    {:03X}:        DB {}", addr, bytes.join(", "))
+            }
+            Syntax::Octo => {
+                let bytes: Vec<String> = bytes
+                    .iter()
+                    .map(|byte| format!("0x{:02X}", byte))
+                    .collect();
+                format!("    {}", bytes.join(" "))
+            }
+        };
+        writeln!(out, "{}", line).expect("Writing to a string can't fail");
+        len
+    }
+
+    fn word_at(&self, addr: u16) -> u16 {
+        word_at(&self.rom, addr).unwrap_or(0)
+    }
+
+    fn target(&self, addr: u16, syntax: Syntax) -> String {
+        match (self.label(addr), syntax) {
+            (Some(label), _) => label.to_string(),
+            (None, Syntax::Cowgod) => format!("#{:03X}", addr),
+            (None, Syntax::Octo) => format!("0x{:03X}", addr),
+        }
+    }
+
+    fn render_op(&self, addr: u16, op: &Opcode, syntax: Syntax) -> String {
+        match syntax {
+            Syntax::Cowgod => self.cowgod(addr, op),
+            Syntax::Octo => self.octo(addr, op),
+        }
+    }
+
+    fn cowgod(&self, addr: u16, op: &Opcode) -> String {
+        match op {
+            Opcode::NoArg(arg) => match arg {
+                NoArg::ClearScreen => "CLS".to_string(),
+                NoArg::ReturnSubrt => "RET".to_string(),
+                NoArg::ScrollRight => "SCR".to_string(),
+                NoArg::ScrollLeft => "SCL".to_string(),
+                NoArg::Exit => "EXIT".to_string(),
+                NoArg::LowRes => "LOW".to_string(),
+                NoArg::HighRes => "HIGH".to_string(),
+                NoArg::LoadILong => {
+                    format!("LD I, long #{:04X}", self.word_at(addr + 2))
+                }
+                NoArg::LoadAudio => "AUDIO".to_string(),
+            },
+            Opcode::OneArg(arg) => match arg {
+                OneArg::SkipIfVx(x) => format!("SKP V{:X}", reg(x)),
+                OneArg::SkipIfNVx(x) => format!("SKNP V{:X}", reg(x)),
+                OneArg::SetVxDT(x) => format!("LD V{:X}, DT", reg(x)),
+                OneArg::WaitForKey(x) => format!("LD V{:X}, K", reg(x)),
+                OneArg::SetDT(x) => format!("LD DT, V{:X}", reg(x)),
+                OneArg::SetST(x) => format!("LD ST, V{:X}", reg(x)),
+                OneArg::SetI(x) => format!("ADD I, V{:X}", reg(x)),
+                OneArg::SetSpriteI(x) => format!("LD F, V{:X}", reg(x)),
+                OneArg::StoreDecVx(x) => format!("LD B, V{:X}", reg(x)),
+                OneArg::StoreV0Vx(x) => format!("LD [I], V{:X}", reg(x)),
+                OneArg::ReadV0Vx(x) => format!("LD V{:X}, [I]", reg(x)),
+                OneArg::ScrollDown(n) => format!("SCD {}", reg(n)),
+                OneArg::SetBigSpriteI(x) => format!("LD HF, V{:X}", reg(x)),
+                OneArg::StoreRplVx(x) => format!("LD R, V{:X}", reg(x)),
+                OneArg::ReadRplVx(x) => format!("LD V{:X}, R", reg(x)),
+                OneArg::ScrollUp(n) => format!("SCU {}", reg(n)),
+                OneArg::SelectPlane(n) => format!("PLANE {}", reg(n)),
+                OneArg::SetPitch(x) => format!("PITCH V{:X}", reg(x)),
+            },
+            Opcode::TwoArg(arg) => {
+                let (name, xy) = match arg {
+                    TwoArg::SkipEqVxVy(xy) => ("SE", xy),
+                    TwoArg::VxEqVy(xy) => ("LD", xy),
+                    TwoArg::VxOREqVy(xy) => ("OR", xy),
+                    TwoArg::VxANDEqVy(xy) => ("AND", xy),
+                    TwoArg::VxXOREqVy(xy) => ("XOR", xy),
+                    TwoArg::VxPlusEqVySetF(xy) => ("ADD", xy),
+                    TwoArg::VxSubEqVySetF(xy) => ("SUB", xy),
+                    TwoArg::ShiftVxR(xy) => ("SHR", xy),
+                    TwoArg::VxEqVySubVxSetF(xy) => ("SUBN", xy),
+                    TwoArg::ShiftVxL(xy) => ("SHL", xy),
+                    TwoArg::SkipVxNEqVy(xy) => ("SNE", xy),
+                    TwoArg::SaveVxVy(xy) => {
+                        return format!("SAVE V{:X} - V{:X}", x(xy), y(xy))
+                    }
+                    TwoArg::LoadVxVy(xy) => {
+                        return format!("LOAD V{:X} - V{:X}", x(xy), y(xy))
+                    }
+                };
+                format!("{} V{:X}, V{:X}", name, x(xy), y(xy))
+            }
+            Opcode::ThreeArg(arg) => match arg {
+                ThreeArg::JumpToCodeRout(nnn) => {
+                    format!("SYS #{:03X}", nnn.to_addr())
+                }
+                ThreeArg::JumpToAddr(nnn) => {
+                    format!("JP {}", self.target(nnn.to_addr(), Syntax::Cowgod))
+                }
+                ThreeArg::CallSubAt(nnn) => format!(
+                    "CALL {}",
+                    self.target(nnn.to_addr(), Syntax::Cowgod)
+                ),
+                ThreeArg::SkipVxEqKK(xkk) => {
+                    format!("SE V{:X}, #{:02X}", reg(&xkk.x()), xkk.get_byte())
+                }
+                ThreeArg::SkipVxNEqKK(xkk) => {
+                    format!("SNE V{:X}, #{:02X}", reg(&xkk.x()), xkk.get_byte())
+                }
+                ThreeArg::SetVxKK(xkk) => {
+                    format!("LD V{:X}, #{:02X}", reg(&xkk.x()), xkk.get_byte())
+                }
+                ThreeArg::VxEqVxPlusKK(xkk) => {
+                    format!("ADD V{:X}, #{:02X}", reg(&xkk.x()), xkk.get_byte())
+                }
+                ThreeArg::SetIToNNN(nnn) => {
+                    format!("LD I, #{:03X}", nnn.to_addr())
+                }
+                ThreeArg::PCEqNNNPlusV0(nnn) => format!(
+                    "JP V0, {}",
+                    self.target(nnn.to_addr(), Syntax::Cowgod)
+                ),
+                ThreeArg::VxEqRandANDKK(xkk) => {
+                    format!("RND V{:X}, #{:02X}", reg(&xkk.x()), xkk.get_byte())
+                }
+                ThreeArg::DrawVxVyNib(xyn) => format!(
+                    "DRW V{:X}, V{:X}, {}",
+                    reg(&xyn.x()),
+                    reg(&xyn.y()),
+                    xyn.last_nybble()
+                ),
+            },
+        }
+    }
+
+    fn octo(&self, addr: u16, op: &Opcode) -> String {
+        match op {
+            Opcode::NoArg(arg) => match arg {
+                NoArg::ClearScreen => "clear".to_string(),
+                NoArg::ReturnSubrt => "return".to_string(),
+                NoArg::ScrollRight => "scroll-right".to_string(),
+                NoArg::ScrollLeft => "scroll-left".to_string(),
+                NoArg::Exit => "exit".to_string(),
+                NoArg::LowRes => "lores".to_string(),
+                NoArg::HighRes => "hires".to_string(),
+                NoArg::LoadILong => {
+                    format!("i := long 0x{:04X}", self.word_at(addr + 2))
+                }
+                NoArg::LoadAudio => "audio".to_string(),
+            },
+            Opcode::OneArg(arg) => match arg {
+                // Octo's conditions say when the next instruction runs,
+                // which is the opposite of when it is skipped
+                OneArg::SkipIfVx(x) => format!("if v{:x} -key then", reg(x)),
+                OneArg::SkipIfNVx(x) => format!("if v{:x} key then", reg(x)),
+                OneArg::SetVxDT(x) => format!("v{:x} := delay", reg(x)),
+                OneArg::WaitForKey(x) => format!("v{:x} := key", reg(x)),
+                OneArg::SetDT(x) => format!("delay := v{:x}", reg(x)),
+                OneArg::SetST(x) => format!("buzzer := v{:x}", reg(x)),
+                OneArg::SetI(x) => format!("i += v{:x}", reg(x)),
+                OneArg::SetSpriteI(x) => format!("i := hex v{:x}", reg(x)),
+                OneArg::StoreDecVx(x) => format!("bcd v{:x}", reg(x)),
+                OneArg::StoreV0Vx(x) => format!("save v{:x}", reg(x)),
+                OneArg::ReadV0Vx(x) => format!("load v{:x}", reg(x)),
+                OneArg::ScrollDown(n) => format!("scroll-down {}", reg(n)),
+                OneArg::SetBigSpriteI(x) => {
+                    format!("i := bighex v{:x}", reg(x))
+                }
+                OneArg::StoreRplVx(x) => format!("saveflags v{:x}", reg(x)),
+                OneArg::ReadRplVx(x) => format!("loadflags v{:x}", reg(x)),
+                OneArg::ScrollUp(n) => format!("scroll-up {}", reg(n)),
+                OneArg::SelectPlane(n) => format!("plane {}", reg(n)),
+                OneArg::SetPitch(x) => format!("pitch := v{:x}", reg(x)),
+            },
+            Opcode::TwoArg(arg) => {
+                let (format, xy) = match arg {
+                    TwoArg::SkipEqVxVy(xy) => ("if {} != {} then", xy),
+                    TwoArg::VxEqVy(xy) => ("{} := {}", xy),
+                    TwoArg::VxOREqVy(xy) => ("{} |= {}", xy),
+                    TwoArg::VxANDEqVy(xy) => ("{} &= {}", xy),
+                    TwoArg::VxXOREqVy(xy) => ("{} ^= {}", xy),
+                    TwoArg::VxPlusEqVySetF(xy) => ("{} += {}", xy),
+                    TwoArg::VxSubEqVySetF(xy) => ("{} -= {}", xy),
+                    TwoArg::ShiftVxR(xy) => ("{} >>= {}", xy),
+                    TwoArg::VxEqVySubVxSetF(xy) => ("{} =- {}", xy),
+                    TwoArg::ShiftVxL(xy) => ("{} <<= {}", xy),
+                    TwoArg::SkipVxNEqVy(xy) => ("if {} == {} then", xy),
+                    TwoArg::SaveVxVy(xy) => ("save {} - {}", xy),
+                    TwoArg::LoadVxVy(xy) => ("load {} - {}", xy),
+                };
+                format.replacen("{}", &format!("v{:x}", x(xy)), 1).replacen(
+                    "{}",
+                    &format!("v{:x}", y(xy)),
+                    1,
+                )
+            }
+            Opcode::ThreeArg(arg) => match arg {
+                // Octo has no syntax for machine code calls
+                ThreeArg::JumpToCodeRout(_) => {
+                    let word = self.word_at(addr);
+                    format!("0x{:02X} 0x{:02X}", word >> 8, word & 0xFF)
+                }
+                ThreeArg::JumpToAddr(nnn) => {
+                    format!("jump {}", self.target(nnn.to_addr(), Syntax::Octo))
+                }
+                ThreeArg::CallSubAt(nnn) => match self.label(nnn.to_addr()) {
+                    Some(label) => label.to_string(),
+                    None => format!(":call 0x{:03X}", nnn.to_addr()),
+                },
+                ThreeArg::SkipVxEqKK(xkk) => format!(
+                    "if v{:x} != 0x{:02X} then",
+                    reg(&xkk.x()),
+                    xkk.get_byte()
+                ),
+                ThreeArg::SkipVxNEqKK(xkk) => format!(
+                    "if v{:x} == 0x{:02X} then",
+                    reg(&xkk.x()),
+                    xkk.get_byte()
+                ),
+                ThreeArg::SetVxKK(xkk) => {
+                    format!("v{:x} := 0x{:02X}", reg(&xkk.x()), xkk.get_byte())
+                }
+                ThreeArg::VxEqVxPlusKK(xkk) => {
+                    format!("v{:x} += 0x{:02X}", reg(&xkk.x()), xkk.get_byte())
+                }
+                ThreeArg::SetIToNNN(nnn) => {
+                    format!("i := 0x{:03X}", nnn.to_addr())
+                }
+                ThreeArg::PCEqNNNPlusV0(nnn) => format!(
+                    "jump0 {}",
+                    self.target(nnn.to_addr(), Syntax::Octo)
+                ),
+                ThreeArg::VxEqRandANDKK(xkk) => format!(
+                    "v{:x} := random 0x{:02X}",
+                    reg(&xkk.x()),
+                    xkk.get_byte()
+                ),
+                ThreeArg::DrawVxVyNib(xyn) => format!(
+                    "sprite v{:x} v{:x} {}",
+                    reg(&xyn.x()),
+                    reg(&xyn.y()),
+                    xyn.last_nybble()
+                ),
+            },
+        }
+    }
+}
+
+fn word_at(rom: &[u8], addr: u16) -> Option<u16> {
+    let index = addr.checked_sub(ROM_START)? as usize;
+    let l_byte = *rom.get(index)?;
+    let r_byte = *rom.get(index + 1)?;
+    Some(((l_byte as u16) << 8) | (r_byte as u16))
+}
+
+// The instruction at addr, if the rom holds a whole valid one there
+fn decode_at(rom: &[u8], addr: u16, platform: Platform) -> Option<Opcode> {
+    let op = Opcode::decode_op(word_at(rom, addr)?).ok()?;
+    let op = platform.resolve(&op)?;
+    let last_word = addr.wrapping_add(op.byte_len() - 2);
+    word_at(rom, last_word)?;
+    Some(op)
+}
+
+fn is_skip(op: &Opcode) -> bool {
+    matches!(
+        op,
+        Opcode::OneArg(OneArg::SkipIfVx(_))
+            | Opcode::OneArg(OneArg::SkipIfNVx(_))
+            | Opcode::TwoArg(TwoArg::SkipEqVxVy(_))
+            | Opcode::TwoArg(TwoArg::SkipVxNEqVy(_))
+            | Opcode::ThreeArg(ThreeArg::SkipVxEqKK(_))
+            | Opcode::ThreeArg(ThreeArg::SkipVxNEqKK(_))
+    )
+}
+
+fn reg(nybble: &Nybble) -> usize {
+    nybble.to_usize().expect("Check usize")
+}
+
+fn x(xy: &TwoNybbles) -> usize {
+    reg(&xy.x())
+}
+
+fn y(xy: &TwoNybbles) -> usize {
+    reg(&xy.y())
+}
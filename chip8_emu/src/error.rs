@@ -0,0 +1,147 @@
+use std::error::Error;
+use std::fmt;
+
+use cpu::RomError;
+use fault::FaultClass;
+use opcode::InvalidOpcode;
+use opcode::Opcode;
+use state::StateError;
+
+// An instruction that couldn't be decoded or run, and where it was
+#[derive(Debug, Clone)]
+pub struct Fault {
+    pub pc: u16,
+    // The word at pc, the first half of the instruction
+    pub word: u16,
+    // None when the word never made it to an instruction
+    pub op: Option<Opcode>,
+    pub cause: InvalidOpcode,
+}
+
+impl Fault {
+    pub fn new(pc: u16, word: u16, cause: InvalidOpcode) -> Fault {
+        Fault {
+            pc,
+            word,
+            op: cause.op().cloned(),
+            cause,
+        }
+    }
+
+    pub fn class(&self) -> FaultClass {
+        FaultClass::of(&self.cause)
+    }
+
+    // True when the word isn't an instruction this machine has, rather than
+    // an instruction that went wrong while running
+    pub fn is_decode(&self) -> bool {
+        matches!(
+            self.cause,
+            InvalidOpcode::DoesntExist(..)
+                | InvalidOpcode::UnsupportedOnPlatform(..)
+        )
+    }
+}
+
+impl Error for Fault {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.cause)
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {:#05X} ", self.cause, self.pc)?;
+        // The decoded instruction shows its own word
+        match &self.op {
+            Some(op) => write!(f, "({:?})", op),
+            None => write!(f, "({:04X})", self.word),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputError {
+    NoSuchKey(usize),
+}
+
+impl Error for InputError {}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::NoSuchKey(key) => {
+                write!(f, "There is no key {:#X}, keys go from 0 to F", key)
+            }
+        }
+    }
+}
+
+// Everything that can go wrong with a machine, grouped by where it happened
+#[derive(Debug)]
+pub enum Chip8Error {
+    Rom(RomError),
+    Decode(Fault),
+    Execution(Fault),
+    Input(InputError),
+    State(StateError),
+}
+
+impl Error for Chip8Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Chip8Error::Rom(err) => Some(err),
+            Chip8Error::Decode(err) | Chip8Error::Execution(err) => Some(err),
+            Chip8Error::Input(err) => Some(err),
+            Chip8Error::State(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::Rom(err) => {
+                write!(f, "Could not load the rom: {}", err)
+            }
+            Chip8Error::Decode(err) => {
+                write!(f, "Could not decode an instruction: {}", err)
+            }
+            Chip8Error::Execution(err) => {
+                write!(f, "An instruction failed: {}", err)
+            }
+            Chip8Error::Input(err) => write!(f, "Invalid input: {}", err),
+            Chip8Error::State(err) => {
+                write!(f, "Could not load the state: {}", err)
+            }
+        }
+    }
+}
+
+impl From<RomError> for Chip8Error {
+    fn from(err: RomError) -> Chip8Error {
+        Chip8Error::Rom(err)
+    }
+}
+
+impl From<Fault> for Chip8Error {
+    fn from(err: Fault) -> Chip8Error {
+        if err.is_decode() {
+            Chip8Error::Decode(err)
+        } else {
+            Chip8Error::Execution(err)
+        }
+    }
+}
+
+impl From<InputError> for Chip8Error {
+    fn from(err: InputError) -> Chip8Error {
+        Chip8Error::Input(err)
+    }
+}
+
+impl From<StateError> for Chip8Error {
+    fn from(err: StateError) -> Chip8Error {
+        Chip8Error::State(err)
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt;
+use std::str::FromStr;
+
+use opcode::InvalidOpcode;
+
+// The kinds of fault a policy can be set for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultClass {
+    // The word isn't an instruction at all
+    Undefined,
+    // An instruction the platform doesn't have
+    Unsupported,
+    StackOverflow,
+    StackUnderflow,
+    // Memory accesses and the program counter going past the end of memory
+    Memory,
+    // FX29 and FX30 with a digit that has no sprite
+    Font,
+    Screen,
+    // EX9E and EXA1 with a key that doesn't exist
+    Key,
+}
+
+pub const FAULT_CLASSES: [FaultClass; 8] = [
+    FaultClass::Undefined,
+    FaultClass::Unsupported,
+    FaultClass::StackOverflow,
+    FaultClass::StackUnderflow,
+    FaultClass::Memory,
+    FaultClass::Font,
+    FaultClass::Screen,
+    FaultClass::Key,
+];
+
+impl FaultClass {
+    pub fn of(cause: &InvalidOpcode) -> FaultClass {
+        match cause {
+            InvalidOpcode::DoesntExist(..) => FaultClass::Undefined,
+            InvalidOpcode::UnsupportedOnPlatform(..) => FaultClass::Unsupported,
+            InvalidOpcode::StackOverflow(..) => FaultClass::StackOverflow,
+            InvalidOpcode::StackUnderflow(..) => FaultClass::StackUnderflow,
+            InvalidOpcode::OutOfBoundsAddress(..)
+            | InvalidOpcode::PcOutOfBounds(..) => FaultClass::Memory,
+            InvalidOpcode::NoSuchDigitSprite(..) => FaultClass::Font,
+            InvalidOpcode::OutOfScreenBounds(..) => FaultClass::Screen,
+            InvalidOpcode::NoSuchKey(..) => FaultClass::Key,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            FaultClass::Undefined => "undefined",
+            FaultClass::Unsupported => "unsupported",
+            FaultClass::StackOverflow => "stack-overflow",
+            FaultClass::StackUnderflow => "stack-underflow",
+            FaultClass::Memory => "memory",
+            FaultClass::Font => "font",
+            FaultClass::Screen => "screen",
+            FaultClass::Key => "key",
+        }
+    }
+
+    fn index(&self) -> usize {
+        FAULT_CLASSES
+            .iter()
+            .position(|class| class == self)
+            .expect("Every class is listed")
+    }
+}
+
+impl fmt::Display for FaultClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for FaultClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FaultClass, String> {
+        let name = s.to_lowercase();
+        FAULT_CLASSES
+            .iter()
+            .find(|class| class.name() == name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown fault class: {}", s))
+    }
+}
+
+// What the machine does when an instruction faults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultPolicy {
+    // Stop for good and report the fault
+    Halt,
+    // Treat the instruction as a no-op and carry on after it
+    Skip,
+    // Pause on the faulting instruction so it can be inspected
+    Break,
+}
+
+impl fmt::Display for FaultPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaultPolicy::Halt => write!(f, "halt"),
+            FaultPolicy::Skip => write!(f, "skip"),
+            FaultPolicy::Break => write!(f, "break"),
+        }
+    }
+}
+
+impl FromStr for FaultPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FaultPolicy, String> {
+        match s.to_lowercase().as_str() {
+            "halt" => Ok(FaultPolicy::Halt),
+            "skip" | "nop" => Ok(FaultPolicy::Skip),
+            "break" | "pause" => Ok(FaultPolicy::Break),
+            _ => Err(format!("Unknown fault policy: {}", s)),
+        }
+    }
+}
+
+// A policy for every class of fault, all halting by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultPolicies([FaultPolicy; FAULT_CLASSES.len()]);
+
+impl Default for FaultPolicies {
+    fn default() -> FaultPolicies {
+        FaultPolicies::all(FaultPolicy::Halt)
+    }
+}
+
+impl FaultPolicies {
+    pub fn all(policy: FaultPolicy) -> FaultPolicies {
+        FaultPolicies([policy; FAULT_CLASSES.len()])
+    }
+
+    pub fn get(&self, class: FaultClass) -> FaultPolicy {
+        self.0[class.index()]
+    }
+
+    pub fn set(&mut self, class: FaultClass, policy: FaultPolicy) {
+        self.0[class.index()] = policy;
+    }
+}
+
+// Parses a comma separated list where each entry is either a policy for
+// every class or a class=policy pair, later entries winning. For example
+// "break,undefined=skip" skips undefined words and breaks on anything else.
+impl FromStr for FaultPolicies {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FaultPolicies, String> {
+        let mut policies = FaultPolicies::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((class, policy)) => policies.set(
+                    class.trim().parse::<FaultClass>()?,
+                    policy.trim().parse::<FaultPolicy>()?,
+                ),
+                None => {
+                    policies = FaultPolicies::all(entry.parse::<FaultPolicy>()?)
+                }
+            }
+        }
+        Ok(policies)
+    }
+}
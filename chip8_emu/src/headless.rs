@@ -0,0 +1,386 @@
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+use cpu::RomError;
+use debugger::Condition;
+use debugger::Debugger;
+use debugger::StopReason;
+use error::Fault;
+use fault::FaultPolicies;
+use platform::Platform;
+use quirks::Quirks;
+use report::RegisterDump;
+use rng::Rng;
+use screen::Screen;
+use screen::PALETTE;
+use Chip8;
+use DEFAULT_CLOCK_SPEED;
+use FRAME_RATE;
+
+// Exit codes for scripted runs. Anything that goes wrong before the machine
+// starts, like a missing rom or a bad argument, exits with 1.
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_TIMEOUT: i32 = 2;
+pub const EXIT_INVALID_OPCODE: i32 = 3;
+
+const DEFAULT_FRAMES: u64 = 600;
+const DEFAULT_CYCLES_PER_FRAME: u32 = DEFAULT_CLOCK_SPEED / FRAME_RATE;
+// Enough work per frame for the timing to mean something
+const BENCH_CYCLES_PER_FRAME: u32 = 10_000;
+
+// A key press or release applied at the start of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// Key events read from lines of "<frame> <key> down|up", where the key is a
+// hex digit. Blank lines and everything after a # are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct KeyScript {
+    events: Vec<KeyEvent>,
+}
+
+impl KeyScript {
+    pub fn events(&self) -> &[KeyEvent] {
+        &self.events
+    }
+}
+
+impl FromStr for KeyScript {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<KeyScript, String> {
+        let mut events = Vec::new();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            let error = |message: &str| format!("{}: {}", number + 1, message);
+            if words.len() != 3 {
+                return Err(error("expected <frame> <key> down|up"));
+            }
+            let frame = words[0]
+                .parse::<u64>()
+                .map_err(|_| error("invalid frame number"))?;
+            let key = u8::from_str_radix(words[1], 16)
+                .ok()
+                .filter(|key| *key <= 0xF)
+                .ok_or_else(|| error("key must be a hex digit 0-F"))?;
+            let pressed = match words[2].to_lowercase().as_str() {
+                "down" | "press" => true,
+                "up" | "release" => false,
+                _ => return Err(error("expected down or up")),
+            };
+            events.push(KeyEvent {
+                frame,
+                key,
+                pressed,
+            });
+        }
+        // Stable, so events on the same frame keep the order they were given
+        events.sort_by_key(|event| event.frame);
+        Ok(KeyScript { events })
+    }
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    // Every frame ran and there was nothing to stop at
+    Finished,
+    // The program exited before there was anything to stop at
+    Exited,
+    // Stopped at the requested address or condition
+    Reached(StopReason),
+    // Ran out of frames, or exited, before reaching the stop target
+    Timeout,
+    Fault(Fault),
+}
+
+impl Outcome {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Finished | Outcome::Exited | Outcome::Reached(_) => {
+                EXIT_SUCCESS
+            }
+            Outcome::Timeout => EXIT_TIMEOUT,
+            Outcome::Fault(_) => EXIT_INVALID_OPCODE,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Finished => write!(f, "Ran every frame"),
+            Outcome::Exited => write!(f, "Program exited"),
+            Outcome::Reached(reason) => write!(f, "{}", reason),
+            Outcome::Timeout => write!(f, "Timed out before reaching target"),
+            Outcome::Fault(err) => write!(f, "Faulted: {}", err),
+        }
+    }
+}
+
+// Runs a machine with no display for a fixed number of frames, or until it
+// reaches an address or a condition becomes true, feeding in scripted keys.
+pub struct Headless {
+    debugger: Debugger,
+    script: KeyScript,
+    frames: u64,
+    cycles_per_frame: u32,
+    has_target: bool,
+}
+
+impl Headless {
+    pub fn new(frames: u64, cycles_per_frame: u32) -> Headless {
+        Headless {
+            debugger: Debugger::new(),
+            script: KeyScript::default(),
+            frames,
+            cycles_per_frame,
+            has_target: false,
+        }
+    }
+
+    pub fn stop_at(&mut self, addr: u16) {
+        self.debugger.add_breakpoint(addr);
+        self.has_target = true;
+    }
+
+    pub fn stop_when(&mut self, condition: Condition) {
+        self.debugger.break_when(condition);
+        self.has_target = true;
+    }
+
+    pub fn set_script(&mut self, script: KeyScript) {
+        self.script = script;
+    }
+
+    // Returns how the run ended together with the number of frames started
+    pub fn run(&mut self, emu: &mut Chip8) -> (Outcome, u64) {
+        let events = self.script.events();
+        let mut next_event = 0;
+        for frame in 0..self.frames {
+            while let Some(event) =
+                events.get(next_event).filter(|event| event.frame <= frame)
+            {
+                // Keys were checked to be in range when the script was parsed
+                let _ = emu.set_ctrl_state(event.key as usize, event.pressed);
+                next_event += 1;
+            }
+            let outcome =
+                match self.debugger.run_frame(emu, self.cycles_per_frame) {
+                    None => continue,
+                    Some(StopReason::Fault(err)) => Outcome::Fault(err),
+                    Some(StopReason::Exited) => {
+                        if self.has_target {
+                            Outcome::Timeout
+                        } else {
+                            Outcome::Exited
+                        }
+                    }
+                    Some(reason) => Outcome::Reached(reason),
+                };
+            return (outcome, frame + 1);
+        }
+        let outcome = if self.has_target {
+            Outcome::Timeout
+        } else {
+            Outcome::Finished
+        };
+        (outcome, self.frames)
+    }
+}
+
+// How to set up and run a machine for a scripted run, everything the
+// headless tool takes on its command line besides the rom and the outputs
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub platform: Platform,
+    // The platform's own quirks when None
+    pub quirks: Option<Quirks>,
+    pub seed: u64,
+    pub frames: u64,
+    // DEFAULT_CYCLES_PER_FRAME, or BENCH_CYCLES_PER_FRAME for benchmarks,
+    // when None
+    pub cycles: Option<u32>,
+    pub until_pc: Vec<u16>,
+    pub until: Vec<Condition>,
+    pub script: KeyScript,
+    pub fault_policies: FaultPolicies,
+    pub cache: bool,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> HeadlessOptions {
+        HeadlessOptions {
+            platform: Platform::default(),
+            quirks: None,
+            seed: 0,
+            frames: DEFAULT_FRAMES,
+            cycles: None,
+            until_pc: Vec::new(),
+            until: Vec::new(),
+            script: KeyScript::default(),
+            fault_policies: FaultPolicies::default(),
+            cache: false,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    fn machine(&self, rom: &[u8], cache: bool) -> Result<Chip8, RomError> {
+        let quirks =
+            self.quirks.unwrap_or_else(|| self.platform.default_quirks());
+        let mut chip8 =
+            Chip8::new(rom, self.platform, quirks, Rng::new(self.seed))?;
+        chip8.set_decode_cache(cache);
+        chip8.set_fault_policies(self.fault_policies);
+        Ok(chip8)
+    }
+
+    // Runs the rom and returns the machine as the run left it, how the run
+    // ended and the number of frames started
+    pub fn run(&self, rom: &[u8]) -> Result<(Chip8, Outcome, u64), RomError> {
+        let mut chip8 = self.machine(rom, self.cache)?;
+        let cycles = self.cycles.unwrap_or(DEFAULT_CYCLES_PER_FRAME);
+        let mut headless = Headless::new(self.frames, cycles);
+        for addr in &self.until_pc {
+            headless.stop_at(*addr);
+        }
+        for condition in &self.until {
+            headless.stop_when(*condition);
+        }
+        headless.set_script(self.script.clone());
+        let (outcome, frames) = headless.run(&mut chip8);
+        Ok((chip8, outcome, frames))
+    }
+
+    // Benchmarks the rom without and then with the instruction cache
+    pub fn bench(&self, rom: &[u8]) -> Result<[Benchmark; 2], RomError> {
+        let cycles = self.cycles.unwrap_or(BENCH_CYCLES_PER_FRAME);
+        Ok([
+            benchmark(&mut self.machine(rom, false)?, self.frames, cycles),
+            benchmark(&mut self.machine(rom, true)?, self.frames, cycles),
+        ])
+    }
+}
+
+pub struct Benchmark {
+    pub instructions: u64,
+    pub elapsed: Duration,
+    // Set if the rom stopped on an invalid opcode before the last frame
+    pub fault: Option<Fault>,
+}
+
+impl Benchmark {
+    pub fn per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+}
+
+// Runs the given frames as fast as possible, counting the instructions that
+// actually execute. Cycles spent blocked on a key or the next frame aren't
+// counted.
+pub fn benchmark(
+    emu: &mut Chip8,
+    frames: u64,
+    cycles_per_frame: u32,
+) -> Benchmark {
+    let mut instructions = 0;
+    let mut fault = None;
+    let start = Instant::now();
+    'frames: for _ in 0..frames {
+        emu.vblank();
+        for _ in 0..cycles_per_frame {
+            if emu.is_blocked() {
+                break;
+            }
+            if let Err(err) = emu.run_cycle() {
+                fault = Some(err);
+                break 'frames;
+            }
+            instructions += 1;
+        }
+    }
+    Benchmark {
+        instructions,
+        elapsed: start.elapsed(),
+        fault,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenFormat {
+    Ascii,
+    Pbm,
+    Png,
+}
+
+impl ScreenFormat {
+    // Picks the format from a file's extension, falling back to ASCII
+    pub fn from_path(path: &str) -> ScreenFormat {
+        let path = path.to_lowercase();
+        if path.ends_with(".pbm") {
+            ScreenFormat::Pbm
+        } else if path.ends_with(".png") {
+            ScreenFormat::Png
+        } else {
+            ScreenFormat::Ascii
+        }
+    }
+}
+
+// Writes the screen as text, as a black and white PBM where every lit pixel
+// is black, or as a PNG in the emulator's colours.
+pub fn write_screen<W: Write>(
+    screen: &Screen,
+    format: ScreenFormat,
+    mut out: W,
+) -> io::Result<()> {
+    match format {
+        ScreenFormat::Ascii => write!(out, "{:?}", screen),
+        ScreenFormat::Pbm => {
+            write!(out, "P4\n{} {}\n", screen.width, screen.height)?;
+            for row in screen.buffer.chunks(screen.width) {
+                let mut packed = vec![0u8; screen.width.div_ceil(8)];
+                for (x, pixel) in row.iter().enumerate() {
+                    if *pixel != 0 {
+                        packed[x / 8] |= 0x80 >> (x % 8);
+                    }
+                }
+                out.write_all(&packed)?;
+            }
+            Ok(())
+        }
+        ScreenFormat::Png => {
+            let mut encoder = png::Encoder::new(
+                out,
+                screen.width as u32,
+                screen.height as u32,
+            );
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_palette(PALETTE.concat());
+            let mut writer =
+                encoder.write_header().map_err(io::Error::other)?;
+            writer
+                .write_image_data(&screen.buffer)
+                .map_err(io::Error::other)
+        }
+    }
+}
+
+// The registers and call stack as a JSON object
+pub fn registers_json(emu: &Chip8) -> String {
+    serde_json::to_string_pretty(&RegisterDump::of(emu))
+        .expect("Registers always serialize")
+}
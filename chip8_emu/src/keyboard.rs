@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy)]
+use error::InputError;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Keyboard {
     pub key_buffer: [bool; 0xF + 1],
     pub wait_press: Option<u8>,
@@ -12,9 +14,9 @@ impl Keyboard {
         }
     }
 
-    pub fn press_key(&mut self, key: usize, vreg: &mut [u8; 16]) -> Result<(), String> {
-        if key > 16 { // There are 16 elements in the keyboard
-            Err(format!("Error, tried to press key that does not exist {:X}", key))
+    pub fn press_key(&mut self, key: usize, vreg: &mut [u8; 16]) -> Result<(), InputError> {
+        if key >= 16 { // There are 16 elements in the keyboard
+            Err(InputError::NoSuchKey(key))
         } else {
             self.key_buffer[key] = true;
             if self.wait_press != None {
@@ -25,9 +27,9 @@ impl Keyboard {
         }
     }
 
-    pub fn release_key(&mut self, key: usize) -> Result<(), String> {
-        if key > 16 { // There are 16 elements in the keyboard
-            Err(format!("Error, tried to press key that does not exist {:X}", key))
+    pub fn release_key(&mut self, key: usize) -> Result<(), InputError> {
+        if key >= 16 { // There are 16 elements in the keyboard
+            Err(InputError::NoSuchKey(key))
         } else {
             self.key_buffer[key] = false;
             Ok(())
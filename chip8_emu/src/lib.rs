@@ -1,32 +1,280 @@
-extern crate rand;
 extern crate num;
+extern crate bincode;
+extern crate png;
+extern crate serde_json;
+#[macro_use]
+extern crate serde;
+mod asm;
+mod audio;
 mod cpu;
+mod debugger;
+mod disasm;
+mod error;
+mod fault;
+mod hash;
+mod headless;
 mod keyboard;
+mod movie;
 mod nybble;
 mod opcode;
+mod platform;
+mod quirks;
+mod report;
+mod rewind;
+mod rng;
 mod screen;
+mod state;
 
 use cpu::Cpu;
-use opcode::InvalidOpcode;
+pub use asm::assemble;
+pub use asm::AsmError;
+pub use audio::Beeper;
+pub use audio::Waveform;
+pub use audio::DEFAULT_FREQUENCY;
+pub use audio::DEFAULT_SAMPLE_RATE;
+pub use audio::DEFAULT_VOLUME;
+pub use cpu::Access;
+pub use cpu::MemoryAccess;
+pub use cpu::RomError;
+pub use cpu::FONT;
+pub use debugger::Comparison;
+pub use debugger::Condition;
+pub use debugger::Debugger;
+pub use debugger::Register;
+pub use debugger::StopReason;
+pub use debugger::Watchpoint;
+pub use disasm::detect_platform;
+pub use disasm::disassemble;
+pub use disasm::Disassembly;
+pub use disasm::Syntax;
+pub use error::Chip8Error;
+pub use error::Fault;
+pub use error::InputError;
+pub use fault::FaultClass;
+pub use fault::FaultPolicies;
+pub use fault::FaultPolicy;
+pub use fault::FAULT_CLASSES;
+pub use headless::benchmark;
+pub use headless::registers_json;
+pub use headless::write_screen;
+pub use headless::Benchmark;
+pub use headless::Headless;
+pub use headless::HeadlessOptions;
+pub use headless::KeyEvent;
+pub use headless::KeyScript;
+pub use headless::Outcome;
+pub use headless::ScreenFormat;
+pub use headless::EXIT_INVALID_OPCODE;
+pub use headless::EXIT_SUCCESS;
+pub use headless::EXIT_TIMEOUT;
+pub use movie::replay;
+pub use movie::Movie;
+pub use movie::MovieError;
+pub use movie::MovieEvent;
+pub use movie::MoviePlayer;
+pub use movie::MovieRecorder;
+pub use opcode::InvalidOpcode;
+pub use opcode::Opcode;
+pub use platform::Platform;
+pub use quirks::IndexIncrement;
+pub use quirks::Quirks;
+pub use report::CrashReport;
+pub use report::History;
+pub use report::RegisterDump;
+pub use report::ReportError;
+pub use report::HISTORY_LEN;
+pub use rewind::Rewind;
+pub use rng::Rng;
+pub use screen::PALETTE;
+pub use state::StateError;
+pub use state::STATE_VERSION;
+
+// Frames per second, every frame starts with a vertical blank
+pub const FRAME_RATE: u32 = 60;
+// How often DT and ST count down per second on every real interpreter
+pub const DEFAULT_TIMER_RATE: u32 = 60;
+// Instructions per second
+pub const DEFAULT_CLOCK_SPEED: u32 = 540;
+// Rates above these are cut down to them. Past 256 ticks a frame any timer
+// runs out within the frame, and both keep the per frame sums from
+// overflowing.
+pub const MAX_TIMER_RATE: u32 = 256 * FRAME_RATE;
+pub const MAX_CLOCK_SPEED: u32 = 1_000_000_000;
 
 pub struct Chip8 {
     pub cpu: Cpu,
+    rom_hash: u64,
+    timer_rate: u32,
+    clock_speed: u32,
+    // Instructions owed to the next frame when the clock speed isn't a
+    // multiple of the frame rate
+    clock_phase: u32,
+    decode_cache: bool,
+    fault_policies: FaultPolicies,
+    history: History,
 }
 
 impl Chip8 {
-    pub fn new(rom_bytes: &[u8]) -> Chip8 {
-        Chip8 {
-            cpu: Cpu::new(rom_bytes),
-        }
+    pub fn new(
+        rom_bytes: &[u8],
+        platform: Platform,
+        quirks: Quirks,
+        rng: Rng,
+    ) -> Result<Chip8, RomError> {
+        Ok(Chip8 {
+            cpu: Cpu::new(rom_bytes, platform, quirks, rng)?,
+            rom_hash: hash::fnv1a(rom_bytes),
+            timer_rate: DEFAULT_TIMER_RATE,
+            clock_speed: DEFAULT_CLOCK_SPEED,
+            clock_phase: 0,
+            decode_cache: false,
+            fault_policies: FaultPolicies::default(),
+            history: History::default(),
+        })
+    }
+
+    pub fn timer_rate(&self) -> u32 {
+        self.timer_rate
+    }
+
+    // Sets how many times per second DT and ST count down, at most
+    // MAX_TIMER_RATE
+    pub fn set_timer_rate(&mut self, timer_rate: u32) {
+        self.timer_rate = timer_rate.min(MAX_TIMER_RATE);
+    }
+
+    pub fn clock_speed(&self) -> u32 {
+        self.clock_speed
+    }
+
+    // Sets how many instructions run per second, starting on a frame
+    // boundary. Speeds that aren't a multiple of the frame rate spread the
+    // extra instructions evenly over the frames. Capped at MAX_CLOCK_SPEED.
+    pub fn set_clock_speed(&mut self, clock_speed: u32) {
+        self.clock_speed = clock_speed.min(MAX_CLOCK_SPEED);
+        self.clock_phase = 0;
+    }
+
+    // How many instructions the next frame runs at the current clock speed
+    pub fn cycles_for_frame(&mut self) -> u32 {
+        self.clock_phase += self.clock_speed;
+        let cycles = self.clock_phase / FRAME_RATE;
+        self.clock_phase %= FRAME_RATE;
+        cycles
     }
 
-    pub fn run_cycle(
+    // Starts the rom over on a fresh machine, keeping the platform, quirks
+    // and settings. The RPL flags aren't part of the machine and survive.
+    pub fn reset(
         &mut self,
-    ) -> Result<(), InvalidOpcode> {
-        if self.cpu.keyboard.wait_press == None {
-            self.cpu.step()
+        rom_bytes: &[u8],
+        rng: Rng,
+    ) -> Result<(), RomError> {
+        let (platform, quirks) = (self.cpu.platform, self.cpu.quirks);
+        let cpu = Cpu::new(rom_bytes, platform, quirks, rng)?;
+        let rpl_flags = self.cpu.rpl_flags;
+        self.cpu = cpu;
+        self.cpu.rpl_flags = rpl_flags;
+        self.rom_hash = hash::fnv1a(rom_bytes);
+        self.clock_phase = 0;
+        self.history.clear();
+        Ok(())
+    }
+
+    // Remembers decoded instructions by address instead of decoding every
+    // word again each time it runs. Off by default.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache = enabled;
+    }
+
+    pub fn rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+    // Identifies the complete machine state, two machines with the same hash
+    // will behave identically from here on.
+    pub fn state_hash(&self) -> u64 {
+        hash::fnv1a(&state::serialize_cpu(&self.cpu))
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        state::save(&self.cpu, self.rom_hash)
+    }
+
+    // Replaces the whole machine with a previously saved one. The current
+    // machine is left untouched if the state can't be loaded.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), StateError> {
+        self.cpu = state::load(bytes, self.rom_hash)?;
+        self.history.clear();
+        Ok(())
+    }
+
+    pub fn fault_policies(&self) -> FaultPolicies {
+        self.fault_policies
+    }
+
+    // Decides what happens to each class of fault. Skipped faults never
+    // reach the caller, the others come back from run_cycle as errors.
+    pub fn set_fault_policies(&mut self, policies: FaultPolicies) {
+        self.fault_policies = policies;
+    }
+
+    pub fn fault_policy(&self, fault: &Fault) -> FaultPolicy {
+        self.fault_policies.get(fault.class())
+    }
+
+    // The instructions that ran most recently, for crash reports
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn run_cycle(&mut self) -> Result<(), Fault> {
+        if self.is_blocked() {
+            return Ok(());
+        }
+        self.history.record(self.cpu.regs.pc(), self.cpu.fetch_opcode());
+        let result = if self.decode_cache {
+            self.cpu.step_cached()
         } else {
-            Ok(())
+            self.cpu.step()
+        };
+        match result {
+            Err(fault) if self.fault_policy(&fault) == FaultPolicy::Skip => {
+                self.cpu.skip_fault(&fault);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    // True while a cycle would not execute anything, because the machine is
+    // waiting on a key, on the next frame or has exited.
+    pub fn is_blocked(&self) -> bool {
+        self.cpu.keyboard.wait_press.is_some()
+            || self.cpu.exited
+            || self.cpu.vblank_wait
+    }
+
+    // Runs one 60th of a second: the vertical blank, then the given number of
+    // instructions.
+    pub fn run_frame(&mut self, cycles: u32) -> Result<(), Fault> {
+        self.vblank();
+        for _ in 0..cycles {
+            self.run_cycle()?;
+        }
+        Ok(())
+    }
+
+    // Signals the start of a new frame, releasing a draw waiting on the
+    // display wait quirk and counting the timers down.
+    pub fn vblank(&mut self) {
+        self.cpu.vblank_wait = false;
+        self.cpu.rng.tick();
+        self.cpu.timer_phase += self.timer_rate;
+        while self.cpu.timer_phase >= FRAME_RATE {
+            self.cpu.timer_phase -= FRAME_RATE;
+            self.decrement_delay();
+            self.decrement_sound();
         }
     }
 
@@ -42,7 +290,7 @@ impl Chip8 {
         }
     }
 
-    pub fn set_ctrl_state(&mut self, button: usize, pressed: bool) -> Result<(), String> {
+    pub fn set_ctrl_state(&mut self, button: usize, pressed: bool) -> Result<(), InputError> {
         if pressed {
             self.cpu.keyboard.press_key(button, &mut self.cpu.regs.v_regs)?
         } else {
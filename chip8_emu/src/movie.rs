@@ -0,0 +1,303 @@
+use std::error::Error;
+use std::fmt;
+
+use bincode;
+
+use error::Fault;
+use error::InputError;
+use platform::Platform;
+use quirks::Quirks;
+use rng::Rng;
+use state::StateError;
+use Chip8;
+
+const MAGIC: &[u8; 4] = b"C8MV";
+pub const MOVIE_VERSION: u16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovieEvent {
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// A recorded play session. Replaying the events on top of the start state
+// has to reproduce the recorded state hash after every single frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub rng: Rng,
+    pub cycles_per_frame: u32,
+    pub timer_rate: u32,
+    pub start_state: Vec<u8>,
+    pub events: Vec<MovieEvent>,
+    pub frame_hashes: Vec<u64>,
+}
+
+#[derive(Debug)]
+pub enum MovieError {
+    NotAMovie,
+    UnsupportedVersion(u16),
+    Corrupt(String),
+    RomMismatch {
+        expected: u64,
+        found: u64,
+    },
+    State(StateError),
+    Fault(u64, Fault),
+    Desync {
+        frame: u64,
+        expected: u64,
+        found: u64,
+    },
+    // Every recorded frame has already been played
+    Finished,
+}
+
+impl Error for MovieError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MovieError::State(err) => Some(err),
+            MovieError::Fault(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovieError::NotAMovie => write!(f, "Data is not a movie"),
+            MovieError::UnsupportedVersion(version) => write!(
+                f,
+                "Movie has format version {}, expected {}",
+                version, MOVIE_VERSION
+            ),
+            MovieError::Corrupt(reason) => {
+                write!(f, "Movie is corrupt: {}", reason)
+            }
+            MovieError::RomMismatch { expected, found } => write!(
+                f,
+                "Movie was recorded with rom {:016x}, but rom {:016x} is loaded",
+                found, expected
+            ),
+            MovieError::State(err) => {
+                write!(f, "Could not load the movie's start state: {}", err)
+            }
+            MovieError::Fault(frame, err) => {
+                write!(f, "Emulation faulted on frame {}: {}", frame, err)
+            }
+            MovieError::Desync {
+                frame,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Playback diverged on frame {}: expected state {:016x}, got {:016x}",
+                frame, expected, found
+            ),
+            MovieError::Finished => {
+                write!(f, "Movie has no frames left to play")
+            }
+        }
+    }
+}
+
+impl Movie {
+    pub fn frames(&self) -> u64 {
+        self.frame_hashes.len() as u64
+    }
+
+    pub fn final_hash(&self) -> Option<u64> {
+        self.frame_hashes.last().cloned()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&MOVIE_VERSION.to_le_bytes());
+        bytes.extend(
+            bincode::serde::encode_to_vec(self, bincode::config::standard())
+                .expect("Movies are always serializable"),
+        );
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Movie, MovieError> {
+        let body_start = MAGIC.len() + 2;
+        if bytes.len() < body_start || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(MovieError::NotAMovie);
+        }
+        let version =
+            u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+        let movie: Movie = bincode::serde::decode_from_slice(
+            &bytes[body_start..],
+            bincode::config::standard(),
+        )
+        .map(|(movie, _)| movie)
+        .map_err(|err| MovieError::Corrupt(err.to_string()))?;
+        movie.validate()?;
+        Ok(movie)
+    }
+
+    // Events have to press keys that exist, in the order of the frames they
+    // happen on, and no later than the frame after the last one recorded
+    fn validate(&self) -> Result<(), MovieError> {
+        let mut frame = 0;
+        for event in &self.events {
+            if event.key > 0xF {
+                return Err(MovieError::Corrupt(format!(
+                    "Event on frame {} is for key {}, which doesn't exist",
+                    event.frame, event.key
+                )));
+            }
+            if event.frame < frame || event.frame > self.frames() {
+                return Err(MovieError::Corrupt(format!(
+                    "Event on frame {} is out of order",
+                    event.frame
+                )));
+            }
+            frame = event.frame;
+        }
+        Ok(())
+    }
+}
+
+pub struct MovieRecorder {
+    movie: Movie,
+}
+
+impl MovieRecorder {
+    // Starts recording from the machine's current state
+    pub fn start(emu: &Chip8, cycles_per_frame: u32) -> MovieRecorder {
+        MovieRecorder {
+            movie: Movie {
+                rom_hash: emu.rom_hash(),
+                platform: emu.cpu.platform,
+                quirks: emu.cpu.quirks,
+                rng: emu.cpu.rng,
+                cycles_per_frame,
+                timer_rate: emu.timer_rate(),
+                start_state: emu.save_state(),
+                events: Vec::new(),
+                frame_hashes: Vec::new(),
+            },
+        }
+    }
+
+    // Presses or releases a key and stamps the event with the frame about to
+    // be run.
+    pub fn set_ctrl_state(
+        &mut self,
+        emu: &mut Chip8,
+        key: usize,
+        pressed: bool,
+    ) -> Result<(), InputError> {
+        emu.set_ctrl_state(key, pressed)?;
+        self.movie.events.push(MovieEvent {
+            frame: self.movie.frames(),
+            key: key as u8,
+            pressed,
+        });
+        Ok(())
+    }
+
+    pub fn end_frame(&mut self, emu: &Chip8) {
+        self.movie.frame_hashes.push(emu.state_hash());
+    }
+
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+pub struct MoviePlayer {
+    movie: Movie,
+    frame: u64,
+    next_event: usize,
+}
+
+impl MoviePlayer {
+    // Puts the machine back into the state the recording started from
+    pub fn start(
+        movie: Movie,
+        emu: &mut Chip8,
+    ) -> Result<MoviePlayer, MovieError> {
+        if movie.rom_hash != emu.rom_hash() {
+            return Err(MovieError::RomMismatch {
+                expected: emu.rom_hash(),
+                found: movie.rom_hash,
+            });
+        }
+        movie.validate()?;
+        emu.load_state(&movie.start_state)
+            .map_err(MovieError::State)?;
+        emu.set_timer_rate(movie.timer_rate);
+        Ok(MoviePlayer {
+            movie,
+            frame: 0,
+            next_event: 0,
+        })
+    }
+
+    pub fn cycles_per_frame(&self) -> u32 {
+        self.movie.cycles_per_frame
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.movie.frames()
+    }
+
+    // Feeds the key events recorded for the frame about to be run
+    pub fn start_frame(&mut self, emu: &mut Chip8) -> Result<(), MovieError> {
+        while let Some(event) = self.movie.events.get(self.next_event) {
+            if event.frame > self.frame {
+                break;
+            }
+            emu.set_ctrl_state(event.key as usize, event.pressed)
+                .map_err(|err| MovieError::Corrupt(err.to_string()))?;
+            self.next_event += 1;
+        }
+        Ok(())
+    }
+
+    // Checks the frame that was just run against the recording
+    pub fn end_frame(&mut self, emu: &Chip8) -> Result<(), MovieError> {
+        let expected = match self.movie.frame_hashes.get(self.frame as usize)
+        {
+            Some(hash) => *hash,
+            None => return Err(MovieError::Finished),
+        };
+        let found = emu.state_hash();
+        if expected != found {
+            return Err(MovieError::Desync {
+                frame: self.frame,
+                expected,
+                found,
+            });
+        }
+        self.frame += 1;
+        Ok(())
+    }
+}
+
+// Plays a whole movie back without any front end, returning the number of
+// frames verified.
+pub fn replay(emu: &mut Chip8, movie: Movie) -> Result<u64, MovieError> {
+    let mut player = MoviePlayer::start(movie, emu)?;
+    while !player.is_finished() {
+        player.start_frame(emu)?;
+        emu.run_frame(player.cycles_per_frame())
+            .map_err(|err| MovieError::Fault(player.frame(), err))?;
+        player.end_frame(emu)?;
+    }
+    Ok(player.frame())
+}
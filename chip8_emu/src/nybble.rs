@@ -1,7 +1,7 @@
 use num::ToPrimitive;
 use std::fmt;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Nybble([u8; 1]);
 
 impl Nybble {
@@ -41,7 +41,7 @@ impl ToPrimitive for Nybble {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct TwoNybbles([u8; 1]);
 
 impl TwoNybbles {
@@ -66,7 +66,7 @@ impl fmt::Debug for TwoNybbles {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct ThreeNybbles([u8; 2]);
 
 impl ThreeNybbles {
@@ -1,11 +1,29 @@
 use std::fmt;
 
+use num::ToPrimitive;
+
 use nybble::Nybble;
 use nybble::ThreeNybbles;
 use nybble::TwoNybbles;
 
 const CLEAR_SCREEN: u16 = 0x00E0;
 const RET_SUBROUTINE: u16 = 0x00EE;
+const SCROLL_RIGHT: u16 = 0x00FB;
+const SCROLL_LEFT: u16 = 0x00FC;
+const EXIT: u16 = 0x00FD;
+const LOW_RES: u16 = 0x00FE;
+const HIGH_RES: u16 = 0x00FF;
+const SCROLL_DOWN: u16 = 0x00C0;
+const SCROLL_UP: u16 = 0x00D0;
+const LOAD_I_LONG: u16 = 0xF000;
+const LOAD_AUDIO: u16 = 0xF002;
+const SELECT_PLANE: u16 = 0xF001;
+const SET_PITCH: u16 = 0xF03A;
+const SAVE_VX_VY: u16 = 0x5002;
+const LOAD_VX_VY: u16 = 0x5003;
+const SET_BIG_SPR_I: u16 = 0xF030;
+const STORE_RPL_VX: u16 = 0xF075;
+const READ_RPL_VX: u16 = 0xF085;
 const SKIP_IF_VX: u16 = 0xE09E;
 const SKIP_IF_NOT_VX: u16 = 0xE0A1;
 const SET_VX_DT: u16 = 0xF007;
@@ -40,7 +58,127 @@ const PC_EQ_V0_PLUS_NNN: u16 = 0xB000;
 const VX_EQ_RAND_PLUS_KK: u16 = 0xC000;
 const DRAW_VX_VY_NIB: u16 = 0xD000;
 
-#[derive(Clone)]
+// Which bits of a word are fixed by the instruction, the rest are operands
+const EXACT: u16 = 0xFFFF;
+const NNN_ARGS: u16 = 0xF000;
+const X_ARG: u16 = 0xF0FF;
+const XY_ARGS: u16 = 0xF00F;
+const N_ARG: u16 = 0xFFF0;
+
+// How the operands are laid out in the word, and the instruction they make
+#[derive(Clone, Copy)]
+enum Args {
+    None(&'static NoArg),
+    // A register in the second nibble
+    X(fn(Nybble) -> OneArg),
+    // A count in the last nibble
+    N(fn(Nybble) -> OneArg),
+    XY(fn(TwoNybbles) -> TwoArg),
+    // The lower twelve bits, as nnn, xkk or xyn
+    Nnn(fn(ThreeNybbles) -> ThreeArg),
+}
+
+impl Args {
+    fn build(&self, op: u16) -> Opcode {
+        match self {
+            Args::None(arg) => Opcode::NoArg((*arg).clone()),
+            Args::X(build) => Opcode::OneArg(build(Nybble::from(op))),
+            Args::N(build) => {
+                Opcode::OneArg(build(Nybble::new([(op & 0x000F) as u8])))
+            }
+            Args::XY(build) => Opcode::TwoArg(build(TwoNybbles::from(op))),
+            Args::Nnn(build) => Opcode::ThreeArg(build(ThreeNybbles::from(op))),
+        }
+    }
+}
+
+type Decoding = (u16, u16, Args);
+
+// Every instruction as (mask, pattern, operands), grouped by high nibble. A
+// word is the instruction when word & mask == pattern. No two entries in a
+// group match the same word, except that 0nnn is last and takes whatever
+// the other 0 instructions leave.
+static DECODINGS: [&[Decoding]; 16] = [
+    &[
+        (EXACT, CLEAR_SCREEN, Args::None(&NoArg::ClearScreen)),
+        (EXACT, RET_SUBROUTINE, Args::None(&NoArg::ReturnSubrt)),
+        (EXACT, SCROLL_RIGHT, Args::None(&NoArg::ScrollRight)),
+        (EXACT, SCROLL_LEFT, Args::None(&NoArg::ScrollLeft)),
+        (EXACT, EXIT, Args::None(&NoArg::Exit)),
+        (EXACT, LOW_RES, Args::None(&NoArg::LowRes)),
+        (EXACT, HIGH_RES, Args::None(&NoArg::HighRes)),
+        (N_ARG, SCROLL_DOWN, Args::N(OneArg::ScrollDown)),
+        (N_ARG, SCROLL_UP, Args::N(OneArg::ScrollUp)),
+        (
+            NNN_ARGS,
+            JUMP_TO_CODEROUTE,
+            Args::Nnn(ThreeArg::JumpToCodeRout),
+        ),
+    ],
+    &[(NNN_ARGS, JUMP_TO_ADDR, Args::Nnn(ThreeArg::JumpToAddr))],
+    &[(NNN_ARGS, CALL_SUB_AT_ADDR, Args::Nnn(ThreeArg::CallSubAt))],
+    &[(NNN_ARGS, SKIP_VX_EQ_KK, Args::Nnn(ThreeArg::SkipVxEqKK))],
+    &[(NNN_ARGS, SKIP_VX_NEQ_KK, Args::Nnn(ThreeArg::SkipVxNEqKK))],
+    &[
+        (XY_ARGS, SKIP_VX_EQ_VY, Args::XY(TwoArg::SkipEqVxVy)),
+        (XY_ARGS, SAVE_VX_VY, Args::XY(TwoArg::SaveVxVy)),
+        (XY_ARGS, LOAD_VX_VY, Args::XY(TwoArg::LoadVxVy)),
+    ],
+    &[(NNN_ARGS, VX_EQ_KK, Args::Nnn(ThreeArg::SetVxKK))],
+    &[(NNN_ARGS, VX_PLUS_EQ_KK, Args::Nnn(ThreeArg::VxEqVxPlusKK))],
+    &[
+        (XY_ARGS, VX_EQ_VY, Args::XY(TwoArg::VxEqVy)),
+        (XY_ARGS, VX_OR_EQ_VY, Args::XY(TwoArg::VxOREqVy)),
+        (XY_ARGS, VX_AND_EQ_VY, Args::XY(TwoArg::VxANDEqVy)),
+        (XY_ARGS, VX_XOR_EQ_VY, Args::XY(TwoArg::VxXOREqVy)),
+        (XY_ARGS, VX_PLUS_EQ_VY_F, Args::XY(TwoArg::VxPlusEqVySetF)),
+        (XY_ARGS, VX_SUB_EQ_VY_F, Args::XY(TwoArg::VxSubEqVySetF)),
+        (XY_ARGS, SHIFT_VX_R, Args::XY(TwoArg::ShiftVxR)),
+        (
+            XY_ARGS,
+            VX_EQ_VY_SUB_VX_F,
+            Args::XY(TwoArg::VxEqVySubVxSetF),
+        ),
+        (XY_ARGS, SHIFT_VX_L, Args::XY(TwoArg::ShiftVxL)),
+    ],
+    &[(XY_ARGS, SKIP_VX_NOT_VY, Args::XY(TwoArg::SkipVxNEqVy))],
+    &[(NNN_ARGS, I_EQ_NNN, Args::Nnn(ThreeArg::SetIToNNN))],
+    &[(
+        NNN_ARGS,
+        PC_EQ_V0_PLUS_NNN,
+        Args::Nnn(ThreeArg::PCEqNNNPlusV0),
+    )],
+    &[(
+        NNN_ARGS,
+        VX_EQ_RAND_PLUS_KK,
+        Args::Nnn(ThreeArg::VxEqRandANDKK),
+    )],
+    &[(NNN_ARGS, DRAW_VX_VY_NIB, Args::Nnn(ThreeArg::DrawVxVyNib))],
+    &[
+        (X_ARG, SKIP_IF_VX, Args::X(OneArg::SkipIfVx)),
+        (X_ARG, SKIP_IF_NOT_VX, Args::X(OneArg::SkipIfNVx)),
+    ],
+    &[
+        (EXACT, LOAD_I_LONG, Args::None(&NoArg::LoadILong)),
+        (EXACT, LOAD_AUDIO, Args::None(&NoArg::LoadAudio)),
+        (X_ARG, SELECT_PLANE, Args::X(OneArg::SelectPlane)),
+        (X_ARG, SET_VX_DT, Args::X(OneArg::SetVxDT)),
+        (X_ARG, WAIT_FOR_KEY, Args::X(OneArg::WaitForKey)),
+        (X_ARG, SET_DT, Args::X(OneArg::SetDT)),
+        (X_ARG, SET_ST, Args::X(OneArg::SetST)),
+        (X_ARG, SET_I, Args::X(OneArg::SetI)),
+        (X_ARG, SET_SPR_I, Args::X(OneArg::SetSpriteI)),
+        (X_ARG, SET_BIG_SPR_I, Args::X(OneArg::SetBigSpriteI)),
+        (X_ARG, STORE_DEC_VX, Args::X(OneArg::StoreDecVx)),
+        (X_ARG, SET_PITCH, Args::X(OneArg::SetPitch)),
+        (X_ARG, STORE_V0_VX, Args::X(OneArg::StoreV0Vx)),
+        (X_ARG, READ_V0_VX, Args::X(OneArg::ReadV0Vx)),
+        (X_ARG, STORE_RPL_VX, Args::X(OneArg::StoreRplVx)),
+        (X_ARG, READ_RPL_VX, Args::X(OneArg::ReadRplVx)),
+    ],
+];
+
+#[derive(Clone, PartialEq, Eq)]
 pub enum Opcode {
     NoArg(NoArg),
     OneArg(OneArg),
@@ -59,10 +197,17 @@ impl fmt::Debug for Opcode {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum NoArg {
     ClearScreen, //00E0
     ReturnSubrt, //00EE
+    ScrollRight, //00FB
+    ScrollLeft,  //00FC
+    Exit,        //00FD
+    LowRes,      //00FE
+    HighRes,     //00FF
+    LoadILong,   //F000 nnnn
+    LoadAudio,   //F002
 }
 
 impl fmt::Debug for NoArg {
@@ -70,23 +215,37 @@ impl fmt::Debug for NoArg {
         match self {
             NoArg::ClearScreen => write!(f, "00E0       ClearScreen"),
             NoArg::ReturnSubrt => write!(f, "00EE       ReturnSubrt"),
+            NoArg::ScrollRight => write!(f, "00FB       ScrollRight"),
+            NoArg::ScrollLeft => write!(f, "00FC       ScrollLeft"),
+            NoArg::Exit => write!(f, "00FD       Exit"),
+            NoArg::LowRes => write!(f, "00FE       LowRes"),
+            NoArg::HighRes => write!(f, "00FF       HighRes"),
+            NoArg::LoadILong => write!(f, "F000       LoadILong"),
+            NoArg::LoadAudio => write!(f, "F002       LoadAudio"),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum OneArg {
-    SkipIfVx(Nybble),   //Ex9E
-    SkipIfNVx(Nybble),  //ExA1
-    SetVxDT(Nybble),    //Fx07
-    WaitForKey(Nybble), //Fx0A
-    SetDT(Nybble),      //Fx15
-    SetST(Nybble),      //Fx18
-    SetI(Nybble),       //Fx1E
-    SetSpriteI(Nybble), //Fx29
-    StoreDecVx(Nybble), //Fx33
-    StoreV0Vx(Nybble),  //Fx55
-    ReadV0Vx(Nybble),   //Fx65
+    SkipIfVx(Nybble),      //Ex9E
+    SkipIfNVx(Nybble),     //ExA1
+    SetVxDT(Nybble),       //Fx07
+    WaitForKey(Nybble),    //Fx0A
+    SetDT(Nybble),         //Fx15
+    SetST(Nybble),         //Fx18
+    SetI(Nybble),          //Fx1E
+    SetSpriteI(Nybble),    //Fx29
+    StoreDecVx(Nybble),    //Fx33
+    StoreV0Vx(Nybble),     //Fx55
+    ReadV0Vx(Nybble),      //Fx65
+    ScrollDown(Nybble),    //00Cn
+    SetBigSpriteI(Nybble), //Fx30
+    StoreRplVx(Nybble),    //Fx75
+    ReadRplVx(Nybble),     //Fx85
+    ScrollUp(Nybble),      //00Dn
+    SelectPlane(Nybble),   //Fn01
+    SetPitch(Nybble),      //Fx3A
 }
 
 impl fmt::Debug for OneArg {
@@ -103,11 +262,22 @@ impl fmt::Debug for OneArg {
             OneArg::StoreDecVx(nyb) => write!(f, "Fx33 {:?}   StoreDecVx", nyb),
             OneArg::StoreV0Vx(nyb) => write!(f, "Fx55 {:?}   StoreV0Vx", nyb),
             OneArg::ReadV0Vx(nyb) => write!(f, "Fx65 {:?}   ReadV0Vx", nyb),
+            OneArg::ScrollDown(nyb) => write!(f, "00Cn {:?}   ScrollDown", nyb),
+            OneArg::SetBigSpriteI(nyb) => {
+                write!(f, "Fx30 {:?}   SetBigSpriteI", nyb)
+            }
+            OneArg::StoreRplVx(nyb) => write!(f, "Fx75 {:?}   StoreRplVx", nyb),
+            OneArg::ReadRplVx(nyb) => write!(f, "Fx85 {:?}   ReadRplVx", nyb),
+            OneArg::ScrollUp(nyb) => write!(f, "00Dn {:?}   ScrollUp", nyb),
+            OneArg::SelectPlane(nyb) => {
+                write!(f, "Fn01 {:?}   SelectPlane", nyb)
+            }
+            OneArg::SetPitch(nyb) => write!(f, "Fx3A {:?}   SetPitch", nyb),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum TwoArg {
     SkipEqVxVy(TwoNybbles),      //5xy0
     VxEqVy(TwoNybbles),          //8xy0
@@ -120,6 +290,8 @@ pub enum TwoArg {
     VxEqVySubVxSetF(TwoNybbles), //8xy7
     ShiftVxL(TwoNybbles),        //8xyE
     SkipVxNEqVy(TwoNybbles),     //9xy0
+    SaveVxVy(TwoNybbles),        //5xy2
+    LoadVxVy(TwoNybbles),        //5xy3
 }
 
 impl fmt::Debug for TwoArg {
@@ -144,11 +316,13 @@ impl fmt::Debug for TwoArg {
             TwoArg::SkipVxNEqVy(nyb) => {
                 write!(f, "9xy0 {:?}  SkipVxNEqVy", nyb)
             }
+            TwoArg::SaveVxVy(nyb) => write!(f, "5xy2 {:?}  SaveVxVy", nyb),
+            TwoArg::LoadVxVy(nyb) => write!(f, "5xy3 {:?}  LoadVxVy", nyb),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum ThreeArg {
     JumpToCodeRout(ThreeNybbles), //0nnn
     JumpToAddr(ThreeNybbles),     //1nnn
@@ -196,137 +370,172 @@ impl fmt::Debug for ThreeArg {
 #[derive(Debug, Clone)]
 pub enum InvalidOpcode {
     DoesntExist(String, u16),
+    // The program counter ran off the end of memory, holds the address
+    PcOutOfBounds(String, u16),
     StackOverflow(String, Opcode),
     StackUnderflow(String, Opcode),
     OutOfBoundsAddress(String, Opcode),
     NoSuchDigitSprite(String, Opcode),
     OutOfScreenBounds(String, Opcode),
+    NoSuchKey(String, Opcode),
+    UnsupportedOnPlatform(String, Opcode),
 }
 
 impl std::error::Error for InvalidOpcode {}
 
 impl std::fmt::Display for InvalidOpcode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "kek")
-    }
-}
-
-impl Opcode {
-    pub fn decode_op(op: u16) -> Result<Opcode, InvalidOpcode> {
-        match op {
-            CLEAR_SCREEN => Ok(Opcode::NoArg(NoArg::ClearScreen)),
-            RET_SUBROUTINE => Ok(Opcode::NoArg(NoArg::ReturnSubrt)),
-            op if ((op & READ_V0_VX) == READ_V0_VX) => {
-                Ok(Opcode::OneArg(OneArg::ReadV0Vx(Nybble::from(op))))
-            }
-            op if ((op & STORE_V0_VX) == STORE_V0_VX) => {
-                Ok(Opcode::OneArg(OneArg::StoreV0Vx(Nybble::from(op))))
-            }
-            op if ((op & STORE_DEC_VX) == STORE_DEC_VX) => {
-                Ok(Opcode::OneArg(OneArg::StoreDecVx(Nybble::from(op))))
-            }
-            op if ((op & SET_SPR_I) == SET_SPR_I) => {
-                Ok(Opcode::OneArg(OneArg::SetSpriteI(Nybble::from(op))))
-            }
-            op if ((op & SET_I) == SET_I) => {
-                Ok(Opcode::OneArg(OneArg::SetI(Nybble::from(op))))
-            }
-            op if ((op & SET_ST) == SET_ST) => {
-                Ok(Opcode::OneArg(OneArg::SetST(Nybble::from(op))))
-            }
-            op if ((op & SET_DT) == SET_DT) => {
-                Ok(Opcode::OneArg(OneArg::SetDT(Nybble::from(op))))
-            }
-            op if ((op & WAIT_FOR_KEY) == WAIT_FOR_KEY) => {
-                Ok(Opcode::OneArg(OneArg::WaitForKey(Nybble::from(op))))
-            }
-            op if ((op & SET_VX_DT) == SET_VX_DT) => {
-                Ok(Opcode::OneArg(OneArg::SetVxDT(Nybble::from(op))))
-            }
-            op if ((op & SKIP_IF_NOT_VX) == SKIP_IF_NOT_VX) => {
-                Ok(Opcode::OneArg(OneArg::SkipIfNVx(Nybble::from(op))))
-            }
-            op if ((op & SKIP_IF_VX) == SKIP_IF_VX) => {
-                Ok(Opcode::OneArg(OneArg::SkipIfVx(Nybble::from(op))))
-            }
-            op if ((op & DRAW_VX_VY_NIB) == DRAW_VX_VY_NIB) => Ok(
-                Opcode::ThreeArg(ThreeArg::DrawVxVyNib(ThreeNybbles::from(op))),
-            ),
-            op if ((op & VX_EQ_RAND_PLUS_KK) == VX_EQ_RAND_PLUS_KK) => {
-                Ok(Opcode::ThreeArg(ThreeArg::VxEqRandANDKK(
-                    ThreeNybbles::from(op),
-                )))
-            }
-            op if ((op & PC_EQ_V0_PLUS_NNN) == PC_EQ_V0_PLUS_NNN) => {
-                Ok(Opcode::ThreeArg(ThreeArg::PCEqNNNPlusV0(
-                    ThreeNybbles::from(op),
-                )))
-            }
-            op if ((op & I_EQ_NNN) == I_EQ_NNN) => Ok(Opcode::ThreeArg(
-                ThreeArg::SetIToNNN(ThreeNybbles::from(op)),
-            )),
-            op if ((op & SKIP_VX_NOT_VY) == SKIP_VX_NOT_VY) => {
-                Ok(Opcode::TwoArg(TwoArg::SkipVxNEqVy(TwoNybbles::from(op))))
-            }
-            op if ((op & SHIFT_VX_L) == SHIFT_VX_L) => {
-                Ok(Opcode::TwoArg(TwoArg::ShiftVxL(TwoNybbles::from(op))))
-            }
-            op if ((op & VX_EQ_VY_SUB_VX_F) == VX_EQ_VY_SUB_VX_F) => Ok(
-                Opcode::TwoArg(TwoArg::VxEqVySubVxSetF(TwoNybbles::from(op))),
-            ),
-            op if ((op & SHIFT_VX_R) == SHIFT_VX_R) => {
-                Ok(Opcode::TwoArg(TwoArg::ShiftVxR(TwoNybbles::from(op))))
-            }
-            op if ((op & VX_SUB_EQ_VY_F) == VX_SUB_EQ_VY_F) => {
-                Ok(Opcode::TwoArg(TwoArg::VxSubEqVySetF(TwoNybbles::from(op))))
-            }
-            op if ((op & VX_PLUS_EQ_VY_F) == VX_PLUS_EQ_VY_F) => {
-                Ok(Opcode::TwoArg(TwoArg::VxPlusEqVySetF(TwoNybbles::from(op))))
+        match self {
+            InvalidOpcode::DoesntExist(_, word) => {
+                write!(f, "No instruction is encoded as {:04X}", word)
             }
-            op if ((op & VX_XOR_EQ_VY) == VX_XOR_EQ_VY) => {
-                Ok(Opcode::TwoArg(TwoArg::VxXOREqVy(TwoNybbles::from(op))))
+            InvalidOpcode::PcOutOfBounds(reason, _) => {
+                write!(f, "Ran off the end of memory: {}", reason)
             }
-            op if ((op & VX_AND_EQ_VY) == VX_AND_EQ_VY) => {
-                Ok(Opcode::TwoArg(TwoArg::VxANDEqVy(TwoNybbles::from(op))))
+            InvalidOpcode::StackOverflow(..) => {
+                write!(f, "Stack overflow, subroutines nest too deep")
             }
-            op if ((op & VX_OR_EQ_VY) == VX_OR_EQ_VY) => {
-                Ok(Opcode::TwoArg(TwoArg::VxOREqVy(TwoNybbles::from(op))))
+            InvalidOpcode::StackUnderflow(..) => {
+                write!(f, "Returned from a subroutine with an empty stack")
             }
-            op if ((op & VX_EQ_VY) == VX_EQ_VY) => {
-                Ok(Opcode::TwoArg(TwoArg::VxEqVy(TwoNybbles::from(op))))
+            InvalidOpcode::OutOfBoundsAddress(reason, _) => {
+                write!(f, "Memory access out of bounds: {}", reason)
             }
-            op if ((op & VX_PLUS_EQ_KK) == VX_PLUS_EQ_KK) => {
-                Ok(Opcode::ThreeArg(ThreeArg::VxEqVxPlusKK(
-                    ThreeNybbles::from(op),
-                )))
+            InvalidOpcode::NoSuchDigitSprite(reason, _) => {
+                write!(f, "No font sprite: {}", reason)
             }
-            op if ((op & VX_EQ_KK) == VX_EQ_KK) => {
-                Ok(Opcode::ThreeArg(ThreeArg::SetVxKK(ThreeNybbles::from(op))))
+            InvalidOpcode::OutOfScreenBounds(reason, _) => {
+                write!(f, "Drawing out of bounds: {}", reason)
             }
-            op if ((op & SKIP_VX_EQ_VY) == SKIP_VX_EQ_VY) => {
-                Ok(Opcode::TwoArg(TwoArg::SkipEqVxVy(TwoNybbles::from(op))))
+            InvalidOpcode::UnsupportedOnPlatform(reason, _) => {
+                write!(f, "Unsupported instruction: {}", reason)
             }
-            op if ((op & SKIP_VX_NEQ_KK) == SKIP_VX_NEQ_KK) => Ok(
-                Opcode::ThreeArg(ThreeArg::SkipVxNEqKK(ThreeNybbles::from(op))),
-            ),
-            op if ((op & SKIP_VX_EQ_KK) == SKIP_VX_EQ_KK) => Ok(
-                Opcode::ThreeArg(ThreeArg::SkipVxEqKK(ThreeNybbles::from(op))),
-            ),
-            op if ((op & CALL_SUB_AT_ADDR) == CALL_SUB_AT_ADDR) => Ok(
-                Opcode::ThreeArg(ThreeArg::CallSubAt(ThreeNybbles::from(op))),
-            ),
-            op if ((op & JUMP_TO_ADDR) == JUMP_TO_ADDR) => Ok(
-                Opcode::ThreeArg(ThreeArg::JumpToAddr(ThreeNybbles::from(op))),
-            ),
-            op if ((op & JUMP_TO_CODEROUTE) == JUMP_TO_CODEROUTE) => {
-                Ok(Opcode::ThreeArg(ThreeArg::JumpToCodeRout(
-                    ThreeNybbles::from(op),
-                )))
+            InvalidOpcode::NoSuchKey(reason, _) => {
+                write!(f, "No such key: {}", reason)
             }
-            _ => Err(InvalidOpcode::DoesntExist(
-                "Unsupported op".to_string(),
-                op,
-            )),
         }
     }
 }
+
+impl InvalidOpcode {
+    // The instruction that failed, if the word decoded to one
+    pub fn op(&self) -> Option<&Opcode> {
+        match self {
+            InvalidOpcode::DoesntExist(..) | InvalidOpcode::PcOutOfBounds(..) => {
+                None
+            }
+            InvalidOpcode::StackOverflow(_, op)
+            | InvalidOpcode::StackUnderflow(_, op)
+            | InvalidOpcode::OutOfBoundsAddress(_, op)
+            | InvalidOpcode::NoSuchDigitSprite(_, op)
+            | InvalidOpcode::OutOfScreenBounds(_, op)
+            | InvalidOpcode::UnsupportedOnPlatform(_, op)
+            | InvalidOpcode::NoSuchKey(_, op) => Some(op),
+        }
+    }
+}
+
+impl Opcode {
+    // Length of the instruction in bytes. XO-CHIP's long I load is the only
+    // instruction that takes up two words, its address lives in the second.
+    pub fn byte_len(&self) -> u16 {
+        match self {
+            Opcode::NoArg(NoArg::LoadILong) => 4,
+            _ => 2,
+        }
+    }
+
+    // Looks the word up among the instructions sharing its high nibble. The
+    // fixed bits of every instruction have to match exactly, so undefined
+    // words like 5xy1 or 8xyF are rejected instead of decoding to a neighbour.
+    pub fn decode_op(op: u16) -> Result<Opcode, InvalidOpcode> {
+        DECODINGS[(op >> 12) as usize]
+            .iter()
+            .find(|(mask, pattern, _)| op & mask == *pattern)
+            .map(|(_, _, operands)| operands.build(op))
+            .ok_or_else(|| {
+                InvalidOpcode::DoesntExist("Unsupported op".to_string(), op)
+            })
+    }
+
+    // The inverse of decode_op. LoadILong only encodes its first word, the
+    // address that follows it is up to the caller.
+    pub fn encode(&self) -> u16 {
+        match self {
+            Opcode::NoArg(arg) => match arg {
+                NoArg::ClearScreen => CLEAR_SCREEN,
+                NoArg::ReturnSubrt => RET_SUBROUTINE,
+                NoArg::ScrollRight => SCROLL_RIGHT,
+                NoArg::ScrollLeft => SCROLL_LEFT,
+                NoArg::Exit => EXIT,
+                NoArg::LowRes => LOW_RES,
+                NoArg::HighRes => HIGH_RES,
+                NoArg::LoadILong => LOAD_I_LONG,
+                NoArg::LoadAudio => LOAD_AUDIO,
+            },
+            Opcode::OneArg(arg) => match arg {
+                OneArg::SkipIfVx(x) => SKIP_IF_VX | x_bits(x),
+                OneArg::SkipIfNVx(x) => SKIP_IF_NOT_VX | x_bits(x),
+                OneArg::SetVxDT(x) => SET_VX_DT | x_bits(x),
+                OneArg::WaitForKey(x) => WAIT_FOR_KEY | x_bits(x),
+                OneArg::SetDT(x) => SET_DT | x_bits(x),
+                OneArg::SetST(x) => SET_ST | x_bits(x),
+                OneArg::SetI(x) => SET_I | x_bits(x),
+                OneArg::SetSpriteI(x) => SET_SPR_I | x_bits(x),
+                OneArg::StoreDecVx(x) => STORE_DEC_VX | x_bits(x),
+                OneArg::StoreV0Vx(x) => STORE_V0_VX | x_bits(x),
+                OneArg::ReadV0Vx(x) => READ_V0_VX | x_bits(x),
+                OneArg::ScrollDown(n) => SCROLL_DOWN | (x_bits(n) >> 8),
+                OneArg::SetBigSpriteI(x) => SET_BIG_SPR_I | x_bits(x),
+                OneArg::StoreRplVx(x) => STORE_RPL_VX | x_bits(x),
+                OneArg::ReadRplVx(x) => READ_RPL_VX | x_bits(x),
+                OneArg::ScrollUp(n) => SCROLL_UP | (x_bits(n) >> 8),
+                OneArg::SelectPlane(n) => SELECT_PLANE | x_bits(n),
+                OneArg::SetPitch(x) => SET_PITCH | x_bits(x),
+            },
+            Opcode::TwoArg(arg) => match arg {
+                TwoArg::SkipEqVxVy(xy) => SKIP_VX_EQ_VY | xy_bits(xy),
+                TwoArg::VxEqVy(xy) => VX_EQ_VY | xy_bits(xy),
+                TwoArg::VxOREqVy(xy) => VX_OR_EQ_VY | xy_bits(xy),
+                TwoArg::VxANDEqVy(xy) => VX_AND_EQ_VY | xy_bits(xy),
+                TwoArg::VxXOREqVy(xy) => VX_XOR_EQ_VY | xy_bits(xy),
+                TwoArg::VxPlusEqVySetF(xy) => VX_PLUS_EQ_VY_F | xy_bits(xy),
+                TwoArg::VxSubEqVySetF(xy) => VX_SUB_EQ_VY_F | xy_bits(xy),
+                TwoArg::ShiftVxR(xy) => SHIFT_VX_R | xy_bits(xy),
+                TwoArg::VxEqVySubVxSetF(xy) => VX_EQ_VY_SUB_VX_F | xy_bits(xy),
+                TwoArg::ShiftVxL(xy) => SHIFT_VX_L | xy_bits(xy),
+                TwoArg::SkipVxNEqVy(xy) => SKIP_VX_NOT_VY | xy_bits(xy),
+                TwoArg::SaveVxVy(xy) => SAVE_VX_VY | xy_bits(xy),
+                TwoArg::LoadVxVy(xy) => LOAD_VX_VY | xy_bits(xy),
+            },
+            Opcode::ThreeArg(arg) => match arg {
+                ThreeArg::JumpToCodeRout(nnn) => {
+                    JUMP_TO_CODEROUTE | nnn.to_addr()
+                }
+                ThreeArg::JumpToAddr(nnn) => JUMP_TO_ADDR | nnn.to_addr(),
+                ThreeArg::CallSubAt(nnn) => CALL_SUB_AT_ADDR | nnn.to_addr(),
+                ThreeArg::SkipVxEqKK(xkk) => SKIP_VX_EQ_KK | xkk.to_addr(),
+                ThreeArg::SkipVxNEqKK(xkk) => SKIP_VX_NEQ_KK | xkk.to_addr(),
+                ThreeArg::SetVxKK(xkk) => VX_EQ_KK | xkk.to_addr(),
+                ThreeArg::VxEqVxPlusKK(xkk) => VX_PLUS_EQ_KK | xkk.to_addr(),
+                ThreeArg::SetIToNNN(nnn) => I_EQ_NNN | nnn.to_addr(),
+                ThreeArg::PCEqNNNPlusV0(nnn) => {
+                    PC_EQ_V0_PLUS_NNN | nnn.to_addr()
+                }
+                ThreeArg::VxEqRandANDKK(xkk) => {
+                    VX_EQ_RAND_PLUS_KK | xkk.to_addr()
+                }
+                ThreeArg::DrawVxVyNib(xyn) => DRAW_VX_VY_NIB | xyn.to_addr(),
+            },
+        }
+    }
+}
+
+// Places a register number in the x position of an opcode
+fn x_bits(x: &Nybble) -> u16 {
+    x.to_u16().expect("Check u16") << 8
+}
+
+fn xy_bits(xy: &TwoNybbles) -> u16 {
+    x_bits(&xy.x()) | (x_bits(&xy.y()) >> 4)
+}
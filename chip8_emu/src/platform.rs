@@ -0,0 +1,103 @@
+use std::fmt;
+use std::str::FromStr;
+
+use nybble::ThreeNybbles;
+use opcode::NoArg;
+use opcode::OneArg;
+use opcode::Opcode;
+use opcode::ThreeArg;
+use opcode::TwoArg;
+use quirks::Quirks;
+
+const RAM_SIZE: usize = 0x1000;
+const XO_RAM_SIZE: usize = 0x10000;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum Platform {
+    #[default]
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    // Returns whether the instruction exists in this platform's instruction
+    // set. Everything the original CHIP-8 understands is supported everywhere.
+    pub fn supports(&self, op: &Opcode) -> bool {
+        match op {
+            Opcode::NoArg(NoArg::ScrollRight)
+            | Opcode::NoArg(NoArg::ScrollLeft)
+            | Opcode::NoArg(NoArg::Exit)
+            | Opcode::NoArg(NoArg::LowRes)
+            | Opcode::NoArg(NoArg::HighRes)
+            | Opcode::OneArg(OneArg::ScrollDown(_))
+            | Opcode::OneArg(OneArg::SetBigSpriteI(_))
+            | Opcode::OneArg(OneArg::StoreRplVx(_))
+            | Opcode::OneArg(OneArg::ReadRplVx(_)) => *self != Platform::Chip8,
+            Opcode::NoArg(NoArg::LoadILong)
+            | Opcode::NoArg(NoArg::LoadAudio)
+            | Opcode::OneArg(OneArg::ScrollUp(_))
+            | Opcode::OneArg(OneArg::SelectPlane(_))
+            | Opcode::OneArg(OneArg::SetPitch(_))
+            | Opcode::TwoArg(TwoArg::SaveVxVy(_))
+            | Opcode::TwoArg(TwoArg::LoadVxVy(_)) => *self == Platform::XoChip,
+            _ => true,
+        }
+    }
+
+    // The instruction the platform runs for op, if it has one. The original
+    // CHIP-8 only has 00E0 and 00EE among the 00xx words, every other 0nnn
+    // word calls a machine code routine.
+    pub fn resolve(&self, op: &Opcode) -> Option<Opcode> {
+        let word = op.encode();
+        if self.supports(op) {
+            Some(op.clone())
+        } else if *self == Platform::Chip8 && word & 0xF000 == 0 {
+            let nnn = ThreeNybbles::from(word);
+            Some(Opcode::ThreeArg(ThreeArg::JumpToCodeRout(nnn)))
+        } else {
+            None
+        }
+    }
+
+    // The quirks games written for this platform usually expect
+    pub fn default_quirks(&self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::cosmac_vip(),
+            Platform::SuperChip => Quirks::schip(),
+            Platform::XoChip => Quirks::xo_chip(),
+        }
+    }
+
+    pub fn ram_size(&self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::SuperChip => RAM_SIZE,
+            Platform::XoChip => XO_RAM_SIZE,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Platform::Chip8 => write!(f, "chip8"),
+            Platform::SuperChip => write!(f, "schip"),
+            Platform::XoChip => write!(f, "xochip"),
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Platform, String> {
+        match s.to_lowercase().as_str() {
+            "chip8" | "chip-8" => Ok(Platform::Chip8),
+            "schip" | "superchip" | "super-chip" => Ok(Platform::SuperChip),
+            "xochip" | "xo-chip" => Ok(Platform::XoChip),
+            _ => Err(format!("Unknown platform: {}", s)),
+        }
+    }
+}
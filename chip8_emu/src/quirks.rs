@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+// How FX55/FX65 leave the index register after storing or loading registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexIncrement {
+    Unchanged,
+    X,
+    XPlusOne,
+}
+
+// The behaviours that differ between CHIP-8 interpreters. Each flag describes
+// what the interpreter does when it is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quirks {
+    // 8XY6/8XYE shift VX in place instead of shifting VY into VX
+    pub shift: bool,
+    // How far FX55/FX65 move I past the registers they touched
+    pub load_store: IndexIncrement,
+    // 8XY1/8XY2/8XY3 reset VF to 0
+    pub vf_reset: bool,
+    // BNNN behaves as BXNN, jumping to XNN + VX instead of NNN + V0
+    pub jump: bool,
+    // Sprites are clipped at the screen edges instead of wrapping around
+    pub clipping: bool,
+    // DXYN waits for the next vertical blank, limiting drawing to one sprite
+    // per frame
+    pub display_wait: bool,
+    // FX1E sets VF when I moves past the 12 bit address space
+    pub i_overflow: bool,
+}
+
+impl Quirks {
+    pub fn cosmac_vip() -> Quirks {
+        Quirks {
+            shift: false,
+            load_store: IndexIncrement::XPlusOne,
+            vf_reset: true,
+            jump: false,
+            clipping: true,
+            display_wait: true,
+            i_overflow: false,
+        }
+    }
+
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift: true,
+            load_store: IndexIncrement::X,
+            vf_reset: false,
+            jump: true,
+            clipping: true,
+            display_wait: false,
+            i_overflow: false,
+        }
+    }
+
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift: true,
+            load_store: IndexIncrement::Unchanged,
+            vf_reset: false,
+            jump: true,
+            clipping: true,
+            display_wait: false,
+            i_overflow: false,
+        }
+    }
+
+    pub fn xo_chip() -> Quirks {
+        Quirks {
+            shift: false,
+            load_store: IndexIncrement::XPlusOne,
+            vf_reset: false,
+            jump: false,
+            clipping: false,
+            display_wait: false,
+            i_overflow: false,
+        }
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Quirks, String> {
+        match s.to_lowercase().as_str() {
+            "vip" | "cosmac-vip" => Ok(Quirks::cosmac_vip()),
+            "chip48" | "chip-48" => Ok(Quirks::chip48()),
+            "schip" | "superchip" | "super-chip" => Ok(Quirks::schip()),
+            "xochip" | "xo-chip" => Ok(Quirks::xo_chip()),
+            _ => Err(format!("Unknown quirks preset: {}", s)),
+        }
+    }
+}
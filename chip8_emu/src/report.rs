@@ -0,0 +1,192 @@
+use std::error::Error;
+use std::fmt;
+
+use error::Fault;
+use opcode::Opcode;
+use platform::Platform;
+use quirks::Quirks;
+use state::StateError;
+use Chip8;
+
+// How many of the most recent instructions a crash report shows
+pub const HISTORY_LEN: usize = 32;
+pub const REPORT_VERSION: u16 = 1;
+const RAM_DUMP_WIDTH: usize = 16;
+
+// The addresses and words of the last instructions the machine started,
+// oldest first. The word is kept so self-modifying code shows what actually
+// ran rather than what is in memory now.
+// This runs every cycle, so it's a fixed ring rather than a queue.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: [(u16, u16); HISTORY_LEN],
+    // Where the next entry goes, and how many entries are filled in
+    next: usize,
+    len: usize,
+}
+
+impl History {
+    pub fn record(&mut self, pc: u16, word: u16) {
+        self.entries[self.next] = (pc, word);
+        self.next = (self.next + 1) % HISTORY_LEN;
+        self.len = (self.len + 1).min(HISTORY_LEN);
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &(u16, u16)> {
+        let start = (self.next + HISTORY_LEN - self.len) % HISTORY_LEN;
+        (0..self.len).map(move |n| &self.entries[(start + n) % HISTORY_LEN])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterDump {
+    pub pc: u16,
+    pub i: u16,
+    pub sp: u8,
+    pub delay: u8,
+    pub sound: u8,
+    pub v: [u8; 16],
+    pub stack: Vec<u16>,
+}
+
+impl RegisterDump {
+    pub fn of(emu: &Chip8) -> RegisterDump {
+        let regs = &emu.cpu.regs;
+        RegisterDump {
+            pc: regs.pc(),
+            i: regs.i_reg(),
+            sp: regs.sp(),
+            delay: regs.delay,
+            sound: regs.sound,
+            v: regs.v_regs,
+            stack: emu.cpu.stack.entries(regs.sp()).to_vec(),
+        }
+    }
+}
+
+// Everything about a machine at the moment it faulted. The readable parts are
+// for whoever looks into the bug, the save state lets the machine be loaded
+// back exactly as it was.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub version: u16,
+    pub fault: String,
+    pub rom_hash: String,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub registers: RegisterDump,
+    pub history: Vec<String>,
+    pub screen: Vec<String>,
+    pub ram: Vec<String>,
+    pub state: String,
+}
+
+impl CrashReport {
+    pub fn new(emu: &Chip8, fault: &Fault) -> CrashReport {
+        let history = emu
+            .history()
+            .entries()
+            .map(|(pc, word)| match Opcode::decode_op(*word) {
+                Ok(op) => format!("{:03X}  {:?}", pc, op),
+                Err(_) => format!("{:03X}  {:04X}", pc, word),
+            })
+            .collect();
+        let screen = format!("{:?}", emu.cpu.screen)
+            .lines()
+            .map(|line| line.replace(' ', "."))
+            .collect();
+        let ram = (0..emu.cpu.ram.len())
+            .step_by(RAM_DUMP_WIDTH)
+            .map(|start| {
+                let bytes: Vec<String> = (start..start + RAM_DUMP_WIDTH)
+                    .map(|addr| format!("{:02X}", emu.cpu.ram.peek(addr)))
+                    .collect();
+                format!("{:04X}: {}", start, bytes.join(" "))
+            })
+            .collect();
+        CrashReport {
+            version: REPORT_VERSION,
+            fault: fault.to_string(),
+            rom_hash: format!("{:016x}", emu.rom_hash()),
+            platform: emu.cpu.platform,
+            quirks: emu.cpu.quirks,
+            registers: RegisterDump::of(emu),
+            history,
+            screen,
+            ram,
+            state: to_hex(&emu.save_state()),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Reports always serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<CrashReport, ReportError> {
+        let report: CrashReport = serde_json::from_str(json)
+            .map_err(|err| ReportError::Corrupt(err.to_string()))?;
+        if report.version != REPORT_VERSION {
+            return Err(ReportError::UnsupportedVersion(report.version));
+        }
+        Ok(report)
+    }
+
+    // Puts the machine back into the state it faulted in. It has to be
+    // running the same rom.
+    pub fn restore(&self, emu: &mut Chip8) -> Result<(), ReportError> {
+        let state = from_hex(&self.state).ok_or_else(|| {
+            ReportError::Corrupt("State is not hexadecimal".to_string())
+        })?;
+        emu.load_state(&state).map_err(ReportError::State)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// None unless every pair of characters is a hex byte, so an odd length fails
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum ReportError {
+    UnsupportedVersion(u16),
+    Corrupt(String),
+    State(StateError),
+}
+
+impl Error for ReportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReportError::State(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReportError::UnsupportedVersion(version) => write!(
+                f,
+                "Crash report has format version {}, expected {}",
+                version, REPORT_VERSION
+            ),
+            ReportError::Corrupt(reason) => {
+                write!(f, "Crash report is corrupt: {}", reason)
+            }
+            ReportError::State(err) => {
+                write!(f, "Could not restore the crash report: {}", err)
+            }
+        }
+    }
+}
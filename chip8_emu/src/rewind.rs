@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+
+use state;
+use Chip8;
+
+// Keeps a bounded history of machine snapshots to run the emulator backwards.
+// Only the newest snapshot is stored in full, every older one is stored as
+// the difference to the snapshot that followed it. Consecutive frames barely
+// differ, so the differences compress down to a few bytes each.
+pub struct Rewind {
+    interval: u32,
+    capacity: usize,
+    frames_since_snapshot: u32,
+    newest: Option<Vec<u8>>,
+    // Oldest first, each entry turns the snapshot after it back into itself
+    deltas: VecDeque<Vec<u8>>,
+}
+
+impl Rewind {
+    // Takes a snapshot every `interval` frames and keeps at most `capacity`
+    // of them around.
+    pub fn new(interval: u32, capacity: usize) -> Rewind {
+        Rewind {
+            interval: interval.max(1),
+            capacity: capacity.max(1),
+            frames_since_snapshot: 0,
+            newest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    // Call once per emulated frame
+    pub fn record(&mut self, emu: &Chip8) {
+        self.frames_since_snapshot += 1;
+        if self.frames_since_snapshot < self.interval {
+            return;
+        }
+        self.frames_since_snapshot = 0;
+
+        let snapshot = state::serialize_cpu(&emu.cpu);
+        if let Some(previous) = self.newest.take() {
+            self.deltas.push_back(encode_delta(&snapshot, &previous));
+            if self.deltas.len() >= self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+        self.newest = Some(snapshot);
+    }
+
+    // Restores the most recent snapshot older than the machine's current
+    // state and drops it from the history. The newest snapshot is usually of
+    // the frame on screen, restoring it would look like nothing happened.
+    // Returns false once there is nothing left to go back to.
+    pub fn step_back(&mut self, emu: &mut Chip8) -> bool {
+        if self.newest == Some(state::serialize_cpu(&emu.cpu)) {
+            self.pop_newest();
+        }
+        self.frames_since_snapshot = 0;
+        let snapshot = match self.pop_newest() {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+        match state::deserialize_cpu(&snapshot) {
+            Ok(cpu) => {
+                emu.cpu = cpu;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    // Takes the newest snapshot out, rebuilding the one before it
+    fn pop_newest(&mut self) -> Option<Vec<u8>> {
+        let snapshot = self.newest.take()?;
+        self.newest = self
+            .deltas
+            .pop_back()
+            .and_then(|delta| decode_delta(&snapshot, &delta));
+        Some(snapshot)
+    }
+
+    pub fn len(&self) -> usize {
+        self.newest.as_ref().map_or(0, |_| self.deltas.len() + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+        self.frames_since_snapshot = 0;
+    }
+}
+
+// A delta is the length of the target followed by alternating runs of
+// unchanged bytes and changed bytes, where changed bytes are stored XORed
+// with the base they apply to.
+fn encode_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let diff: Vec<u8> = target
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| byte ^ base.get(index).unwrap_or(&0))
+        .collect();
+    let mut delta = Vec::new();
+    write_varint(&mut delta, target.len());
+    let mut index = 0;
+    while index < diff.len() {
+        let same = diff[index..].iter().take_while(|byte| **byte == 0).count();
+        index += same;
+        let changed =
+            diff[index..].iter().take_while(|byte| **byte != 0).count();
+        write_varint(&mut delta, same);
+        write_varint(&mut delta, changed);
+        delta.extend_from_slice(&diff[index..index + changed]);
+        index += changed;
+    }
+    delta
+}
+
+// Returns None for a delta that doesn't fit the base, which can only happen
+// if the history got mixed up
+fn decode_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    let len = read_varint(delta, &mut pos)?;
+    let mut target: Vec<u8> = (0..len)
+        .map(|index| *base.get(index).unwrap_or(&0))
+        .collect();
+    let mut index: usize = 0;
+    while pos < delta.len() {
+        index = index.checked_add(read_varint(delta, &mut pos)?)?;
+        let changed = read_varint(delta, &mut pos)?;
+        let bytes = delta.get(pos..pos.checked_add(changed)?)?;
+        let targets = target.get_mut(index..index.checked_add(changed)?)?;
+        for (target, byte) in targets.iter_mut().zip(bytes) {
+            *target ^= byte;
+        }
+        index += changed;
+        pos += changed;
+    }
+    Some(target)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut value: usize = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        let bits = ((byte & 0x7F) as usize).checked_shl(shift)?;
+        value |= bits;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
@@ -0,0 +1,53 @@
+use cpu::Ram;
+
+// Stand in for xorshift's state when seeded with 0, which it can't leave
+const ZERO_SEED: u64 = 0x9E3779B97F4A7C15;
+const XORSHIFT_MULTIPLIER: u64 = 0x2545F4914F6CDD1D;
+
+// The random number generator behind CXKK. It is part of the machine state,
+// so a given seed always produces the same run and save states restore it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rng {
+    Xorshift(u64),
+    // The COSMAC VIP interpreter walks a pointer, advanced by every call and
+    // every 60 Hz interrupt, through a page of its own memory and adds the
+    // byte found there to its previous result. The VIP interpreter itself
+    // isn't in our memory, so the page holding the fonts stands in for it.
+    CosmacVip { index: u8, last: u8 },
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng::Xorshift(if seed == 0 { ZERO_SEED } else { seed })
+    }
+
+    pub fn cosmac_vip(seed: u64) -> Rng {
+        Rng::CosmacVip {
+            index: seed as u8,
+            last: (seed >> 8) as u8,
+        }
+    }
+
+    pub fn next_byte(&mut self, ram: &Ram) -> u8 {
+        match self {
+            Rng::Xorshift(state) => {
+                *state ^= *state >> 12;
+                *state ^= *state << 25;
+                *state ^= *state >> 27;
+                (state.wrapping_mul(XORSHIFT_MULTIPLIER) >> 56) as u8
+            }
+            Rng::CosmacVip { index, last } => {
+                *index = index.wrapping_add(1);
+                *last = last.wrapping_add(ram.peek(*index as usize));
+                *last
+            }
+        }
+    }
+
+    // Called once per frame, mirroring the VIP's interrupt routine
+    pub fn tick(&mut self) {
+        if let Rng::CosmacVip { index, .. } = self {
+            *index = index.wrapping_add(1);
+        }
+    }
+}
@@ -1,43 +1,141 @@
 use std::fmt;
 use nybble::Nybble;
 use cpu::Ram;
+use num::ToPrimitive;
 
-const SCREEN_WIDTH: usize = 64;
-const SCREEN_HEIGHT: usize = 32;
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
 
-#[derive(Clone)]
+// XO-CHIP has two bitplanes, each pixel stores one bit per plane
+pub const PLANES: usize = 2;
+pub const ALL_PLANES: u8 = 0b11;
+// Colours for each combination of XO-CHIP's two bitplanes: neither, the first,
+// the second and both. Plain CHIP-8 only ever uses the first two.
+pub const PALETTE: [[u8; 3]; 4] = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0xAA, 0xAA, 0xAA],
+    [0x55, 0x55, 0x55],
+];
+
+// SUPER-CHIP scrolls horizontally by a fixed amount of pixels
+const HORIZONTAL_SCROLL: isize = 4;
+const LARGE_SPRITE_BYTES: usize = 32;
+const TALL_SPRITE_ROWS: usize = 16;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Screen {
-    pub buffer: Box<[[bool; SCREEN_WIDTH]; SCREEN_HEIGHT]>,
+    pub buffer: Vec<u8>,
     pub height: usize,
     pub width: usize,
+    pub hires: bool,
+    // Bitmask of the planes that drawing, clearing and scrolling apply to
+    pub planes: u8,
+    // Whether sprites are cut off at the screen edges or wrap around
+    pub clipping: bool,
 }
 
 impl fmt::Debug for Screen {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut screen_string = "".to_string();
-        for y in 0..SCREEN_HEIGHT {
-            for x in 0..SCREEN_WIDTH {
-                if self.buffer[y][x] {
-                    screen_string.push_str("*");
-                } else {
-                    screen_string.push_str(" ");
+        for y in 0..self.height {
+            for x in 0..self.width {
+                match self.get_pixel(x, y) {
+                    0 => screen_string.push(' '),
+                    1 => screen_string.push('*'),
+                    2 => screen_string.push('+'),
+                    _ => screen_string.push('#'),
                 }
             }
-            screen_string.push_str("\n");
+            screen_string.push('\n');
         }
         write!(f, "{}", screen_string)
     }
 }
 
 impl Screen {
-    pub fn new() -> Screen {
+    pub fn new(clipping: bool) -> Screen {
         Screen {
-            buffer: Box::new([[false; SCREEN_WIDTH]; SCREEN_HEIGHT]),
-            height: SCREEN_HEIGHT,
-            width: SCREEN_WIDTH,
+            buffer: vec![0; LORES_WIDTH * LORES_HEIGHT],
+            height: LORES_HEIGHT,
+            width: LORES_WIDTH,
+            hires: false,
+            planes: 1,
+            clipping,
         }
     }
 
+    // Returns the planes lit at the given pixel as a bitmask, which doubles as
+    // an index into a four colour palette.
+    pub fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        self.buffer[y * self.width + x]
+    }
+
+    pub fn clear(&mut self) {
+        let planes = self.planes;
+        self.buffer.iter_mut().for_each(|pixel| *pixel &= !planes);
+    }
+
+    // Switching resolution resizes the buffer, which also clears the screen
+    pub fn set_hires(&mut self, hires: bool) {
+        let (width, height) = if hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (LORES_WIDTH, LORES_HEIGHT)
+        };
+        self.hires = hires;
+        self.width = width;
+        self.height = height;
+        self.buffer = vec![0; width * height];
+    }
+
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & ALL_PLANES;
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.scroll(0, rows as isize);
+    }
+
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.scroll(0, -(rows as isize));
+    }
+
+    pub fn scroll_right(&mut self) {
+        self.scroll(HORIZONTAL_SCROLL, 0);
+    }
+
+    pub fn scroll_left(&mut self) {
+        self.scroll(-HORIZONTAL_SCROLL, 0);
+    }
+
+    // Moves the selected planes by the given offset, leaving the other planes
+    // in place and filling the uncovered area with unlit pixels.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let old = self.buffer.clone();
+        let (width, height) = (self.width as isize, self.height as isize);
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let moved = if src_x >= 0
+                    && src_x < width
+                    && src_y >= 0
+                    && src_y < height
+                {
+                    old[(src_y * width + src_x) as usize] & self.planes
+                } else {
+                    0
+                };
+                let index = (y * width + x) as usize;
+                self.buffer[index] = (old[index] & !self.planes) | moved;
+            }
+        }
+    }
+
+    // Draws an 8 pixel wide sprite. With more than one plane selected the
+    // sprite data for each plane follows the previous one in memory.
     pub fn draw_nybble(
         &mut self,
         x: u8,
@@ -47,29 +145,122 @@ impl Screen {
         collision_flag: &mut u8,
         ram: &Ram,
     ) -> Result<(), String> {
-        if x > (SCREEN_WIDTH as u8) || y > (SCREEN_HEIGHT as u8) {
-            return Err(format!("Out of screen bounds: {0}, {1}", x, y));
-        }
-        let sprite = ram.retrieve_bytes(ram_index, num_bytes);
+        let len = num_bytes.to_usize().expect("Can't fail");
+        self.draw_planes(x, y, ram_index, len, 1, collision_flag, ram)
+    }
+
+    // SUPER-CHIP's high resolution Dxy0, and XO-CHIP's in either resolution,
+    // draws a 16x16 sprite stored as two bytes per row
+    pub fn draw_large(
+        &mut self,
+        x: u8,
+        y: u8,
+        ram_index: u16,
+        collision_flag: &mut u8,
+        ram: &Ram,
+    ) -> Result<(), String> {
+        self.draw_planes(
+            x,
+            y,
+            ram_index,
+            LARGE_SPRITE_BYTES,
+            2,
+            collision_flag,
+            ram,
+        )
+    }
+
+    // SUPER-CHIP's low resolution Dxy0 draws an 8x16 sprite, like CHIP-48
+    pub fn draw_tall(
+        &mut self,
+        x: u8,
+        y: u8,
+        ram_index: u16,
+        collision_flag: &mut u8,
+        ram: &Ram,
+    ) -> Result<(), String> {
+        self.draw_planes(
+            x,
+            y,
+            ram_index,
+            TALL_SPRITE_ROWS,
+            1,
+            collision_flag,
+            ram,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_planes(
+        &mut self,
+        x: u8,
+        y: u8,
+        ram_index: u16,
+        len: usize,
+        bytes_per_row: usize,
+        collision_flag: &mut u8,
+        ram: &Ram,
+    ) -> Result<(), String> {
         *collision_flag = 0;
-        for byte_num in 0..sprite.len() {
-            for bit in 0..8 {
-                let pixel_val = get_bit(sprite[byte_num], bit)
-                    .expect("Iterator went over 8");
-                let y_cord = Some((y as usize + byte_num) % (SCREEN_HEIGHT));
-                let x_cord = Some((x + bit) as usize % (SCREEN_WIDTH));
-                *collision_flag |= (pixel_val
-                    & self.buffer[y_cord.expect("Should've gotten an x value")]
-                        [x_cord.expect("Should've gotten a y value")])
-                    as u8;
-
-                self.buffer[y_cord.expect("Should've gotten an x value")]
-                    [x_cord.expect("Should've gotten a y value")] ^= pixel_val
+        let mut index = ram_index as usize;
+        for plane in 0..PLANES {
+            let plane_bit = 1 << plane;
+            if self.planes & plane_bit == 0 {
+                continue;
             }
+            let sprite = ram.retrieve_range(index as u16, len)?;
+            self.draw_sprite(
+                x,
+                y,
+                sprite,
+                bytes_per_row,
+                plane_bit,
+                collision_flag,
+            );
+            index += len;
         }
 
         Ok(())
     }
+
+    // The starting coordinates always wrap, only the parts of the sprite that
+    // run off the screen are subject to clipping.
+    fn draw_sprite(
+        &mut self,
+        x: u8,
+        y: u8,
+        sprite: &[u8],
+        bytes_per_row: usize,
+        plane_bit: u8,
+        collision_flag: &mut u8,
+    ) {
+        let start_x = x as usize % self.width;
+        let start_y = y as usize % self.height;
+        for (row, row_bytes) in sprite.chunks(bytes_per_row).enumerate() {
+            let y_cord = start_y + row;
+            if y_cord >= self.height && self.clipping {
+                break;
+            }
+            let y_cord = y_cord % self.height;
+            for (byte_num, byte) in row_bytes.iter().enumerate() {
+                for bit in 0..8 {
+                    if !get_bit(*byte, bit).expect("Iterator went over 8") {
+                        continue;
+                    }
+                    let x_cord = start_x + byte_num * 8 + bit as usize;
+                    if x_cord >= self.width && self.clipping {
+                        continue;
+                    }
+                    let x_cord = x_cord % self.width;
+                    let index = y_cord * self.width + x_cord;
+                    if self.buffer[index] & plane_bit != 0 {
+                        *collision_flag = 1;
+                    }
+                    self.buffer[index] ^= plane_bit;
+                }
+            }
+        }
+    }
 }
 
 // Note, this will return error if you attempt to pass in a value over 7, as it
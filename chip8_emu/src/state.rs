@@ -0,0 +1,166 @@
+use std::error::Error;
+use std::fmt;
+
+use bincode;
+
+use cpu::Cpu;
+use cpu::STACK_SLOTS;
+use hash::fnv1a;
+use platform::Platform;
+use quirks::Quirks;
+use screen::ALL_PLANES;
+use screen::HIRES_HEIGHT;
+use screen::HIRES_WIDTH;
+use screen::LORES_HEIGHT;
+use screen::LORES_WIDTH;
+use FRAME_RATE;
+
+const MAGIC: &[u8; 4] = b"C8ST";
+// Bump whenever the layout of the serialized machine changes
+pub const STATE_VERSION: u16 = 3;
+
+// Everything in front of the serialized machine. The magic and version come
+// first and are written by hand so that they can always be read back, even
+// when the rest of the format changes between versions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateHeader {
+    pub rom_hash: u64,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub checksum: u64,
+}
+
+#[derive(Debug)]
+pub enum StateError {
+    NotAState,
+    UnsupportedVersion(u16),
+    RomMismatch { expected: u64, found: u64 },
+    ChecksumMismatch,
+    Corrupt(String),
+}
+
+impl Error for StateError {}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::NotAState => write!(f, "Data is not a save state"),
+            StateError::UnsupportedVersion(version) => write!(
+                f,
+                "Save state has format version {}, expected {}",
+                version, STATE_VERSION
+            ),
+            StateError::RomMismatch { expected, found } => write!(
+                f,
+                "Save state belongs to rom {:016x}, but rom {:016x} is loaded",
+                found, expected
+            ),
+            StateError::ChecksumMismatch => {
+                write!(f, "Save state checksum does not match its contents")
+            }
+            StateError::Corrupt(reason) => {
+                write!(f, "Save state is corrupt: {}", reason)
+            }
+        }
+    }
+}
+
+pub fn serialize_cpu(cpu: &Cpu) -> Vec<u8> {
+    bincode::serde::encode_to_vec(cpu, bincode::config::standard())
+        .expect("The machine state is always serializable")
+}
+
+pub fn deserialize_cpu(bytes: &[u8]) -> Result<Cpu, StateError> {
+    bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+        .map(|(cpu, _)| cpu)
+        .map_err(|err| StateError::Corrupt(err.to_string()))
+}
+
+pub fn save(cpu: &Cpu, rom_hash: u64) -> Vec<u8> {
+    let payload = serialize_cpu(cpu);
+    let header = StateHeader {
+        rom_hash,
+        platform: cpu.platform,
+        quirks: cpu.quirks,
+        checksum: fnv1a(&payload),
+    };
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&STATE_VERSION.to_le_bytes());
+    bytes.extend(
+        bincode::serde::encode_to_vec(&header, bincode::config::standard())
+            .expect("The header is always serializable"),
+    );
+    bytes.extend(payload);
+    bytes
+}
+
+pub fn load(bytes: &[u8], rom_hash: u64) -> Result<Cpu, StateError> {
+    let header_start = MAGIC.len() + 2;
+    if bytes.len() < header_start || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(StateError::NotAState);
+    }
+    let version =
+        u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+    if version != STATE_VERSION {
+        return Err(StateError::UnsupportedVersion(version));
+    }
+    let (header, header_len): (StateHeader, usize) =
+        bincode::serde::decode_from_slice(
+            &bytes[header_start..],
+            bincode::config::standard(),
+        )
+        .map_err(|err| StateError::Corrupt(err.to_string()))?;
+    if header.rom_hash != rom_hash {
+        return Err(StateError::RomMismatch {
+            expected: rom_hash,
+            found: header.rom_hash,
+        });
+    }
+    let payload = &bytes[header_start + header_len..];
+    if fnv1a(payload) != header.checksum {
+        return Err(StateError::ChecksumMismatch);
+    }
+    let cpu = deserialize_cpu(payload)?;
+    if cpu.platform != header.platform || cpu.quirks != header.quirks {
+        return Err(StateError::Corrupt(
+            "Header does not match the machine state".to_string(),
+        ));
+    }
+    check_machine(&cpu)?;
+    Ok(cpu)
+}
+
+// Refuses machines the emulator would index out of bounds on. PC and I may
+// hold any address, fetches and accesses past the end of memory are faults.
+fn check_machine(cpu: &Cpu) -> Result<(), StateError> {
+    let corrupt = |reason: &str| Err(StateError::Corrupt(reason.to_string()));
+    if cpu.ram.len() != cpu.platform.ram_size() {
+        return corrupt("Memory size does not match the platform");
+    }
+    let screen = &cpu.screen;
+    let size = if screen.hires {
+        (HIRES_WIDTH, HIRES_HEIGHT)
+    } else {
+        (LORES_WIDTH, LORES_HEIGHT)
+    };
+    if (screen.width, screen.height) != size
+        || screen.buffer.len() != screen.width * screen.height
+    {
+        return corrupt("Screen size does not match its resolution");
+    }
+    if screen.planes & !ALL_PLANES != 0
+        || screen.buffer.iter().any(|pixel| pixel & !ALL_PLANES != 0)
+    {
+        return corrupt("Screen uses bitplanes that don't exist");
+    }
+    if cpu.regs.sp() as usize >= STACK_SLOTS {
+        return corrupt("Stack pointer is past the end of the stack");
+    }
+    if cpu.keyboard.wait_press.is_some_and(|reg| reg > 0xF) {
+        return corrupt("Waiting for a key press into a missing register");
+    }
+    if cpu.timer_phase >= FRAME_RATE {
+        return corrupt("Timers are more than a frame behind");
+    }
+    Ok(())
+}
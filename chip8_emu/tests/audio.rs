@@ -0,0 +1,78 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Beeper;
+use chip8::DEFAULT_VOLUME;
+use common::machine;
+
+// Loops forever
+const ROM: [u8; 2] = [0x12, 0x00];
+// Samples it takes a beep to fade all the way in or out, at 44100Hz
+const FADE_SAMPLES: usize = 221;
+
+fn frame(beeper: &mut Beeper, sound_timer: u8) -> Vec<f32> {
+    let mut chip8 = machine(&ROM);
+    chip8.cpu.regs.sound = sound_timer;
+    let mut samples = Vec::new();
+    beeper.render_frame(&chip8, &mut samples);
+    samples
+}
+
+#[test]
+fn frames_hold_a_sixtieth_of_a_second() {
+    let mut beeper = Beeper::new(44100);
+    for _ in 0..10 {
+        assert_eq!(frame(&mut beeper, 0).len(), 735);
+    }
+    // 367.5 samples a frame, the half sample is carried to the next one
+    let mut beeper = Beeper::new(22050);
+    let lengths: Vec<usize> =
+        (0..4).map(|_| frame(&mut beeper, 0).len()).collect();
+    assert_eq!(lengths, [367, 368, 367, 368]);
+    let total: usize = (0..60).map(|_| frame(&mut beeper, 0).len()).sum();
+    assert_eq!(total, 22050);
+}
+
+#[test]
+fn silent_while_sound_timer_is_zero() {
+    let mut beeper = Beeper::new(44100);
+    assert!(frame(&mut beeper, 0).iter().all(|sample| *sample == 0.0));
+    let mut beeper = Beeper::new(44100);
+    beeper.set_muted(true);
+    assert!(frame(&mut beeper, 10).iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn beeps_fade_in_and_out() {
+    let mut beeper = Beeper::new(44100);
+    let loudness: Vec<f32> =
+        frame(&mut beeper, 10).iter().map(|s| s.abs()).collect();
+    assert!(loudness[0] > 0.0 && loudness[0] < 0.01);
+    assert!(loudness[..FADE_SAMPLES].windows(2).all(|w| w[0] < w[1]));
+    assert!(loudness[FADE_SAMPLES..]
+        .iter()
+        .all(|s| *s == DEFAULT_VOLUME));
+    // The timer running out fades the beep rather than cutting it off
+    let loudness: Vec<f32> =
+        frame(&mut beeper, 0).iter().map(|s| s.abs()).collect();
+    assert!(loudness[0] > DEFAULT_VOLUME * 0.99);
+    assert!(loudness[..FADE_SAMPLES].windows(2).all(|w| w[0] > w[1]));
+    assert!(loudness[FADE_SAMPLES..].iter().all(|s| *s == 0.0));
+}
+
+#[test]
+fn stopping_fades_out_what_is_still_sounding() {
+    let mut beeper = Beeper::new(44100);
+    let mut samples = Vec::new();
+    beeper.render_fade_out(&mut samples);
+    assert!(samples.is_empty(), "Nothing to fade out");
+    frame(&mut beeper, 10);
+    beeper.render_fade_out(&mut samples);
+    assert!(samples.len() <= FADE_SAMPLES);
+    assert!(samples[0].abs() > DEFAULT_VOLUME * 0.99);
+    assert!(samples.last().expect("Fade").abs() < 0.01);
+    samples.clear();
+    beeper.render_fade_out(&mut samples);
+    assert!(samples.is_empty(), "Already faded out");
+}
@@ -0,0 +1,98 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Chip8;
+use chip8::Rng;
+use chip8::DEFAULT_CLOCK_SPEED;
+use chip8::FRAME_RATE;
+use chip8::MAX_CLOCK_SPEED;
+use chip8::MAX_TIMER_RATE;
+use common::machine;
+
+// v0 += 1, then loops back to the start
+const ROM: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+fn cycles_over_a_second(chip8: &mut Chip8) -> Vec<u32> {
+    (0..FRAME_RATE).map(|_| chip8.cycles_for_frame()).collect()
+}
+
+#[test]
+fn default_clock_runs_evenly() {
+    let mut chip8 = machine(&ROM);
+    assert_eq!(chip8.clock_speed(), DEFAULT_CLOCK_SPEED);
+    let cycles = cycles_over_a_second(&mut chip8);
+    assert!(cycles
+        .iter()
+        .all(|&n| n == DEFAULT_CLOCK_SPEED / FRAME_RATE));
+}
+
+#[test]
+fn uneven_clock_speeds_add_up_over_a_second() {
+    let mut chip8 = machine(&ROM);
+    for &speed in [550, 1, 59, 61, 1000].iter() {
+        chip8.set_clock_speed(speed);
+        let cycles = cycles_over_a_second(&mut chip8);
+        assert_eq!(cycles.iter().sum::<u32>(), speed, "{} Hz", speed);
+        let (min, max) = (cycles.iter().min(), cycles.iter().max());
+        assert!(max.unwrap() - min.unwrap() <= 1, "{} Hz", speed);
+    }
+}
+
+// The delay timer after each of the given number of frames, starting at 200
+fn delay_over_frames(timer_rate: u32, frames: usize) -> Vec<u8> {
+    let mut chip8 = machine(&ROM);
+    chip8.set_timer_rate(timer_rate);
+    chip8.cpu.regs.delay = 200;
+    (0..frames)
+        .map(|_| {
+            chip8.run_frame(1).expect("Rom runs");
+            chip8.cpu.regs.delay
+        })
+        .collect()
+}
+
+#[test]
+fn timers_count_down_at_the_timer_rate() {
+    assert_eq!(delay_over_frames(60, 4), [199, 198, 197, 196]);
+    assert_eq!(delay_over_frames(120, 4), [198, 196, 194, 192]);
+    assert_eq!(delay_over_frames(30, 4), [200, 199, 199, 198]);
+    assert_eq!(delay_over_frames(90, 4), [199, 197, 196, 194]);
+    let second = delay_over_frames(45, FRAME_RATE as usize);
+    assert_eq!(second.last(), Some(&155));
+}
+
+#[test]
+fn huge_rates_are_capped_instead_of_overflowing() {
+    let mut chip8 = machine(&ROM);
+    chip8.set_timer_rate(u32::MAX);
+    assert_eq!(chip8.timer_rate(), MAX_TIMER_RATE);
+    chip8.cpu.regs.delay = 0xFF;
+    chip8.run_frame(0).expect("Rom runs");
+    assert_eq!(chip8.cpu.regs.delay, 0);
+
+    chip8.set_clock_speed(u32::MAX);
+    assert_eq!(chip8.clock_speed(), MAX_CLOCK_SPEED);
+    let cycles = cycles_over_a_second(&mut chip8);
+    let total: u64 = cycles.iter().map(|&n| n as u64).sum();
+    assert_eq!(total, MAX_CLOCK_SPEED as u64);
+}
+
+#[test]
+fn reset_starts_the_rom_over() {
+    let mut chip8 = machine(&ROM);
+    chip8.set_clock_speed(600);
+    chip8.cpu.rpl_flags[0] = 0xAB;
+    chip8.run_frame(10).expect("Rom runs");
+    assert_eq!(chip8.cpu.regs.v_regs[0], 5);
+
+    chip8.reset(&ROM, Rng::new(0)).expect("Rom fits");
+    assert_eq!(chip8.state_hash(), {
+        let mut fresh = machine(&ROM);
+        fresh.cpu.rpl_flags[0] = 0xAB;
+        fresh.state_hash()
+    });
+    assert_eq!(chip8.cpu.regs.pc(), 0x200);
+    assert_eq!(chip8.clock_speed(), 600);
+    assert_eq!(chip8.cpu.rpl_flags[0], 0xAB);
+}
@@ -0,0 +1,17 @@
+// Helpers shared by the integration tests, not every test uses all of them
+#![allow(dead_code)]
+
+use chip8::Chip8;
+use chip8::Platform;
+use chip8::Rng;
+
+// The rom on the given platform with its own quirks and a fixed seed
+pub fn machine_on(rom: &[u8], platform: Platform) -> Chip8 {
+    Chip8::new(rom, platform, platform.default_quirks(), Rng::new(0))
+        .expect("Rom fits")
+}
+
+// The rom on the original CHIP-8, with the COSMAC VIP's quirks
+pub fn machine(rom: &[u8]) -> Chip8 {
+    machine_on(rom, Platform::Chip8)
+}
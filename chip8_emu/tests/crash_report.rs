@@ -0,0 +1,81 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Chip8;
+use chip8::CrashReport;
+use chip8::Fault;
+use chip8::ReportError;
+use chip8::HISTORY_LEN;
+use common::machine;
+
+// Counts v0 up to 40, then returns with nothing on the stack
+const ROM: [u8; 10] =
+    [0x70, 0x01, 0x30, 0x28, 0x12, 0x00, 0xA2, 0x34, 0x00, 0xEE];
+
+fn run_until_fault(chip8: &mut Chip8) -> Fault {
+    (0..1000)
+        .find_map(|_| chip8.run_cycle().err())
+        .expect("Rom faults")
+}
+
+#[test]
+fn report_describes_the_machine() {
+    let mut chip8 = machine(&ROM);
+    let fault = run_until_fault(&mut chip8);
+    let report = CrashReport::new(&chip8, &fault);
+
+    assert_eq!(report.fault, fault.to_string());
+    assert_eq!(report.rom_hash, format!("{:016x}", chip8.rom_hash()));
+    assert_eq!(report.registers.pc, 0x208);
+    assert_eq!(report.registers.i, 0x234);
+    assert_eq!(report.registers.v[0], 40);
+    assert_eq!(report.history.len(), HISTORY_LEN);
+    let last = report.history.last().unwrap();
+    assert!(
+        last.starts_with("208") && last.contains("ReturnSubrt"),
+        "{}",
+        last
+    );
+    assert_eq!(report.screen.len(), 32);
+    assert_eq!(report.ram.len(), 0x1000 / 16);
+    assert!(
+        report.ram[0x20].starts_with("0200: 70 01 30 28"),
+        "{}",
+        report.ram[0x20]
+    );
+}
+
+#[test]
+fn report_restores_the_faulting_state() {
+    let mut chip8 = machine(&ROM);
+    let fault = run_until_fault(&mut chip8);
+    let json = CrashReport::new(&chip8, &fault).to_json();
+
+    let report = CrashReport::from_json(&json).expect("Report parses");
+    let mut restored = machine(&ROM);
+    report.restore(&mut restored).expect("Same rom");
+    assert_eq!(restored.state_hash(), chip8.state_hash());
+    // And it faults all over again
+    let again = restored.run_cycle().unwrap_err();
+    assert_eq!(again.pc, fault.pc);
+}
+
+#[test]
+fn report_needs_the_same_rom() {
+    let mut chip8 = machine(&ROM);
+    let fault = run_until_fault(&mut chip8);
+    let report = CrashReport::new(&chip8, &fault);
+    match report.restore(&mut machine(&[0x12, 0x00])) {
+        Err(ReportError::State(_)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    let mut corrupt = report.clone();
+    corrupt.state.pop();
+    match corrupt.restore(&mut machine(&ROM)) {
+        Err(ReportError::Corrupt(_)) => {}
+        other => panic!("{:?}", other),
+    }
+    assert!(CrashReport::from_json("{}").is_err());
+}
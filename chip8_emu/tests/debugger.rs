@@ -0,0 +1,233 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Access;
+use chip8::Chip8;
+use chip8::Comparison;
+use chip8::Condition;
+use chip8::Debugger;
+use chip8::MemoryAccess;
+use chip8::Platform;
+use chip8::Register;
+use chip8::StopReason;
+use chip8::Watchpoint;
+use common::machine;
+use common::machine_on;
+
+// Counts in v0 and calls a subroutine that saves v0 to 0x300 and loads it
+// back, forever
+const ROM: [u8; 26] = [
+    0x60, 0x00, // 0x200: v0 := 0
+    0x70, 0x01, // 0x202: v0 += 1
+    0x22, 0x10, // 0x204: call 0x210
+    0x12, 0x02, // 0x206: jump 0x202
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 0x208: unused
+    0xA3, 0x00, // 0x210: i := 0x300
+    0xF0, 0x55, // 0x212: save v0
+    0xA3, 0x00, // 0x214: i := 0x300
+    0xF0, 0x65, // 0x216: load v0
+    0x00, 0xEE, // 0x218: return
+];
+const CYCLES: u32 = 1000;
+
+fn pc(chip8: &Chip8) -> u16 {
+    chip8.cpu.regs.pc()
+}
+
+// Runs to the given address and leaves the debugger paused there
+fn run_to(debugger: &mut Debugger, chip8: &mut Chip8, addr: u16) {
+    debugger.add_breakpoint(addr);
+    match debugger.run(chip8, CYCLES) {
+        Some(StopReason::Breakpoint(found)) => assert_eq!(found, addr),
+        other => panic!("Expected a breakpoint, got {:?}", other),
+    }
+    debugger.remove_breakpoint(addr);
+}
+
+#[test]
+fn breakpoints_stop_before_their_instruction() {
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x210);
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Breakpoint(addr)) => assert_eq!(addr, 0x210),
+        other => panic!("Expected a breakpoint, got {:?}", other),
+    }
+    assert_eq!(pc(&chip8), 0x210);
+    assert_eq!(chip8.cpu.regs.v_regs[0], 1);
+    assert!(debugger.is_paused());
+    assert!(debugger.run(&mut chip8, CYCLES).is_none(), "Stays paused");
+    // Resuming moves past the breakpoint, and stops on it the next time
+    debugger.resume();
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Breakpoint(addr)) => assert_eq!(addr, 0x210),
+        other => panic!("Expected a breakpoint, got {:?}", other),
+    }
+    assert_eq!(chip8.cpu.regs.v_regs[0], 2);
+}
+
+#[test]
+fn conditional_breakpoints_wait_for_their_condition() {
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    let condition = Condition::new(Register::V(0), Comparison::Equal, 3);
+    debugger.add_conditional_breakpoint(0x204, condition);
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Breakpoint(addr)) => assert_eq!(addr, 0x204),
+        other => panic!("Expected a breakpoint, got {:?}", other),
+    }
+    assert_eq!(chip8.cpu.regs.v_regs[0], 3);
+}
+
+#[test]
+fn conditions_stop_wherever_they_become_true() {
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    let condition = Condition::new(Register::V(0), Comparison::Equal, 5);
+    debugger.break_when(condition);
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Condition(found)) => assert_eq!(found, condition),
+        other => panic!("Expected a condition, got {:?}", other),
+    }
+    assert_eq!(pc(&chip8), 0x204, "Stops right after v0 += 1");
+    assert_eq!(chip8.cpu.regs.v_regs[0], 5);
+    // It still holds, so it doesn't stop again until it has been false
+    debugger.resume();
+    assert!(debugger.run(&mut chip8, 50).is_none());
+}
+
+#[test]
+fn watchpoints_stop_on_reads() {
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::new(0x300, 0x300, true, false));
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Watchpoint { pc, access }) => {
+            assert_eq!(pc, 0x216);
+            assert_eq!(
+                access,
+                MemoryAccess {
+                    addr: 0x300,
+                    access: Access::Read,
+                }
+            );
+        }
+        other => panic!("Expected a watchpoint, got {:?}", other),
+    }
+    assert_eq!(pc(&chip8), 0x218, "Stops after the instruction");
+}
+
+#[test]
+fn watchpoints_stop_on_writes() {
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::new(0x2FF, 0x301, false, true));
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Watchpoint { pc, access }) => {
+            assert_eq!(pc, 0x212);
+            assert_eq!(
+                access,
+                MemoryAccess {
+                    addr: 0x300,
+                    access: Access::Write,
+                }
+            );
+        }
+        other => panic!("Expected a watchpoint, got {:?}", other),
+    }
+    // Addresses outside the range are never watched
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::new(0x301, 0x400, true, true));
+    assert!(debugger.run(&mut chip8, CYCLES).is_none());
+}
+
+#[test]
+fn step_into_enters_subroutines() {
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    run_to(&mut debugger, &mut chip8, 0x204);
+    debugger.step_into();
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Step) => {}
+        other => panic!("Expected a step, got {:?}", other),
+    }
+    assert_eq!(pc(&chip8), 0x210);
+    assert_eq!(chip8.cpu.regs.sp(), 1);
+}
+
+#[test]
+fn steps_leave_the_timers_alone_and_end_on_a_key_wait() {
+    // v1 := 5, delay := v1, then wait for a key into v2
+    let mut chip8 = machine(&[0x61, 0x05, 0xF1, 0x15, 0xF2, 0x0A]);
+    let mut debugger = Debugger::new();
+    for _ in 0..3 {
+        debugger.step_into();
+        assert!(debugger.run(&mut chip8, CYCLES).is_some());
+    }
+    assert_eq!(chip8.cpu.regs.delay, 5);
+    assert!(chip8.is_blocked());
+
+    // Blocked, a step stops straight away instead of running on
+    debugger.step_into();
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Step) => {}
+        other => panic!("Expected a step, got {:?}", other),
+    }
+    assert!(debugger.is_paused());
+    assert_eq!(pc(&chip8), 0x206);
+}
+
+#[test]
+fn step_over_runs_whole_subroutines() {
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    run_to(&mut debugger, &mut chip8, 0x204);
+    debugger.step_over(&chip8);
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Step) => {}
+        other => panic!("Expected a step, got {:?}", other),
+    }
+    assert_eq!(pc(&chip8), 0x206);
+    assert_eq!(chip8.cpu.regs.sp(), 0);
+    assert_eq!(chip8.cpu.ram.peek(0x300), 1, "The subroutine ran");
+    // Anything but a call is a single step
+    debugger.step_over(&chip8);
+    assert!(debugger.run(&mut chip8, CYCLES).is_some());
+    assert_eq!(pc(&chip8), 0x202);
+}
+
+#[test]
+fn step_out_runs_to_the_return() {
+    let mut chip8 = machine(&ROM);
+    let mut debugger = Debugger::new();
+    run_to(&mut debugger, &mut chip8, 0x212);
+    assert_eq!(chip8.cpu.regs.sp(), 1);
+    debugger.step_out(&chip8);
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Step) => {}
+        other => panic!("Expected a step, got {:?}", other),
+    }
+    assert_eq!(pc(&chip8), 0x206);
+    assert_eq!(chip8.cpu.regs.sp(), 0);
+}
+
+#[test]
+fn faults_and_exits_stop_the_machine() {
+    // return with nothing to return to
+    let mut chip8 = machine(&[0x00, 0xEE]);
+    let mut debugger = Debugger::new();
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Fault(fault)) => assert_eq!(fault.pc, 0x200),
+        other => panic!("Expected a fault, got {:?}", other),
+    }
+    assert!(debugger.is_paused());
+    // exit
+    let mut chip8 = machine_on(&[0x00, 0xFD], Platform::SuperChip);
+    let mut debugger = Debugger::new();
+    match debugger.run(&mut chip8, CYCLES) {
+        Some(StopReason::Exited) => {}
+        other => panic!("Expected an exit, got {:?}", other),
+    }
+}
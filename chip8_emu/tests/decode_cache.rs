@@ -0,0 +1,42 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::assemble;
+use chip8::Chip8;
+use common::machine;
+
+// Counts in v3 ten times, then rewrites its own counting instruction into
+// v4 += 5 and starts over
+const SELF_MODIFYING: &str = "
+: main
+    v2 := 0
+: patch
+    v3 += 1
+    v2 += 1
+    if v2 != 10 then jump patch
+    i := patch
+    v0 := 0x74
+    v1 := 0x05
+    save v1
+    jump main
+";
+
+fn run(rom: &[u8], cache: bool) -> Chip8 {
+    let mut chip8 = machine(rom);
+    chip8.set_decode_cache(cache);
+    for _ in 0..100 {
+        chip8.run_frame(9).expect("Valid rom");
+    }
+    chip8
+}
+
+#[test]
+fn cache_sees_self_modifying_code() {
+    let rom = assemble(SELF_MODIFYING).expect("Program assembles");
+    let uncached = run(&rom, false);
+    let cached = run(&rom, true);
+    assert_eq!(cached.cpu.regs.v_regs[3], 10);
+    assert!(cached.cpu.regs.v_regs[4] > 0, "Stale instruction kept running");
+    assert_eq!(cached.state_hash(), uncached.state_hash());
+}
@@ -0,0 +1,52 @@
+extern crate chip8;
+
+use chip8::InvalidOpcode;
+use chip8::Opcode;
+
+#[test]
+fn every_word_round_trips() {
+    for word in 0..=0xFFFF {
+        if let Ok(op) = Opcode::decode_op(word) {
+            assert_eq!(op.encode(), word, "{:04X} -> {:?}", word, op);
+            let again = Opcode::decode_op(op.encode()).expect("Still valid");
+            assert!(again == op, "{:04X} -> {:?} -> {:?}", word, op, again);
+        }
+    }
+}
+
+#[test]
+fn undefined_words_are_rejected() {
+    let words = [
+        0x5121, 0x5124, 0x512F, 0x8128, 0x812D, 0x812F, 0x9121, 0x912E, 0xE000,
+        0xE19F, 0xE1A0, 0xE1FF, 0xF100, 0xF102, 0xF108, 0xF1FF,
+    ];
+    for word in words.iter() {
+        match Opcode::decode_op(*word) {
+            Err(InvalidOpcode::DoesntExist(_, raw)) => assert_eq!(raw, *word),
+            other => panic!("{:04X} decoded to {:?}", word, other),
+        }
+    }
+}
+
+#[test]
+fn only_defined_words_decode() {
+    let mut counts = [0; 16];
+    for word in 0..=0xFFFFu16 {
+        if Opcode::decode_op(word).is_ok() {
+            counts[(word >> 12) as usize] += 1;
+        }
+    }
+    for (group, count) in counts.iter().enumerate() {
+        // Each xy form covers 256 words and each x form 16. 0nnn takes up
+        // the whole 0 group and F000 and F002 are single words.
+        let expected = match group {
+            0x5 => 3 * 256,
+            0x8 => 9 * 256,
+            0x9 => 256,
+            0xE => 2 * 16,
+            0xF => 14 * 16 + 2,
+            _ => 4096,
+        };
+        assert_eq!(*count, expected, "Valid words in group {:X}", group);
+    }
+}
@@ -0,0 +1,111 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Chip8;
+use chip8::Chip8Error;
+use chip8::FaultClass;
+use chip8::InputError;
+use chip8::Platform;
+use chip8::Quirks;
+use chip8::Rng;
+use chip8::RomError;
+use common::machine;
+use common::machine_on;
+use std::error::Error;
+
+fn run_until_error(rom: &[u8]) -> Chip8Error {
+    let mut chip8 = machine(rom);
+    let fault = (0..10)
+        .find_map(|_| chip8.run_cycle().err())
+        .expect("Rom faults");
+    Chip8Error::from(fault)
+}
+
+#[test]
+fn execution_fault_knows_where_it_happened() {
+    // v0 := 1, return with nothing to return to
+    let err = run_until_error(&[0x60, 0x01, 0x00, 0xEE]);
+    let fault = match &err {
+        Chip8Error::Execution(fault) => fault,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(fault.pc, 0x202);
+    assert_eq!(fault.word, 0x00EE);
+    assert!(fault.op.is_some());
+
+    let message = err.to_string();
+    assert!(message.contains("0x202"), "{}", message);
+    assert!(message.contains("00EE"), "{}", message);
+    assert!(message.contains("empty stack"), "{}", message);
+
+    // Chip8Error -> Fault -> InvalidOpcode
+    let fault = err.source().expect("Has a fault");
+    let cause = fault.source().expect("Has a cause");
+    assert!(cause.source().is_none());
+}
+
+#[test]
+fn offset_jumps_fault_only_when_fetching_past_memory() {
+    // v0 := 4, jump0 0x0FC lands below the rom like any other jump
+    let mut chip8 = machine(&[0x60, 0x04, 0xB0, 0xFC]);
+    chip8.run_frame(2).expect("Jump is allowed");
+    assert_eq!(chip8.cpu.regs.pc(), 0x100);
+
+    // v0 := 0xFF, jump0 0xFFF lands past the end of memory
+    let mut chip8 = machine(&[0x60, 0xFF, 0xBF, 0xFF]);
+    chip8.run_frame(2).expect("Jump is allowed");
+    let fault = chip8.run_cycle().expect_err("Fetch is out of bounds");
+    assert_eq!(fault.pc, 0x10FE);
+    assert_eq!(fault.class(), FaultClass::Memory);
+
+    // XO-CHIP's jump tables can reach past 0xFFF, v0 := 2, jump0 0xFFE, then
+    // v1 := 1 at 0x1000
+    let mut rom = vec![0; 0xE02];
+    rom[..4].copy_from_slice(&[0x60, 0x02, 0xBF, 0xFE]);
+    rom[0xE00..].copy_from_slice(&[0x61, 0x01]);
+    let mut chip8 = machine_on(&rom, Platform::XoChip);
+    chip8.run_frame(3).expect("Rom runs");
+    assert_eq!(chip8.cpu.regs.v_regs[1], 1);
+}
+
+#[test]
+fn undefined_word_is_a_decode_error() {
+    let err = run_until_error(&[0x51, 0x21]);
+    match &err {
+        Chip8Error::Decode(fault) => {
+            assert_eq!(fault.pc, 0x200);
+            assert_eq!(fault.word, 0x5121);
+            assert!(fault.op.is_none());
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(err.to_string().contains("5121"), "{}", err);
+}
+
+#[test]
+fn unsupported_instruction_is_a_decode_error() {
+    // Saving a range of registers is XO-CHIP only
+    match run_until_error(&[0x51, 0x22]) {
+        Chip8Error::Decode(fault) => assert!(fault.op.is_some()),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn input_and_rom_errors() {
+    let mut chip8 = machine(&[]);
+    let err = chip8.set_ctrl_state(0x10, true).unwrap_err();
+    assert_eq!(err, InputError::NoSuchKey(0x10));
+    assert!(Chip8Error::from(err).to_string().contains("0x10"));
+
+    let rom = vec![0; 0x1000];
+    let err =
+        Chip8::new(&rom, Platform::Chip8, Quirks::cosmac_vip(), Rng::new(0))
+            .err()
+            .expect("Rom is too big");
+    match Chip8Error::from(err) {
+        Chip8Error::Rom(RomError::TooLarge { .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
@@ -0,0 +1,73 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Chip8;
+use chip8::FaultClass;
+use chip8::FaultPolicies;
+use chip8::FaultPolicy;
+use chip8::FAULT_CLASSES;
+
+// An undefined word, then v0 := 5, then a return with an empty stack
+const ROM: [u8; 6] = [0x51, 0x21, 0x60, 0x05, 0x00, 0xEE];
+
+fn machine(policies: &str) -> Chip8 {
+    let mut chip8 = common::machine(&ROM);
+    chip8.set_fault_policies(policies.parse().expect("Valid policies"));
+    chip8
+}
+
+#[test]
+fn faults_halt_by_default() {
+    let mut chip8 = machine("");
+    let fault = chip8.run_cycle().unwrap_err();
+    assert_eq!(fault.class(), FaultClass::Undefined);
+    assert_eq!(chip8.fault_policy(&fault), FaultPolicy::Halt);
+    // Nothing moves on a fault
+    assert_eq!(chip8.cpu.regs.pc(), 0x200);
+}
+
+#[test]
+fn skipped_faults_act_as_no_ops() {
+    let mut chip8 = machine("undefined=skip");
+    chip8.run_cycle().expect("Undefined word is skipped");
+    chip8.run_cycle().expect("v0 := 5 runs");
+    assert_eq!(chip8.cpu.regs.v_regs[0], 5);
+    let fault = chip8.run_cycle().unwrap_err();
+    assert_eq!(fault.class(), FaultClass::StackUnderflow);
+
+    let mut chip8 = machine("skip");
+    chip8.run_frame(3).expect("Every fault is skipped");
+    assert_eq!(chip8.cpu.regs.pc(), 0x206);
+}
+
+#[test]
+fn breaking_faults_still_stop_the_machine() {
+    let mut chip8 = machine("halt,undefined=break");
+    let fault = chip8.run_cycle().unwrap_err();
+    assert_eq!(chip8.fault_policy(&fault), FaultPolicy::Break);
+    assert_eq!(chip8.cpu.regs.pc(), 0x200);
+}
+
+#[test]
+fn policies_parse() {
+    let policies: FaultPolicies =
+        "break, stack-overflow=skip,KEY=halt".parse().unwrap();
+    for class in FAULT_CLASSES.iter() {
+        let expected = match class {
+            FaultClass::StackOverflow => FaultPolicy::Skip,
+            FaultClass::Key => FaultPolicy::Halt,
+            _ => FaultPolicy::Break,
+        };
+        assert_eq!(policies.get(*class), expected, "{}", class);
+    }
+    // Later entries win, even ones for every class
+    let policies: FaultPolicies = "memory=skip,halt".parse().unwrap();
+    assert_eq!(policies, FaultPolicies::all(FaultPolicy::Halt));
+
+    assert!("crash".parse::<FaultPolicies>().is_err());
+    assert!("stack=skip".parse::<FaultPolicies>().is_err());
+    for class in FAULT_CLASSES.iter() {
+        assert_eq!(class.to_string().parse::<FaultClass>(), Ok(*class));
+    }
+}
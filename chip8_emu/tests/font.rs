@@ -0,0 +1,22 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::FONT;
+use common::machine;
+
+// The exported font is the one the machine draws digits with
+#[test]
+fn font_matches_the_digits_in_memory() {
+    for digit in 0..16 {
+        // v0 = digit, then I = address of v0's digit
+        let rom = [0x60, digit, 0xF0, 0x29];
+        let mut chip8 = machine(&rom);
+        chip8.run_frame(2).expect("Rom runs");
+        let start = chip8.cpu.regs.i_reg() as usize;
+        let sprite: Vec<u8> = (start..start + 5)
+            .map(|addr| chip8.cpu.ram.peek(addr))
+            .collect();
+        assert_eq!(sprite, FONT[digit as usize], "digit {:X}", digit);
+    }
+}
@@ -0,0 +1,151 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::write_screen;
+use chip8::Headless;
+use chip8::KeyEvent;
+use chip8::KeyScript;
+use chip8::Outcome;
+use chip8::Platform;
+use chip8::ScreenFormat;
+use chip8::StopReason;
+use chip8::EXIT_INVALID_OPCODE;
+use chip8::EXIT_SUCCESS;
+use chip8::EXIT_TIMEOUT;
+use common::machine;
+use common::machine_on;
+
+// Counts in v2 while key 0 is held, forever
+const ROM: [u8; 6] = [0xE0, 0xA1, 0x72, 0x01, 0x12, 0x00];
+// Exits straight away
+const EXIT: [u8; 2] = [0x00, 0xFD];
+// Returns with nothing to return to
+const FAULT: [u8; 2] = [0x00, 0xEE];
+
+fn parse_error(script: &str) -> String {
+    script.parse::<KeyScript>().expect_err("Script is invalid")
+}
+
+#[test]
+fn key_scripts_parse() {
+    let script: KeyScript = "# held for a while\n\n10 a down\n2 0 press\n\
+                             10 A up # same frame, kept in order\n"
+        .parse()
+        .expect("Valid script");
+    let event = |frame, key, pressed| KeyEvent {
+        frame,
+        key,
+        pressed,
+    };
+    assert_eq!(
+        script.events(),
+        &[
+            event(2, 0, true),
+            event(10, 0xA, true),
+            event(10, 0xA, false)
+        ]
+    );
+}
+
+#[test]
+fn key_script_errors_name_the_line() {
+    assert_eq!(
+        parse_error("0 1 down\n# comment\n5 G down\n"),
+        "3: key must be a hex digit 0-F"
+    );
+    assert_eq!(parse_error("0 10 down"), "1: key must be a hex digit 0-F");
+    assert_eq!(parse_error("\n-1 1 up"), "2: invalid frame number");
+    assert_eq!(parse_error("1 1 sideways"), "1: expected down or up");
+    assert_eq!(
+        parse_error("0 1 down\n1 2"),
+        "2: expected <frame> <key> down|up"
+    );
+}
+
+#[test]
+fn exit_codes_match_outcomes() {
+    let fault = machine(&FAULT).run_frame(1).expect_err("Rom faults");
+    assert_eq!(Outcome::Finished.exit_code(), EXIT_SUCCESS);
+    assert_eq!(Outcome::Exited.exit_code(), EXIT_SUCCESS);
+    assert_eq!(Outcome::Reached(StopReason::Step).exit_code(), EXIT_SUCCESS);
+    assert_eq!(Outcome::Timeout.exit_code(), EXIT_TIMEOUT);
+    assert_eq!(Outcome::Fault(fault).exit_code(), EXIT_INVALID_OPCODE);
+}
+
+#[test]
+fn runs_every_frame_without_a_target() {
+    let mut chip8 = machine(&ROM);
+    let mut headless = Headless::new(30, 10);
+    headless.set_script("5 0 down\n10 0 up".parse().expect("Valid script"));
+    match headless.run(&mut chip8) {
+        (Outcome::Finished, 30) => {}
+        other => panic!("Expected every frame to run, got {:?}", other),
+    }
+    assert!(chip8.cpu.regs.v_regs[2] > 0, "Scripted key was held");
+}
+
+#[test]
+fn reaching_the_target_stops_the_run() {
+    let mut chip8 = machine(&ROM);
+    let mut headless = Headless::new(30, 10);
+    headless.stop_at(0x202);
+    headless.set_script("5 0 down".parse().expect("Valid script"));
+    match headless.run(&mut chip8) {
+        (Outcome::Reached(StopReason::Breakpoint(0x202)), 6) => {}
+        other => panic!("Expected the target on frame 6, got {:?}", other),
+    }
+}
+
+#[test]
+fn missing_the_target_times_out() {
+    let mut chip8 = machine(&ROM);
+    let mut headless = Headless::new(30, 10);
+    headless.stop_at(0x202);
+    match headless.run(&mut chip8) {
+        (Outcome::Timeout, 30) => {}
+        other => panic!("Expected a timeout, got {:?}", other),
+    }
+}
+
+#[test]
+fn exiting_only_times_out_with_a_target() {
+    let mut chip8 = machine_on(&EXIT, Platform::SuperChip);
+    match Headless::new(30, 10).run(&mut chip8) {
+        (Outcome::Exited, 1) => {}
+        other => panic!("Expected an exit, got {:?}", other),
+    }
+    let mut chip8 = machine_on(&EXIT, Platform::SuperChip);
+    let mut headless = Headless::new(30, 10);
+    headless.stop_at(0x300);
+    match headless.run(&mut chip8) {
+        (Outcome::Timeout, 1) => {}
+        other => panic!("Expected a timeout, got {:?}", other),
+    }
+}
+
+#[test]
+fn faults_end_the_run() {
+    let mut chip8 = machine(&FAULT);
+    match Headless::new(30, 10).run(&mut chip8) {
+        (Outcome::Fault(fault), 1) => assert_eq!(fault.pc, 0x200),
+        other => panic!("Expected a fault, got {:?}", other),
+    }
+}
+
+#[test]
+fn pbm_rows_are_padded_to_whole_bytes() {
+    let mut screen = machine(&ROM).cpu.screen.clone();
+    screen.width = 10;
+    screen.height = 2;
+    screen.buffer = vec![
+        1, 0, 0, 0, 0, 0, 0, 1, 1, 0, //
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 3, //
+    ];
+    let mut out = Vec::new();
+    write_screen(&screen, ScreenFormat::Pbm, &mut out)
+        .expect("Writes to memory");
+    let mut expected = b"P4\n10 2\n".to_vec();
+    expected.extend_from_slice(&[0b1000_0001, 0b1000_0000, 0, 0b0100_0000]);
+    assert_eq!(out, expected);
+}
@@ -0,0 +1,89 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::replay;
+use chip8::Movie;
+use chip8::MovieError;
+use chip8::MovieEvent;
+use chip8::MoviePlayer;
+use chip8::MovieRecorder;
+use common::machine;
+
+// Counts in v2 while key 0 is held, drawing a random number every loop
+const ROM: [u8; 8] = [0xE1, 0xA1, 0x72, 0x01, 0xC3, 0xFF, 0x12, 0x00];
+const CYCLES: u32 = 9;
+const FRAMES: u64 = 30;
+
+// Holds key 0 from frame 5 until frame 20
+fn record() -> Movie {
+    let mut chip8 = machine(&ROM);
+    let mut recorder = MovieRecorder::start(&chip8, CYCLES);
+    for frame in 0..FRAMES {
+        match frame {
+            5 => recorder.set_ctrl_state(&mut chip8, 0, true),
+            20 => recorder.set_ctrl_state(&mut chip8, 0, false),
+            _ => Ok(()),
+        }
+        .expect("Key 0 exists");
+        chip8.run_frame(CYCLES).expect("Rom runs");
+        recorder.end_frame(&chip8);
+    }
+    recorder.finish()
+}
+
+#[test]
+fn recorded_movie_replays() {
+    let movie = Movie::from_bytes(&record().to_bytes()).expect("Valid movie");
+    assert_eq!(movie.events.len(), 2);
+    let mut chip8 = machine(&ROM);
+    assert_eq!(replay(&mut chip8, movie).expect("Movie replays"), FRAMES);
+    assert!(chip8.cpu.regs.v_regs[2] > 0, "Held key was replayed");
+}
+
+#[test]
+fn desync_is_reported_on_its_frame() {
+    let mut movie = record();
+    movie.frame_hashes[12] ^= 1;
+    let mut chip8 = machine(&ROM);
+    match replay(&mut chip8, movie) {
+        Err(MovieError::Desync { frame, .. }) => assert_eq!(frame, 12),
+        other => panic!("Expected a desync, got {:?}", other),
+    }
+}
+
+#[test]
+fn empty_movie_has_nothing_to_play() {
+    let mut chip8 = machine(&ROM);
+    let movie = MovieRecorder::start(&chip8, CYCLES).finish();
+    let mut player =
+        MoviePlayer::start(movie.clone(), &mut chip8).expect("Same rom");
+    assert!(player.is_finished());
+    match player.end_frame(&chip8) {
+        Err(MovieError::Finished) => {}
+        other => panic!("Expected the movie to be over, got {:?}", other),
+    }
+    assert_eq!(replay(&mut chip8, movie).expect("Nothing to check"), 0);
+}
+
+#[test]
+fn corrupt_events_are_refused() {
+    let mut missing_key = record();
+    missing_key.events[0].key = 0x10;
+    let mut out_of_order = record();
+    out_of_order.events.swap(0, 1);
+    let mut past_the_end = record();
+    past_the_end.events.push(MovieEvent {
+        frame: FRAMES + 1,
+        key: 0,
+        pressed: true,
+    });
+    for movie in [missing_key, out_of_order, past_the_end].iter() {
+        match Movie::from_bytes(&movie.to_bytes()) {
+            Err(MovieError::Corrupt(_)) => {}
+            other => panic!("Expected a corrupt movie, got {:?}", other),
+        }
+        let mut chip8 = machine(&ROM);
+        assert!(MoviePlayer::start(movie.clone(), &mut chip8).is_err());
+    }
+}
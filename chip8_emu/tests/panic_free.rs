@@ -0,0 +1,150 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Chip8;
+use chip8::Fault;
+use chip8::InvalidOpcode;
+use chip8::Platform;
+use chip8::Quirks;
+use chip8::Rng;
+use chip8::RomError;
+use common::machine_on;
+
+const PLATFORMS: [Platform; 3] =
+    [Platform::Chip8, Platform::SuperChip, Platform::XoChip];
+
+// Runs until the machine faults, failing the test if it never does
+fn fault(rom: &[u8]) -> Fault {
+    let mut chip8 = machine_on(rom, Platform::Chip8);
+    for _ in 0..60 {
+        if let Err(err) = chip8.run_frame(20) {
+            return err;
+        }
+    }
+    panic!("Rom ran without faulting");
+}
+
+#[test]
+fn fetch_past_end_of_memory() {
+    // Jump to the last byte, whose second half would be past the end
+    match fault(&[0x1F, 0xFF]).cause {
+        InvalidOpcode::PcOutOfBounds(_, pc) => assert_eq!(pc, 0xFFF),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn bcd_near_top_of_memory() {
+    // i := 0xFFE, bcd v0
+    match fault(&[0xAF, 0xFE, 0xF0, 0x33]).cause {
+        InvalidOpcode::OutOfBoundsAddress(..) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn save_near_top_of_memory_writes_nothing() {
+    // v0 := 0xAA, i := 0xFF8, save vf
+    let rom = [0x60, 0xAA, 0xAF, 0xF8, 0xFF, 0x55];
+    let mut chip8 = machine_on(&rom, Platform::Chip8);
+    let err = (0..10).find_map(|_| chip8.run_cycle().err());
+    match err.map(|err| err.cause) {
+        Some(InvalidOpcode::OutOfBoundsAddress(..)) => {}
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(chip8.cpu.ram.peek(0xFF8), 0);
+}
+
+#[test]
+fn load_near_top_of_memory() {
+    // i := 0xFFF, load v1
+    match fault(&[0xAF, 0xFF, 0xF1, 0x65]).cause {
+        InvalidOpcode::OutOfBoundsAddress(..) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn index_wraps_instead_of_overflowing() {
+    // v0 := 0xFF, i := 0xFFF, i += v0, then spin
+    let rom = [0x60, 0xFF, 0xAF, 0xFF, 0xF0, 0x1E, 0x12, 0x06];
+    let mut chip8 = machine_on(&rom, Platform::Chip8);
+    chip8
+        .run_frame(10)
+        .expect("Nothing out of bounds is touched");
+    assert_eq!(chip8.cpu.regs.i_reg(), 0x10FE);
+}
+
+#[test]
+fn key_past_keypad() {
+    // v0 := 0x10, if v0 -key then
+    match fault(&[0x60, 0x10, 0xE0, 0x9E]).cause {
+        InvalidOpcode::NoSuchKey(..) => {}
+        other => panic!("{:?}", other),
+    }
+    let mut chip8 = machine_on(&[], Platform::Chip8);
+    assert!(chip8.set_ctrl_state(16, true).is_err());
+    assert!(chip8.set_ctrl_state(16, false).is_err());
+    assert!(chip8.set_ctrl_state(15, true).is_ok());
+}
+
+#[test]
+fn stack_overflow_and_underflow() {
+    // Calls itself forever
+    match fault(&[0x22, 0x00]).cause {
+        InvalidOpcode::StackOverflow(..) => {}
+        other => panic!("{:?}", other),
+    }
+    match fault(&[0x00, 0xEE]).cause {
+        InvalidOpcode::StackUnderflow(..) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn oversized_rom_is_rejected() {
+    for platform in PLATFORMS.iter() {
+        let max = platform.ram_size() - 0x200;
+        let quirks = Quirks::cosmac_vip();
+        assert!(
+            Chip8::new(&vec![0; max], *platform, quirks, Rng::new(0)).is_ok()
+        );
+        match Chip8::new(&vec![0; max + 1], *platform, quirks, Rng::new(0)) {
+            Err(RomError::TooLarge { size, max: limit }) => {
+                assert_eq!(size, max + 1);
+                assert_eq!(limit, max);
+            }
+            _ => panic!("{} accepted an oversized rom", platform),
+        }
+    }
+}
+
+// Throws random roms at every platform. Whatever they do has to end in an
+// error or keep running, never a panic.
+#[test]
+fn random_roms_never_panic() {
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for round in 0..300 {
+        let len = (next() % 512) as usize;
+        let rom: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+        let platform = PLATFORMS[round % PLATFORMS.len()];
+        let mut chip8 = machine_on(&rom, platform);
+        chip8.set_decode_cache(round % 2 == 0);
+        for frame in 0..30 {
+            let key = (next() % 16) as usize;
+            chip8
+                .set_ctrl_state(key, frame % 2 == 0)
+                .expect("Key exists");
+            if chip8.run_frame(50).is_err() {
+                break;
+            }
+        }
+    }
+}
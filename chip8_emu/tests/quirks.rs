@@ -0,0 +1,222 @@
+extern crate chip8;
+
+use chip8::Chip8;
+use chip8::IndexIncrement;
+use chip8::Platform;
+use chip8::Quirks;
+use chip8::Rng;
+
+fn run(rom: &[u8], quirks: Quirks, cycles: u32) -> Chip8 {
+    let mut chip8 = Chip8::new(rom, Platform::Chip8, quirks, Rng::new(0))
+        .expect("Rom fits");
+    chip8.run_frame(cycles).expect("Rom runs");
+    chip8
+}
+
+// v1 := 1, v2 := 3, then v1 >>= v2
+fn shifts_in_place(quirks: Quirks) -> bool {
+    let chip8 = run(&[0x61, 0x01, 0x62, 0x03, 0x81, 0x26], quirks, 3);
+    assert_eq!(chip8.cpu.regs.v_regs[0xF], 1);
+    chip8.cpu.regs.v_regs[1] == 0
+}
+
+// i := 0x300, then save v0 - v2, then the same for load
+fn load_store_increment(quirks: Quirks) -> IndexIncrement {
+    let save = run(&[0xA3, 0x00, 0xF2, 0x55], quirks, 2);
+    let load = run(&[0xA3, 0x00, 0xF2, 0x65], quirks, 2);
+    assert_eq!(save.cpu.regs.i_reg(), load.cpu.regs.i_reg());
+    match save.cpu.regs.i_reg() {
+        0x300 => IndexIncrement::Unchanged,
+        0x302 => IndexIncrement::X,
+        0x303 => IndexIncrement::XPlusOne,
+        other => panic!("I moved to {:#05X}", other),
+    }
+}
+
+// vf := 5, then v1 |= v2
+fn resets_vf(quirks: Quirks) -> bool {
+    let chip8 = run(&[0x6F, 0x05, 0x81, 0x21], quirks, 2);
+    chip8.cpu.regs.v_regs[0xF] == 0
+}
+
+// v0 := 2, v3 := 4, then jump0 0x304
+fn jumps_with_vx(quirks: Quirks) -> bool {
+    let chip8 = run(&[0x60, 0x02, 0x63, 0x04, 0xB3, 0x04], quirks, 3);
+    match chip8.cpu.regs.pc() {
+        0x306 => false,
+        0x308 => true,
+        other => panic!("Jumped to {:#05X}", other),
+    }
+}
+
+// Draws the 4 pixel wide digit 0 two pixels from the right edge
+fn clips_sprites(quirks: Quirks) -> bool {
+    let rom = [0x60, 0x00, 0xF0, 0x29, 0x61, 0x3E, 0xD1, 0x05];
+    let chip8 = run(&rom, quirks, 4);
+    assert_eq!(chip8.cpu.screen.buffer[62], 1);
+    chip8.cpu.screen.buffer[0] == 0
+}
+
+// Draws and counts in v3 in a loop. Waiting stops the frame at the first
+// draw, before anything is counted.
+fn waits_for_display(quirks: Quirks) -> bool {
+    let chip8 = run(&[0xD0, 0x05, 0x73, 0x01, 0x12, 0x00], quirks, 30);
+    match chip8.cpu.regs.v_regs[3] {
+        0 => true,
+        10 => false,
+        other => panic!("Drew {} sprites", other),
+    }
+}
+
+// i := 0xFFF, v0 := 1, then i += v0
+fn flags_i_overflow(quirks: Quirks) -> bool {
+    let chip8 = run(&[0xAF, 0xFF, 0x60, 0x01, 0xF0, 0x1E], quirks, 3);
+    assert_eq!(chip8.cpu.regs.i_reg(), 0x1000);
+    chip8.cpu.regs.v_regs[0xF] == 1
+}
+
+// Works out the quirks from how the machine behaves
+fn observed(quirks: Quirks) -> Quirks {
+    Quirks {
+        shift: shifts_in_place(quirks),
+        load_store: load_store_increment(quirks),
+        vf_reset: resets_vf(quirks),
+        jump: jumps_with_vx(quirks),
+        clipping: clips_sprites(quirks),
+        display_wait: waits_for_display(quirks),
+        i_overflow: flags_i_overflow(quirks),
+    }
+}
+
+// Every quirk off, none of the presets look like this
+fn plain() -> Quirks {
+    Quirks {
+        shift: false,
+        load_store: IndexIncrement::Unchanged,
+        vf_reset: false,
+        jump: false,
+        clipping: false,
+        display_wait: false,
+        i_overflow: false,
+    }
+}
+
+#[test]
+fn shift_quirk() {
+    assert!(!shifts_in_place(plain()));
+    assert!(shifts_in_place(Quirks {
+        shift: true,
+        ..plain()
+    }));
+}
+
+#[test]
+fn load_store_quirk() {
+    for increment in &[
+        IndexIncrement::Unchanged,
+        IndexIncrement::X,
+        IndexIncrement::XPlusOne,
+    ] {
+        let quirks = Quirks {
+            load_store: *increment,
+            ..plain()
+        };
+        assert_eq!(load_store_increment(quirks), *increment);
+    }
+}
+
+#[test]
+fn vf_reset_quirk() {
+    assert!(!resets_vf(plain()));
+    assert!(resets_vf(Quirks {
+        vf_reset: true,
+        ..plain()
+    }));
+}
+
+#[test]
+fn jump_quirk() {
+    assert!(!jumps_with_vx(plain()));
+    assert!(jumps_with_vx(Quirks {
+        jump: true,
+        ..plain()
+    }));
+}
+
+#[test]
+fn clipping_quirk() {
+    assert!(!clips_sprites(plain()));
+    assert!(clips_sprites(Quirks {
+        clipping: true,
+        ..plain()
+    }));
+}
+
+#[test]
+fn display_wait_quirk() {
+    assert!(!waits_for_display(plain()));
+    assert!(waits_for_display(Quirks {
+        display_wait: true,
+        ..plain()
+    }));
+}
+
+#[test]
+fn i_overflow_quirk() {
+    assert!(!flags_i_overflow(plain()));
+    assert!(flags_i_overflow(Quirks {
+        i_overflow: true,
+        ..plain()
+    }));
+}
+
+#[test]
+fn vip_preset() {
+    let quirks = Quirks {
+        load_store: IndexIncrement::XPlusOne,
+        vf_reset: true,
+        clipping: true,
+        display_wait: true,
+        ..plain()
+    };
+    assert_eq!(observed(Quirks::cosmac_vip()), quirks);
+    assert_eq!("vip".parse(), Ok(quirks));
+    assert_eq!(Platform::Chip8.default_quirks(), quirks);
+}
+
+#[test]
+fn chip48_preset() {
+    let quirks = Quirks {
+        shift: true,
+        load_store: IndexIncrement::X,
+        jump: true,
+        clipping: true,
+        ..plain()
+    };
+    assert_eq!(observed(Quirks::chip48()), quirks);
+    assert_eq!("chip-48".parse(), Ok(quirks));
+}
+
+#[test]
+fn schip_preset() {
+    let quirks = Quirks {
+        shift: true,
+        jump: true,
+        clipping: true,
+        ..plain()
+    };
+    assert_eq!(observed(Quirks::schip()), quirks);
+    assert_eq!("superchip".parse(), Ok(quirks));
+    assert_eq!(Platform::SuperChip.default_quirks(), quirks);
+}
+
+#[test]
+fn xo_chip_preset() {
+    let quirks = Quirks {
+        load_store: IndexIncrement::XPlusOne,
+        ..plain()
+    };
+    assert_eq!(observed(Quirks::xo_chip()), quirks);
+    assert_eq!("xo-chip".parse(), Ok(quirks));
+    assert_eq!(Platform::XoChip.default_quirks(), quirks);
+}
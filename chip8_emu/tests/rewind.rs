@@ -0,0 +1,132 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Chip8;
+use chip8::Platform;
+use chip8::Rewind;
+use common::machine;
+use common::machine_on;
+
+// Counts in v2 and draws a random number in v3, forever
+const ROM: [u8; 6] = [0x72, 0x01, 0xC3, 0xFF, 0x12, 0x00];
+
+// Runs a frame at a time, recording each one, and returns the state hashes
+// after every frame
+fn record(chip8: &mut Chip8, rewind: &mut Rewind, frames: usize) -> Vec<u64> {
+    (0..frames)
+        .map(|_| {
+            chip8.run_frame(3).expect("Rom runs");
+            rewind.record(chip8);
+            chip8.state_hash()
+        })
+        .collect()
+}
+
+#[test]
+fn stepping_back_restores_each_frame() {
+    let mut chip8 = machine(&ROM);
+    let mut rewind = Rewind::new(1, 100);
+    let hashes = record(&mut chip8, &mut rewind, 20);
+    assert_eq!(rewind.len(), 20);
+    // The last frame is the one showing, so going back starts before it
+    for hash in hashes[..19].iter().rev() {
+        assert!(rewind.step_back(&mut chip8));
+        assert_eq!(chip8.state_hash(), *hash);
+    }
+    assert!(rewind.is_empty());
+    assert!(!rewind.step_back(&mut chip8));
+    assert_eq!(chip8.state_hash(), hashes[0]);
+}
+
+#[test]
+fn one_step_back_goes_back_one_frame() {
+    let mut chip8 = machine(&ROM);
+    let mut rewind = Rewind::new(1, 100);
+    let hashes = record(&mut chip8, &mut rewind, 5);
+    assert!(rewind.step_back(&mut chip8));
+    assert_eq!(chip8.state_hash(), hashes[3]);
+
+    // Running on after a rewind records from there
+    let more = record(&mut chip8, &mut rewind, 2);
+    assert_eq!(more[0], hashes[4]);
+    assert!(rewind.step_back(&mut chip8));
+    assert_eq!(chip8.state_hash(), more[0]);
+}
+
+#[test]
+fn snapshots_are_taken_every_interval() {
+    let mut chip8 = machine(&ROM);
+    let mut rewind = Rewind::new(4, 100);
+    let hashes = record(&mut chip8, &mut rewind, 20);
+    assert_eq!(rewind.len(), 5);
+    for hash in hashes[..19].iter().rev().skip(3).step_by(4) {
+        assert!(rewind.step_back(&mut chip8));
+        assert_eq!(chip8.state_hash(), *hash);
+    }
+    assert!(!rewind.step_back(&mut chip8));
+
+    // Partway to the next snapshot the newest one is still older than the
+    // frame on screen
+    let mut chip8 = machine(&ROM);
+    let mut rewind = Rewind::new(4, 100);
+    let hashes = record(&mut chip8, &mut rewind, 10);
+    assert!(rewind.step_back(&mut chip8));
+    assert_eq!(chip8.state_hash(), hashes[7]);
+}
+
+// Switching resolution changes the size of the screen, so neighbouring
+// snapshots differ in length
+#[test]
+fn snapshots_of_different_sizes_round_trip() {
+    // hires, lores, over and over
+    let rom = [0x00, 0xFF, 0x00, 0xFE, 0x12, 0x00];
+    let mut chip8 = machine_on(&rom, Platform::SuperChip);
+    let mut rewind = Rewind::new(1, 100);
+    let mut sizes = Vec::new();
+    let hashes: Vec<u64> = (0..12)
+        .map(|_| {
+            chip8.run_frame(1).expect("Rom runs");
+            rewind.record(&chip8);
+            sizes.push(chip8.cpu.screen.buffer.len());
+            chip8.state_hash()
+        })
+        .collect();
+    assert!(sizes.windows(2).any(|pair| pair[0] < pair[1]));
+    assert!(sizes.windows(2).any(|pair| pair[0] > pair[1]));
+    for hash in hashes[..11].iter().rev() {
+        assert!(rewind.step_back(&mut chip8));
+        assert_eq!(chip8.state_hash(), *hash);
+    }
+}
+
+#[test]
+fn oldest_snapshots_are_dropped_at_capacity() {
+    let mut chip8 = machine(&ROM);
+    let mut rewind = Rewind::new(1, 5);
+    let hashes = record(&mut chip8, &mut rewind, 20);
+    assert_eq!(rewind.len(), 5);
+    for hash in hashes[15..19].iter().rev() {
+        assert!(rewind.step_back(&mut chip8));
+        assert_eq!(chip8.state_hash(), *hash);
+    }
+    assert!(!rewind.step_back(&mut chip8));
+    assert_eq!(chip8.state_hash(), hashes[15]);
+}
+
+#[test]
+fn stepping_back_with_no_history_does_nothing() {
+    let mut chip8 = machine(&ROM);
+    chip8.run_frame(10).expect("Rom runs");
+    let hash = chip8.state_hash();
+    let mut rewind = Rewind::new(1, 5);
+    assert!(rewind.is_empty());
+    assert!(!rewind.step_back(&mut chip8));
+    assert_eq!(chip8.state_hash(), hash);
+    // Nor after the history has been cleared
+    record(&mut chip8, &mut rewind, 3);
+    rewind.clear();
+    let hash = chip8.state_hash();
+    assert!(!rewind.step_back(&mut chip8));
+    assert_eq!(chip8.state_hash(), hash);
+}
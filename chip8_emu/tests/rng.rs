@@ -0,0 +1,70 @@
+extern crate chip8;
+
+use chip8::Chip8;
+use chip8::Platform;
+use chip8::Rng;
+
+// v0 := random 0xFF, then loops back to the start
+const ROM: [u8; 4] = [0xC0, 0xFF, 0x12, 0x00];
+
+fn machine(rng: Rng) -> Chip8 {
+    let quirks = Platform::Chip8.default_quirks();
+    Chip8::new(&ROM, Platform::Chip8, quirks, rng).expect("Rom fits")
+}
+
+// The next few numbers drawn by CXKK, without any frames in between
+fn draw(chip8: &mut Chip8, count: usize) -> Vec<u8> {
+    (0..count)
+        .map(|_| {
+            chip8.run_cycle().expect("Rom runs");
+            chip8.run_cycle().expect("Rom runs");
+            chip8.cpu.regs.v_regs[0]
+        })
+        .collect()
+}
+
+#[test]
+fn same_seed_draws_the_same_numbers() {
+    let first = draw(&mut machine(Rng::new(42)), 32);
+    assert_eq!(first, draw(&mut machine(Rng::new(42)), 32));
+    assert_ne!(first, draw(&mut machine(Rng::new(43)), 32));
+
+    // A seed of 0 still gets going rather than drawing 0 forever
+    let zero = draw(&mut machine(Rng::new(0)), 32);
+    assert!(zero.iter().any(|&n| n != 0));
+    assert_eq!(zero, draw(&mut machine(Rng::new(0)), 32));
+}
+
+#[test]
+fn cosmac_vip_generator_adds_up_the_font_bytes() {
+    // Starting at index 0 with 0, each number adds the next byte of the
+    // font, 0x90 0x90 0x90 0xF0 from the 0 and then 0x20 from the 1
+    let mut chip8 = machine(Rng::cosmac_vip(0));
+    assert_eq!(draw(&mut chip8, 5), [0x90, 0x20, 0xB0, 0xA0, 0xC0]);
+
+    // The seed's low byte is the index and the next one the last number
+    let mut chip8 = machine(Rng::cosmac_vip(0x0103));
+    assert_eq!(draw(&mut chip8, 2), [0xF1, 0x11]);
+
+    // Each frame moves the index on by one as well, skipping a byte
+    let mut chip8 = machine(Rng::cosmac_vip(0));
+    chip8.run_frame(2).expect("Rom runs");
+    assert_eq!(chip8.cpu.regs.v_regs[0], 0x90);
+    chip8.run_frame(2).expect("Rom runs");
+    assert_eq!(chip8.cpu.regs.v_regs[0], 0x80);
+}
+
+#[test]
+fn save_states_keep_the_generator() {
+    for &rng in [Rng::new(7), Rng::cosmac_vip(7)].iter() {
+        let mut chip8 = machine(rng);
+        draw(&mut chip8, 10);
+        let state = chip8.save_state();
+        let expected = draw(&mut chip8, 10);
+
+        // A machine seeded differently picks up where the state left off
+        let mut other = machine(Rng::new(1));
+        other.load_state(&state).expect("State loads");
+        assert_eq!(draw(&mut other, 10), expected);
+    }
+}
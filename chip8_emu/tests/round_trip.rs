@@ -0,0 +1,119 @@
+extern crate chip8;
+
+use chip8::assemble;
+use chip8::detect_platform;
+use chip8::disassemble;
+use chip8::Opcode;
+use chip8::Platform;
+use chip8::Syntax;
+
+const PROGRAM: &str = "
+:alias counter v3
+:const SPEED 4
+
+: main
+    clear
+    i := digit
+    counter := 0
+    loop
+        sprite counter v1 5
+        counter += SPEED
+        if counter != 60 then
+    again
+    draw-score
+    v0 := key
+    jump main
+
+: draw-score
+    i := score
+    bcd v2
+    load v2
+    i := hex v0
+    sprite v4 v5 5
+    return
+
+: digit
+    0xF0 0x90 0xF0 0x90 0xF0
+: score
+    0 0 0
+";
+
+#[test]
+fn encode_inverts_decode() {
+    let words = [
+        0x00E0, 0x00EE, 0x00FB, 0x00FC, 0x00FD, 0x00FE, 0x00FF, 0x00C3, 0x00D4,
+        0xF000, 0xF002, 0xF201, 0xF13A, 0x5122, 0x5123, 0xF330, 0xF475, 0xF585,
+        0xE19E, 0xE2A1, 0xF307, 0xF40A, 0xF515, 0xF618, 0xF71E, 0xF829, 0xF933,
+        0xFA55, 0xFB65, 0x5AB0, 0x8120, 0x8121, 0x8122, 0x8123, 0x8124, 0x8125,
+        0x8126, 0x8127, 0x812E, 0x9CD0, 0x0123, 0x1234, 0x2345, 0x3456, 0x4567,
+        0x6789, 0x789A, 0xA9BC, 0xBCDE, 0xCDEF, 0xD125,
+    ];
+    for word in words.iter() {
+        let op = Opcode::decode_op(*word).expect("Valid opcode");
+        assert_eq!(op.encode(), *word, "{:04X} -> {:?}", word, op);
+    }
+}
+
+#[test]
+fn assembled_rom_survives_disassembly() {
+    let rom = assemble(PROGRAM).expect("Program assembles");
+    assert_eq!(&rom[..2], &[0x00, 0xE0], "main needs no jump when first");
+    let source = disassemble(&rom, Platform::Chip8)
+        .expect("Rom fits")
+        .render(Syntax::Octo);
+    assert_eq!(assemble(&source).expect("Listing assembles"), rom);
+}
+
+#[test]
+fn errors_carry_line_numbers() {
+    let err = assemble(": main\n    v0 := 300\n").unwrap_err();
+    assert_eq!(err.line, 2);
+    let err = assemble(": main\n    jump nowhere\n").unwrap_err();
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn platform_is_detected_from_the_code() {
+    let rom = assemble(PROGRAM).expect("Program assembles");
+    assert_eq!(detect_platform(&rom), Platform::Chip8);
+    // hires, then loop forever
+    assert_eq!(
+        detect_platform(&[0x00, 0xFF, 0x12, 0x02]),
+        Platform::SuperChip
+    );
+    // i := long 0x1234, then loop forever
+    assert_eq!(
+        detect_platform(&[0xF0, 0x00, 0x12, 0x34, 0x12, 0x04]),
+        Platform::XoChip
+    );
+    // Data that happens to look like hires doesn't count
+    assert_eq!(detect_platform(&[0x12, 0x00, 0x00, 0xFF]), Platform::Chip8);
+    assert_eq!(detect_platform(&[0; 0x1000]), Platform::XoChip);
+}
+
+#[test]
+fn rom_filling_xo_chip_memory_disassembles() {
+    // v0 := 0 over and over, running into the very last address
+    let code: Vec<u8> =
+        [0x60, 0x00].iter().cycle().take(0xFE00).cloned().collect();
+    let listing = disassemble(&code, Platform::XoChip)
+        .expect("Rom fits")
+        .render(Syntax::Cowgod);
+    assert!(listing.trim_end().ends_with("FFFE: 6000  LD V0, #00"));
+    // Bytes that aren't instructions, listed as data up to the end
+    let data = vec![0xFF; 0xFE00];
+    let listing = disassemble(&data, Platform::XoChip)
+        .expect("Rom fits")
+        .render(Syntax::Cowgod);
+    assert!(listing
+        .trim_end()
+        .ends_with("FFF8:        DB #FF, #FF, #FF, #FF, #FF, #FF, #FF, #FF"));
+}
+
+#[test]
+fn roms_too_big_for_memory_are_refused() {
+    assert!(disassemble(&[0; 0xFE01], Platform::XoChip).is_err());
+    assert!(disassemble(&[0; 70002], Platform::XoChip).is_err());
+    assert!(disassemble(&[0; 0xE01], Platform::Chip8).is_err());
+    assert!(disassemble(&[0; 0xE00], Platform::Chip8).is_ok());
+}
@@ -0,0 +1,104 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::StateError;
+use chip8::STATE_VERSION;
+use common::machine;
+
+// Counts in v2 and draws a random number in v3, forever
+const ROM: [u8; 6] = [0x72, 0x01, 0xC3, 0xFF, 0x12, 0x00];
+
+#[test]
+fn saved_state_loads_back() {
+    let mut chip8 = machine(&ROM);
+    chip8.run_frame(50).expect("Rom runs");
+    let state = chip8.save_state();
+    let hash = chip8.state_hash();
+    chip8.run_frame(50).expect("Rom runs");
+    assert_ne!(chip8.state_hash(), hash);
+    chip8.load_state(&state).expect("State loads");
+    assert_eq!(chip8.state_hash(), hash);
+    // A fresh machine with the same rom picks up where the state left off
+    let mut other = machine(&ROM);
+    other.load_state(&state).expect("State loads");
+    assert_eq!(other.state_hash(), hash);
+}
+
+#[test]
+fn state_for_another_rom_is_refused() {
+    let state = machine(&ROM).save_state();
+    let mut other = machine(&[0x12, 0x00]);
+    match other.load_state(&state) {
+        Err(StateError::RomMismatch { .. }) => {}
+        other => panic!("Expected a rom mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn state_from_another_version_is_refused() {
+    let mut state = machine(&ROM).save_state();
+    let version = (STATE_VERSION + 1).to_le_bytes();
+    state[4..6].copy_from_slice(&version);
+    match machine(&ROM).load_state(&state) {
+        Err(StateError::UnsupportedVersion(found)) => {
+            assert_eq!(found, STATE_VERSION + 1)
+        }
+        other => panic!("Expected an unsupported version, got {:?}", other),
+    }
+    assert!(machine(&ROM).load_state(b"not a state").is_err());
+}
+
+#[test]
+fn flipped_payload_byte_is_caught() {
+    let mut state = machine(&ROM).save_state();
+    let last = state.len() - 1;
+    state[last] ^= 0x01;
+    match machine(&ROM).load_state(&state) {
+        Err(StateError::ChecksumMismatch) => {}
+        other => panic!("Expected a checksum mismatch, got {:?}", other),
+    }
+}
+
+// States with a valid checksum that would crash the emulator later on
+#[test]
+fn impossible_machines_are_refused() {
+    let mut chip8 = machine(&ROM);
+    chip8.cpu.screen.buffer.pop();
+    let short_screen = chip8.save_state();
+    let mut chip8 = machine(&ROM);
+    chip8.cpu.screen.width = 128;
+    let wrong_resolution = chip8.save_state();
+    let mut chip8 = machine(&ROM);
+    chip8.cpu.screen.buffer[0] = 7;
+    let bad_pixel = chip8.save_state();
+    let mut chip8 = machine(&ROM);
+    chip8.cpu.keyboard.wait_press = Some(16);
+    let missing_register = chip8.save_state();
+    for state in &[short_screen, wrong_resolution, bad_pixel, missing_register]
+    {
+        let mut chip8 = machine(&ROM);
+        let hash = chip8.state_hash();
+        match chip8.load_state(state) {
+            Err(StateError::Corrupt(_)) => {}
+            other => panic!("Expected a corrupt state, got {:?}", other),
+        }
+        assert_eq!(chip8.state_hash(), hash, "Machine is left untouched");
+    }
+}
+
+// Running off the end of memory is something a rom can do, so such a state
+// loads and faults when run rather than being refused
+#[test]
+fn program_counter_past_memory_faults() {
+    // v0 := 0 over and over, right up to the last address
+    let rom: Vec<u8> =
+        [0x60, 0x00].iter().cycle().take(0xE00).cloned().collect();
+    let mut chip8 = machine(&rom);
+    chip8.run_frame(0x700).expect("Rom runs");
+    assert_eq!(chip8.cpu.regs.pc(), 0x1000);
+    let state = chip8.save_state();
+    let mut other = machine(&rom);
+    other.load_state(&state).expect("State loads");
+    assert!(other.run_frame(1).is_err());
+}
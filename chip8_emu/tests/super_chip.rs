@@ -0,0 +1,147 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Chip8;
+use chip8::Platform;
+use chip8::Rng;
+use common::machine_on;
+
+fn run(rom: &[u8], cycles: u32) -> Chip8 {
+    let mut chip8 = machine_on(rom, Platform::SuperChip);
+    chip8.run_frame(cycles).expect("Rom runs");
+    chip8
+}
+
+// Every lit pixel as (x, y)
+fn lit(chip8: &Chip8) -> Vec<(usize, usize)> {
+    let screen = &chip8.cpu.screen;
+    (0..screen.height)
+        .flat_map(|y| (0..screen.width).map(move |x| (x, y)))
+        .filter(|&(x, y)| screen.get_pixel(x, y) != 0)
+        .collect()
+}
+
+// i := the solid sprite after the code, then sprite v0 v0 0
+fn large_sprite(hires: bool) -> Vec<u8> {
+    let mut rom = if hires { vec![0x00, 0xFF] } else { vec![] };
+    let data = 0x200 + rom.len() as u16 + 4;
+    rom.extend(&[0xA0 | (data >> 8) as u8, data as u8, 0xD0, 0x00]);
+    rom.extend(&[0xFF; 32]);
+    rom
+}
+
+#[test]
+fn scrolling_moves_the_screen() {
+    // v0 := 8, i := 0x20E, sprite v0 v1 1, then scroll-down 2, scroll-right,
+    // scroll-left twice and a single pixel sprite
+    let rom = [
+        0x60, 0x08, 0xA2, 0x0E, 0xD0, 0x11, 0x00, 0xC2, 0x00, 0xFB, 0x00, 0xFC,
+        0x00, 0xFC, 0x80,
+    ];
+    let mut chip8 = run(&rom, 3);
+    assert_eq!(lit(&chip8), [(8, 0)]);
+    let expected = [(8, 2), (12, 2), (8, 2), (4, 2)];
+    for pixel in expected.iter() {
+        chip8.run_cycle().expect("Rom runs");
+        assert_eq!(lit(&chip8), [*pixel]);
+    }
+}
+
+#[test]
+fn switching_resolution_resizes_and_clears_the_screen() {
+    // sprite v0 v0 5 with the font's 0, then high, the same sprite, then low
+    let rom = [0xD0, 0x05, 0x00, 0xFF, 0xD0, 0x05, 0x00, 0xFE];
+    let mut chip8 = run(&rom, 1);
+    assert!(!lit(&chip8).is_empty());
+
+    chip8.run_cycle().expect("Rom runs");
+    assert_eq!((chip8.cpu.screen.width, chip8.cpu.screen.height), (128, 64));
+    assert!(chip8.cpu.screen.hires);
+    assert!(lit(&chip8).is_empty());
+
+    chip8.run_cycle().expect("Rom runs");
+    assert!(!lit(&chip8).is_empty());
+    chip8.run_cycle().expect("Rom runs");
+    assert_eq!((chip8.cpu.screen.width, chip8.cpu.screen.height), (64, 32));
+    assert!(!chip8.cpu.screen.hires);
+    assert!(lit(&chip8).is_empty());
+}
+
+#[test]
+fn large_sprites_are_16_wide_in_high_resolution_only() {
+    let chip8 = run(&large_sprite(true), 3);
+    let pixels = lit(&chip8);
+    assert_eq!(pixels.len(), 16 * 16);
+    assert!(pixels.iter().all(|&(x, y)| x < 16 && y < 16));
+
+    let chip8 = run(&large_sprite(false), 2);
+    let pixels = lit(&chip8);
+    assert_eq!(pixels.len(), 8 * 16);
+    assert!(pixels.iter().all(|&(x, y)| x < 8 && y < 16));
+
+    // XO-CHIP draws them 16 wide either way
+    let mut chip8 = machine_on(&large_sprite(false), Platform::XoChip);
+    chip8.run_frame(2).expect("Rom runs");
+    assert_eq!(lit(&chip8).len(), 16 * 16);
+
+    // Plain CHIP-8 draws nothing for a sprite with no rows
+    let mut chip8 = machine_on(&large_sprite(false), Platform::Chip8);
+    chip8.run_frame(2).expect("Rom runs");
+    assert!(lit(&chip8).is_empty());
+}
+
+#[test]
+fn big_font_digits_are_ten_rows_tall() {
+    // v0 := 1, bighex v0, then sprite v1 v1 10
+    let chip8 = run(&[0x60, 0x01, 0xF0, 0x30, 0xD1, 0x1A], 3);
+    let i = chip8.cpu.regs.i_reg();
+    let zero = run(&[0xF0, 0x30], 1).cpu.regs.i_reg();
+    assert_eq!(i, zero + 10);
+    for y in 0..10 {
+        let byte = chip8.cpu.ram.peek(i as usize + y);
+        assert_ne!(byte, 0);
+        for x in 0..8 {
+            let set = byte & (0x80 >> x) != 0;
+            assert_eq!(chip8.cpu.screen.get_pixel(x, y) != 0, set);
+        }
+    }
+}
+
+#[test]
+fn rpl_flags_hold_registers_across_resets() {
+    // v0 := 1, v1 := 2, v2 := 3, saveflags v2, clear them, loadflags v2
+    let rom = [
+        0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xF2, 0x75, 0x60, 0x00, 0x61, 0x00,
+        0x62, 0x00, 0xF2, 0x85,
+    ];
+    let mut chip8 = run(&rom, 8);
+    assert_eq!(chip8.cpu.rpl_flags[..3], [1, 2, 3]);
+    assert_eq!(chip8.cpu.regs.v_regs[..3], [1, 2, 3]);
+
+    let load = [0xF2, 0x85];
+    chip8.reset(&load, Rng::new(0)).expect("Rom fits");
+    chip8.run_frame(1).expect("Rom runs");
+    assert_eq!(chip8.cpu.regs.v_regs[..3], [1, 2, 3]);
+}
+
+#[test]
+fn exit_stops_the_machine() {
+    // exit, then v1 := 1
+    let chip8 = run(&[0x00, 0xFD, 0x61, 0x01], 5);
+    assert!(chip8.cpu.exited);
+    assert!(chip8.is_blocked());
+    assert_eq!(chip8.cpu.regs.v_regs[1], 0);
+}
+
+#[test]
+fn plain_chip8_skips_them_as_machine_code_calls() {
+    // high, scroll-down 1, scroll-right, exit, then v1 := 1
+    let rom = [0x00, 0xFF, 0x00, 0xC1, 0x00, 0xFB, 0x00, 0xFD, 0x61, 0x01];
+    let mut chip8 = machine_on(&rom, Platform::Chip8);
+    chip8.run_frame(5).expect("Rom runs");
+    assert!(!chip8.cpu.screen.hires);
+    assert!(!chip8.cpu.exited);
+    assert_eq!(chip8.cpu.regs.v_regs[1], 1);
+    assert_eq!(chip8.cpu.regs.pc(), 0x20A);
+}
@@ -0,0 +1,127 @@
+extern crate chip8;
+
+mod common;
+
+use chip8::Chip8;
+use chip8::Platform;
+use chip8::Rng;
+use common::machine_on;
+
+fn run(rom: &[u8], cycles: u32) -> Chip8 {
+    let mut chip8 = machine_on(rom, Platform::XoChip);
+    chip8.run_frame(cycles).expect("Rom runs");
+    chip8
+}
+
+// The planes lit in the first few pixels of a row
+fn row(chip8: &Chip8, y: usize) -> Vec<u8> {
+    (0..8).map(|x| chip8.cpu.screen.get_pixel(x, y)).collect()
+}
+
+#[test]
+fn long_index_load_takes_two_words() {
+    let chip8 = run(&[0xF0, 0x00, 0x12, 0x34], 1);
+    assert_eq!(chip8.cpu.regs.i_reg(), 0x1234);
+    assert_eq!(chip8.cpu.regs.pc(), 0x204);
+}
+
+#[test]
+fn skipping_a_long_index_load_skips_both_words() {
+    // if v0 == 0 then skip i := long 0x1234, then v1 := 1
+    let rom = [0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x61, 0x01];
+    let chip8 = run(&rom, 2);
+    assert_eq!(chip8.cpu.regs.i_reg(), 0);
+    assert_eq!(chip8.cpu.regs.v_regs[1], 1);
+    assert_eq!(chip8.cpu.regs.pc(), 0x208);
+}
+
+#[test]
+fn register_ranges_save_and_load_in_either_order() {
+    // v1 := 1, v2 := 2, v3 := 3, i := 0x300, save v1 - v3, then load them
+    // back into v4 - v6 and the other way round into v9 - v7
+    let rom = [
+        0x61, 0x01, 0x62, 0x02, 0x63, 0x03, 0xA3, 0x00, 0x51, 0x32, 0x54, 0x63,
+        0x59, 0x73,
+    ];
+    let chip8 = run(&rom, 7);
+    assert_eq!(chip8.cpu.ram.peek(0x300), 1);
+    assert_eq!(chip8.cpu.ram.peek(0x301), 2);
+    assert_eq!(chip8.cpu.ram.peek(0x302), 3);
+    assert_eq!(chip8.cpu.regs.v_regs[4..7], [1, 2, 3]);
+    assert_eq!(chip8.cpu.regs.v_regs[7..10], [3, 2, 1]);
+    assert_eq!(chip8.cpu.regs.i_reg(), 0x300);
+
+    // The same, saving v3 - v1 backwards
+    let rom = [0x61, 0x01, 0x62, 0x02, 0x63, 0x03, 0xA3, 0x00, 0x53, 0x12];
+    let chip8 = run(&rom, 5);
+    assert_eq!(chip8.cpu.ram.peek(0x300), 3);
+    assert_eq!(chip8.cpu.ram.peek(0x301), 2);
+    assert_eq!(chip8.cpu.ram.peek(0x302), 1);
+}
+
+#[test]
+fn each_selected_plane_draws_its_own_sprite_data() {
+    // plane 3, i := 0x206, sprite v0 v0 1, then the data for both planes
+    let chip8 = run(&[0xF3, 0x01, 0xA2, 0x06, 0xD0, 0x01, 0xF0, 0x3C], 3);
+    assert_eq!(row(&chip8, 0), [1, 1, 3, 3, 2, 2, 0, 0]);
+    assert_eq!(chip8.cpu.regs.v_regs[0xF], 0);
+
+    // With only the second plane selected it gets the first byte
+    let chip8 = run(&[0xF2, 0x01, 0xA2, 0x06, 0xD0, 0x01, 0xF0, 0x3C], 3);
+    assert_eq!(row(&chip8, 0), [2, 2, 2, 2, 0, 0, 0, 0]);
+}
+
+#[test]
+fn scrolling_moves_only_the_selected_planes() {
+    // Draws on both planes as above, then plane 1 and scroll-down 1
+    let rom = [
+        0xF3, 0x01, 0xA2, 0x0A, 0xD0, 0x01, 0xF1, 0x01, 0x00, 0xC1, 0xF0, 0x3C,
+    ];
+    let chip8 = run(&rom, 5);
+    assert_eq!(row(&chip8, 0), [0, 0, 2, 2, 2, 2, 0, 0]);
+    assert_eq!(row(&chip8, 1), [1, 1, 1, 1, 0, 0, 0, 0]);
+
+    // Scrolling up with plane 2 leaves plane 1 where it was
+    let rom = [
+        0xF3, 0x01, 0xA2, 0x0A, 0xD0, 0x11, 0xF2, 0x01, 0x00, 0xD1, 0xF0, 0x3C,
+    ];
+    let mut chip8 = machine_on(&rom, Platform::XoChip);
+    chip8.cpu.regs.v_regs[1] = 1;
+    chip8.run_frame(5).expect("Rom runs");
+    assert_eq!(row(&chip8, 0), [0, 0, 2, 2, 2, 2, 0, 0]);
+    assert_eq!(row(&chip8, 1), [1, 1, 1, 1, 0, 0, 0, 0]);
+}
+
+#[test]
+fn audio_pattern_and_pitch_are_loaded() {
+    // i := 0x208, audio, v1 := 0x70, pitch := v1, then the pattern
+    let mut rom = vec![0xA2, 0x08, 0xF0, 0x02, 0x61, 0x70, 0xF1, 0x3A];
+    let pattern: Vec<u8> = (0..16).map(|n| n * 0x11).collect();
+    rom.extend(&pattern);
+    let chip8 = run(&rom, 4);
+    assert_eq!(chip8.cpu.audio_pattern[..], pattern[..]);
+    assert_eq!(chip8.cpu.pitch, 0x70);
+}
+
+#[test]
+fn memory_reaches_64_kib() {
+    let max = Platform::XoChip.ram_size() - 0x200;
+    let quirks = Platform::XoChip.default_quirks();
+    let fits = vec![0; max];
+    assert!(Chip8::new(&fits, Platform::XoChip, quirks, Rng::new(0)).is_ok());
+    let too_big = vec![0; max + 1];
+    assert!(
+        Chip8::new(&too_big, Platform::XoChip, quirks, Rng::new(0)).is_err()
+    );
+
+    // i := long 0xFFFF, v0 := 0xAB, save v0 writes the last byte
+    let rom = [0xF0, 0x00, 0xFF, 0xFF, 0x60, 0xAB, 0xF0, 0x55];
+    let chip8 = run(&rom, 3);
+    assert_eq!(chip8.cpu.ram.peek(0xFFFF), 0xAB);
+
+    // Saving v0 - v1 there runs past the end
+    let rom = [0xF0, 0x00, 0xFF, 0xFF, 0xF1, 0x55];
+    let mut chip8 = machine_on(&rom, Platform::XoChip);
+    let fault = chip8.run_frame(2).expect_err("Write past memory");
+    assert_eq!(fault.pc, 0x204);
+}
@@ -0,0 +1,291 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use chip8::FaultPolicies;
+use chip8::HeadlessOptions;
+use chip8::Platform;
+use chip8::Quirks;
+use chip8::Syntax;
+use chip8::FRAME_RATE;
+use config::Config;
+use panel::KeypadView;
+
+pub const USAGE: &str = "Usage: chip8_frontend [command] <rom> [options]
+
+Commands:
+    run <rom>           Play a rom, the default when no command is given
+    info <rom>          Show the rom's size, hash and the platform it needs
+    disasm <rom> [platform] [cowgod|octo]
+                        Print the rom's disassembly
+    headless <rom> [options]
+                        Run the rom without a window, see headless --help
+    help                Show this message
+
+Options for run:
+    --platform <chip8|schip|xochip>
+    --quirks <vip|chip48|schip|xochip>
+    --speed <hz>        Instructions per second
+    --ipf <n>           Instructions per frame, the same as --speed n*60
+    --scale <n>         Size of a low resolution pixel in window pixels
+    --seed <n>          Seed for the random number generator, a new one
+                        every run by default
+    --fullscreen        Start in fullscreen
+    --keypad <hidden|side|overlay>
+                        Show a keypad to click or touch
+    --paused            Start paused, ready to step
+    --on-fault <policy> halt, skip or break per fault class, like
+                        \"halt,undefined=skip\"
+    --load-report <file>
+                        Restore a crash report, paused where it faulted
+    --config <file>     Read settings from this file instead of
+                        $XDG_CONFIG_HOME/res/config.toml
+    --dump-default-config
+                        Print the default config file and exit
+
+Options given here win over the config file.";
+
+pub const DISASM_USAGE: &str = "Usage: disasm <rom> [platform] [cowgod|octo]
+    platform  chip8, schip or xochip, default chip8
+    syntax    cowgod or octo, default cowgod";
+
+pub const HEADLESS_USAGE: &str = "Usage: headless <rom> [options]
+    --platform <chip8|schip|xochip>
+    --quirks <vip|chip48|schip|xochip>
+    --seed <n>          Seed for the random number generator, default 0
+    --frames <n>        Frames to run at most, default 600
+    --cycles <n>        Instructions per frame, default 9
+    --until-pc <addr>   Stop before executing the instruction at addr
+    --until <cond>      Stop once a condition like \"V3 == 0x10\" is true
+    --keys <file>       Key script of \"<frame> <key> down|up\" lines
+    --screen <file>     Write the screen as .pbm, .png or text, - for stdout
+    --regs <file>       Write the registers as JSON, - for stdout
+    --on-fault <policy> halt, skip or break per fault class, like
+                        \"halt,undefined=skip\", default halt
+    --crash-report <file> Write a crash report if the rom faults
+    --cache             Cache decoded instructions
+    --bench             Report instructions per second with and without the
+                        instruction cache, 10000 instructions per frame
+                        unless --cycles is given
+Exits with 0 on success, 2 on timeout and 3 on an invalid opcode.";
+
+pub enum Command {
+    Run(RunOptions),
+    Info(String),
+    Disasm(DisasmOptions),
+    Headless(HeadlessArgs),
+    // Usage of the front end or one of the tools
+    Help(&'static str),
+    DumpDefaultConfig,
+}
+
+pub struct DisasmOptions {
+    pub rom: String,
+    pub platform: Platform,
+    pub syntax: Syntax,
+}
+
+// The headless runner's options, and the files it reads and writes
+#[derive(Debug, Default)]
+pub struct HeadlessArgs {
+    pub rom: String,
+    pub options: HeadlessOptions,
+    pub keys: Option<String>,
+    pub screen: Option<String>,
+    pub regs: Option<String>,
+    pub crash_report: Option<String>,
+    pub bench: bool,
+}
+
+// Everything left as None or false comes from the config file
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    pub rom: String,
+    pub config: Option<PathBuf>,
+    pub load_report: Option<PathBuf>,
+    pub platform: Option<Platform>,
+    pub quirks: Option<Quirks>,
+    pub on_fault: Option<FaultPolicies>,
+    pub speed: Option<u32>,
+    pub scale: Option<u32>,
+    pub keypad: Option<KeypadView>,
+    pub seed: Option<u64>,
+    pub fullscreen: bool,
+    pub paused: bool,
+}
+
+impl RunOptions {
+    // The platform and quirks are left out, as a crash report picks those
+    // unless they're given here
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(speed) = self.speed {
+            config.emulation.clock_speed = speed;
+        }
+        if let Some(scale) = self.scale {
+            config.window.scale = scale;
+        }
+        if let Some(on_fault) = self.on_fault {
+            config.emulation.on_fault = on_fault;
+        }
+        if let Some(keypad) = self.keypad {
+            config.window.keypad = keypad;
+        }
+        config.window.fullscreen |= self.fullscreen;
+    }
+}
+
+// Parses the arguments after the program name. A rom on its own is run.
+pub fn parse(args: Vec<String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let command = match args.next() {
+        Some(command) => command,
+        None => return Err(USAGE.to_string()),
+    };
+    let rest: Vec<String> = args.collect();
+    let wants_help = rest.iter().any(|arg| arg == "--help" || arg == "-h");
+    match command.as_str() {
+        // The tools have usage of their own
+        "disasm" if wants_help => Ok(Command::Help(DISASM_USAGE)),
+        "headless" if wants_help => Ok(Command::Help(HEADLESS_USAGE)),
+        "disasm" => parse_disasm(rest),
+        "headless" => parse_headless(rest),
+        "help" | "--help" | "-h" => Ok(Command::Help(USAGE)),
+        _ if wants_help => Ok(Command::Help(USAGE)),
+        "--dump-default-config" => Ok(Command::DumpDefaultConfig),
+        "info" => match rest.as_slice() {
+            [rom] => Ok(Command::Info(rom.clone())),
+            _ => Err(format!("Usage: info <rom>\n\n{}", USAGE)),
+        },
+        "run" => parse_run(rest),
+        _ => parse_run(Some(command).into_iter().chain(rest).collect()),
+    }
+}
+
+fn parse_run(args: Vec<String>) -> Result<Command, String> {
+    let mut options = RunOptions::default();
+    let mut rom = None;
+    let mut ipf = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fullscreen" => options.fullscreen = true,
+            "--paused" => options.paused = true,
+            "--dump-default-config" => return Ok(Command::DumpDefaultConfig),
+            flag if flag.starts_with('-') => {
+                let value = args.next().ok_or(format!(
+                    "Missing value for {}\n\n{}",
+                    flag, USAGE
+                ))?;
+                match flag {
+                    "--platform" => options.platform = Some(value.parse()?),
+                    "--quirks" => options.quirks = Some(value.parse()?),
+                    "--on-fault" => options.on_fault = Some(value.parse()?),
+                    "--speed" => options.speed = Some(positive(&value)?),
+                    "--ipf" => ipf = Some(positive(&value)?),
+                    "--scale" => options.scale = Some(positive(&value)?),
+                    "--keypad" => options.keypad = Some(value.parse()?),
+                    "--seed" => options.seed = Some(number(&value)?),
+                    "--config" => options.config = Some(value.into()),
+                    "--load-report" => options.load_report = Some(value.into()),
+                    _ => {
+                        return Err(format!(
+                            "Unknown option {}\n\n{}",
+                            flag, USAGE
+                        ))
+                    }
+                }
+            }
+            _ if rom.is_none() => rom = Some(arg),
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+    options.rom = rom.ok_or(format!("Did not get a rom\n\n{}", USAGE))?;
+    match (options.speed, ipf) {
+        (Some(_), Some(_)) => {
+            return Err("Give either --speed or --ipf, not both".to_string())
+        }
+        (None, Some(ipf)) => {
+            let speed = ipf
+                .checked_mul(FRAME_RATE)
+                .ok_or(format!("Too many instructions per frame: {}", ipf))?;
+            options.speed = Some(speed)
+        }
+        _ => {}
+    }
+    Ok(Command::Run(options))
+}
+
+fn parse_disasm(args: Vec<String>) -> Result<Command, String> {
+    let rom = args.first().ok_or(DISASM_USAGE)?.clone();
+    let platform = match args.get(1) {
+        Some(name) => name.parse()?,
+        None => Platform::default(),
+    };
+    let syntax = match args.get(2) {
+        Some(name) => name.parse()?,
+        None => Syntax::Cowgod,
+    };
+    if args.len() > 3 {
+        return Err(format!("Unexpected argument {}", args[3]));
+    }
+    Ok(Command::Disasm(DisasmOptions {
+        rom,
+        platform,
+        syntax,
+    }))
+}
+
+fn parse_headless(args: Vec<String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut headless = HeadlessArgs {
+        rom: args.next().ok_or(HEADLESS_USAGE)?,
+        ..HeadlessArgs::default()
+    };
+    let options = &mut headless.options;
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--cache" => options.cache = true,
+            "--bench" => headless.bench = true,
+            _ => {
+                let value = args.next().ok_or(format!(
+                    "Missing value for {}\n\n{}",
+                    flag, HEADLESS_USAGE
+                ))?;
+                match flag.as_str() {
+                    "--platform" => options.platform = value.parse()?,
+                    "--quirks" => options.quirks = Some(value.parse()?),
+                    "--seed" => options.seed = number(&value)?,
+                    "--frames" => options.frames = number(&value)?,
+                    "--cycles" => options.cycles = Some(number(&value)?),
+                    "--until-pc" => options.until_pc.push(addr(&value)?),
+                    "--until" => options.until.push(value.parse()?),
+                    "--on-fault" => options.fault_policies = value.parse()?,
+                    "--keys" => headless.keys = Some(value),
+                    "--screen" => headless.screen = Some(value),
+                    "--regs" => headless.regs = Some(value),
+                    "--crash-report" => headless.crash_report = Some(value),
+                    _ => return Err(format!("Unknown option {}", flag)),
+                }
+            }
+        }
+    }
+    Ok(Command::Headless(headless))
+}
+
+fn number<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid number: {}", value))
+}
+
+fn positive(value: &str) -> Result<u32, String> {
+    match number(value)? {
+        0 => Err(format!("Must be at least 1: {}", value)),
+        value => Ok(value),
+    }
+}
+
+fn addr(value: &str) -> Result<u16, String> {
+    let hex = value.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(hex, 16)
+        .map_err(|_| format!("Invalid address: {}", value))
+}
@@ -0,0 +1,715 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use chip8::FaultPolicies;
+use chip8::Platform;
+use chip8::Quirks;
+use chip8::Waveform;
+use chip8::DEFAULT_CLOCK_SPEED;
+use chip8::DEFAULT_FREQUENCY;
+use chip8::DEFAULT_TIMER_RATE;
+use chip8::DEFAULT_VOLUME;
+use chip8::PALETTE;
+use gamepad::ControllerMap;
+use gamepad::Input;
+use keymap::Binding;
+use keymap::KeypadPreset;
+use panel::KeypadView;
+use sdl2::keyboard::Keycode;
+use sdl2::keyboard::Scancode;
+use serde::de;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Deserializer;
+use slots::SLOTS;
+
+const CONFIG_DIR: &str = "res";
+const CONFIG_FILE: &str = "config.toml";
+// Size of a low resolution pixel, high resolution pixels are half as big
+const DEFAULT_SCALE: u32 = 10;
+const DEFAULT_DEADZONE: f32 = 0.3;
+
+// Printed by --dump-default-config. It spells out the Default impls below,
+// which fill in anything a config file leaves out.
+pub const DEFAULT_CONFIG: &str = r##"# Res configuration
+#
+# Anything left out keeps the value shown here.
+
+[keys]
+# Keys are named after what they type on a US keyboard, using SDL's names
+# such as "A", "1", "Space", "Left Shift", "F5" or "Keypad 7", but stand for
+# where the key is. "Q" is the key left of "W" on an AZERTY keyboard too. Put
+# "char:" in front to bind what a key types in your layout instead, like
+# "char:Q". A key bound twice goes to the hex keypad.
+#
+# Ready made layouts for the hex keypad:
+# "qwerty"  the 1234/QWER/ASDF/ZXCV block, laid out like the COSMAC VIP's
+#           keypad
+# "numpad"  the numeric keypad's digits, with A to F on / * - + Enter and .
+# "vip"     the keys labelled 0 to 9 and A to F, on any layout
+preset = "qwerty"
+# Or a key for every hex key, in order from 0 to F, which wins over the
+# preset. Press the bind key below to set these up by pressing them instead.
+# keypad = [
+#     "X", "1", "2", "3",
+#     "Q", "W", "E", "A",
+#     "S", "D", "Z", "C",
+#     "4", "R", "F", "V",
+# ]
+pause = "P"
+# Step an instruction or a frame while paused
+step = "M"
+frame_advance = "N"
+# Held down
+rewind = "Backspace"
+fast_forward = "Tab"
+slower = "["
+faster = "]"
+reset = "Home"
+quit = "Escape"
+record = "F11"
+playback = "F12"
+mute = "End"
+volume_down = "-"
+volume_up = "="
+# Asks for a key for every hex key in turn
+bind = "Insert"
+# Switches the clickable keypad between hidden, beside the game and over it
+show_keypad = "K"
+# Saves to slot 1 to 10, holding shift loads from it instead
+save_slots = ["F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10"]
+
+[colours]
+# Colours as "#RRGGBB". XO-CHIP draws with all four, the other platforms only
+# use the background and foreground.
+background = "#000000"
+foreground = "#FFFFFF"
+second_plane = "#AAAAAA"
+both_planes = "#555555"
+
+[window]
+# Size of a low resolution pixel, high resolution pixels are half as big
+scale = 10
+fullscreen = false
+vsync = false
+# A COSMAC VIP keypad to click or touch, which also shows the keys held down:
+# "hidden", "side" for a panel right of the game or "overlay" for see-through
+# keys over the game
+keypad = "hidden"
+
+[emulation]
+# "chip8", "schip" or "xochip"
+platform = "chip8"
+# "vip", "chip48", "schip" or "xochip", defaults to the platform's own
+# quirks = "vip"
+# Instructions per second
+clock_speed = 540
+# How many times a second the delay and sound timers count down
+timer_rate = 60
+# "halt", "skip" or "break", optionally per fault class, for example
+# "break,undefined=skip"
+on_fault = "halt"
+
+[audio]
+enabled = true
+# Between 0 and 1
+volume = 0.25
+muted = false
+# "square", "triangle", "sawtooth" or "sine"
+waveform = "square"
+# Pitch of the beep in Hz
+frequency = 440.0
+
+[controllers]
+# How far a stick or trigger has to move before it counts, between 0 and 1
+deadzone = 0.3
+
+# What each player's controller presses, as input = "hex key". The first
+# controller plugged in plays as the first player, the next one as the second
+# and so on. Controllers past the last player share the first one's keys.
+#
+# Buttons go by SDL's names: a, b, x, y, back, guide, start, leftstick,
+# rightstick, leftshoulder, rightshoulder, dpup, dpdown, dpleft and dpright.
+# Stick directions are leftx, lefty, rightx or righty followed by - for left
+# and up or + for right and down. The triggers are lefttrigger and
+# righttrigger.
+#
+# A file next to the rom with the extension .controllers.toml, such as
+# pong.controllers.toml for pong.ch8, replaces these players for that rom. It
+# holds [[player]] tables laid out like the ones here.
+[[controllers.player]]
+dpup = "5"
+dpleft = "7"
+dpdown = "8"
+dpright = "9"
+"lefty-" = "5"
+"leftx-" = "7"
+"lefty+" = "8"
+"leftx+" = "9"
+a = "6"
+b = "4"
+
+# A second player for two player games, like Pong's paddles on C and D
+# [[controllers.player]]
+# dpup = "C"
+# dpdown = "D"
+"##;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub keys: Keys,
+    pub colours: Colours,
+    pub window: Window,
+    pub emulation: Emulation,
+    pub audio: Audio,
+    pub controllers: Controllers,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    #[serde(deserialize_with = "parsed")]
+    pub preset: KeypadPreset,
+    // Wins over the preset when given
+    pub keypad: Option<[Binding; 16]>,
+    pub pause: Binding,
+    pub step: Binding,
+    pub frame_advance: Binding,
+    pub rewind: Binding,
+    pub fast_forward: Binding,
+    pub slower: Binding,
+    pub faster: Binding,
+    pub reset: Binding,
+    pub quit: Binding,
+    pub record: Binding,
+    pub playback: Binding,
+    pub mute: Binding,
+    pub volume_down: Binding,
+    pub volume_up: Binding,
+    pub bind: Binding,
+    pub show_keypad: Binding,
+    pub save_slots: [Binding; SLOTS],
+}
+
+impl Keys {
+    // Indexed by hex key
+    pub fn keypad(&self) -> [Binding; 16] {
+        self.keypad.unwrap_or_else(|| self.preset.bindings())
+    }
+
+    // The hex key a key event is for, if any
+    pub fn keypad_key(
+        &self,
+        keycode: Option<Keycode>,
+        scancode: Option<Scancode>,
+    ) -> Option<usize> {
+        self.keypad()
+            .iter()
+            .position(|binding| binding.matches(keycode, scancode))
+    }
+
+    // The save slot a key event is for, counting from 1
+    pub fn save_slot(
+        &self,
+        keycode: Option<Keycode>,
+        scancode: Option<Scancode>,
+    ) -> Option<u8> {
+        self.save_slots
+            .iter()
+            .position(|binding| binding.matches(keycode, scancode))
+            .map(|index| index as u8 + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colours {
+    pub background: Colour,
+    pub foreground: Colour,
+    pub second_plane: Colour,
+    pub both_planes: Colour,
+}
+
+impl Colours {
+    // Indexed by the screen's pixel values, like chip8::PALETTE
+    pub fn palette(&self) -> [[u8; 3]; 4] {
+        [
+            self.background.0,
+            self.foreground.0,
+            self.second_plane.0,
+            self.both_planes.0,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Window {
+    #[serde(deserialize_with = "positive")]
+    pub scale: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    #[serde(deserialize_with = "parsed")]
+    pub keypad: KeypadView,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Emulation {
+    #[serde(deserialize_with = "parsed")]
+    pub platform: Platform,
+    // None picks the platform's default quirks
+    #[serde(deserialize_with = "parsed_option")]
+    pub quirks: Option<Quirks>,
+    #[serde(deserialize_with = "positive")]
+    pub clock_speed: u32,
+    #[serde(deserialize_with = "positive")]
+    pub timer_rate: u32,
+    #[serde(deserialize_with = "parsed")]
+    pub on_fault: FaultPolicies,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Audio {
+    pub enabled: bool,
+    #[serde(deserialize_with = "volume")]
+    pub volume: f32,
+    pub muted: bool,
+    #[serde(deserialize_with = "parsed")]
+    pub waveform: Waveform,
+    #[serde(deserialize_with = "frequency")]
+    pub frequency: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Controllers {
+    #[serde(deserialize_with = "deadzone")]
+    pub deadzone: f32,
+    pub player: Vec<ControllerMap>,
+}
+
+// The players for one rom, read from the .controllers.toml file next to it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RomControllers {
+    pub player: Vec<ControllerMap>,
+}
+
+impl Default for Keys {
+    fn default() -> Keys {
+        Keys {
+            preset: KeypadPreset::Qwerty,
+            keypad: None,
+            pause: Binding::Position(Scancode::P),
+            step: Binding::Position(Scancode::M),
+            frame_advance: Binding::Position(Scancode::N),
+            rewind: Binding::Position(Scancode::Backspace),
+            fast_forward: Binding::Position(Scancode::Tab),
+            slower: Binding::Position(Scancode::LeftBracket),
+            faster: Binding::Position(Scancode::RightBracket),
+            reset: Binding::Position(Scancode::Home),
+            quit: Binding::Position(Scancode::Escape),
+            record: Binding::Position(Scancode::F11),
+            playback: Binding::Position(Scancode::F12),
+            mute: Binding::Position(Scancode::End),
+            volume_down: Binding::Position(Scancode::Minus),
+            volume_up: Binding::Position(Scancode::Equals),
+            bind: Binding::Position(Scancode::Insert),
+            show_keypad: Binding::Position(Scancode::K),
+            save_slots: [
+                Scancode::F1,
+                Scancode::F2,
+                Scancode::F3,
+                Scancode::F4,
+                Scancode::F5,
+                Scancode::F6,
+                Scancode::F7,
+                Scancode::F8,
+                Scancode::F9,
+                Scancode::F10,
+            ]
+            .map(Binding::Position),
+        }
+    }
+}
+
+impl Default for Colours {
+    fn default() -> Colours {
+        Colours {
+            background: Colour(PALETTE[0]),
+            foreground: Colour(PALETTE[1]),
+            second_plane: Colour(PALETTE[2]),
+            both_planes: Colour(PALETTE[3]),
+        }
+    }
+}
+
+impl Default for Window {
+    fn default() -> Window {
+        Window {
+            scale: DEFAULT_SCALE,
+            fullscreen: false,
+            vsync: false,
+            keypad: KeypadView::Hidden,
+        }
+    }
+}
+
+impl Default for Emulation {
+    fn default() -> Emulation {
+        Emulation {
+            platform: Platform::default(),
+            quirks: None,
+            clock_speed: DEFAULT_CLOCK_SPEED,
+            timer_rate: DEFAULT_TIMER_RATE,
+            on_fault: FaultPolicies::default(),
+        }
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Audio {
+        Audio {
+            enabled: true,
+            volume: DEFAULT_VOLUME,
+            muted: false,
+            waveform: Waveform::Square,
+            frequency: DEFAULT_FREQUENCY,
+        }
+    }
+}
+
+impl Default for Controllers {
+    fn default() -> Controllers {
+        Controllers {
+            deadzone: DEFAULT_DEADZONE,
+            player: vec![ControllerMap::first_player()],
+        }
+    }
+}
+
+impl Config {
+    // Reads the given config file, or the one in the user's config directory
+    // if there is one. Only a file that was asked for has to exist.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.is_file() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        read(path)
+    }
+}
+
+impl RomControllers {
+    // The players set up for the rom, if it has a file of its own
+    pub fn load(rom: &Path) -> Result<Option<RomControllers>, ConfigError> {
+        let path = rom.with_extension("controllers.toml");
+        if path.is_file() {
+            read(path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn read<T: DeserializeOwned>(path: PathBuf) -> Result<T, ConfigError> {
+    let text = fs::read_to_string(&path)
+        .map_err(|err| ConfigError::Read(path.clone(), err))?;
+    parse(&text).map_err(|err| err.in_file(path))
+}
+
+fn parse<T: DeserializeOwned>(text: &str) -> Result<T, ConfigError> {
+    toml::from_str(text).map_err(|err| {
+        let (line, column) = match err.span() {
+            Some(span) => line_and_column(text, span.start),
+            None => (1, 1),
+        };
+        ConfigError::Invalid {
+            path: None,
+            line,
+            column,
+            message: err.message().to_string(),
+        }
+    })
+}
+
+// $XDG_CONFIG_HOME/res/config.toml, falling back to ~/.config like the XDG
+// base directory spec says to
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+        })?;
+    Some(config_home.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+// Both count from 1, like editors do
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+impl<'de> Deserialize<'de> for Binding {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Binding, D::Error> {
+        parsed(deserializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Input {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Input, D::Error> {
+        parsed(deserializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ControllerMap {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ControllerMap, D::Error> {
+        let map = HashMap::<Input, HexKey>::deserialize(deserializer)?;
+        Ok(ControllerMap(
+            map.into_iter().map(|(input, key)| (input, key.0)).collect(),
+        ))
+    }
+}
+
+// A single hex digit, 0 to F
+struct HexKey(usize);
+
+impl FromStr for HexKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<HexKey, String> {
+        match usize::from_str_radix(s, 16) {
+            Ok(key) if s.len() == 1 => Ok(HexKey(key)),
+            _ => Err(format!("Hex keys go from 0 to F, not {}", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HexKey {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HexKey, D::Error> {
+        parsed(deserializer)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Colour(pub [u8; 3]);
+
+impl FromStr for Colour {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Colour, String> {
+        let invalid = || format!("Colours are written as #RRGGBB, not {}", s);
+        let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+        let mut colour = [0; 3];
+        for (index, channel) in colour.iter_mut().enumerate() {
+            let digits =
+                hex.get(index * 2..index * 2 + 2).ok_or_else(invalid)?;
+            *channel = u8::from_str_radix(digits, 16).map_err(|_| invalid())?;
+        }
+        Ok(Colour(colour))
+    }
+}
+
+impl<'de> Deserialize<'de> for Colour {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Colour, D::Error> {
+        parsed(deserializer)
+    }
+}
+
+// Values written as strings the emulator already knows how to parse
+fn parsed<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(de::Error::custom)
+}
+
+fn parsed_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    parsed(deserializer).map(Some)
+}
+
+fn positive<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u32, D::Error> {
+    match u32::deserialize(deserializer)? {
+        0 => Err(de::Error::custom("Must be at least 1")),
+        value => Ok(value),
+    }
+}
+
+fn volume<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    match f32::deserialize(deserializer)? {
+        volume if (0.0..=1.0).contains(&volume) => Ok(volume),
+        volume => Err(de::Error::custom(format!(
+            "Volume must be between 0 and 1, not {}",
+            volume
+        ))),
+    }
+}
+
+fn frequency<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<f32, D::Error> {
+    match f32::deserialize(deserializer)? {
+        frequency if frequency > 0.0 => Ok(frequency),
+        frequency => Err(de::Error::custom(format!(
+            "Frequency must be above 0 Hz, not {}",
+            frequency
+        ))),
+    }
+}
+
+fn deadzone<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<f32, D::Error> {
+    match f32::deserialize(deserializer)? {
+        deadzone if (0.0..1.0).contains(&deadzone) => Ok(deadzone),
+        deadzone => Err(de::Error::custom(format!(
+            "Dead zone must be at least 0 and below 1, not {}",
+            deadzone
+        ))),
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Invalid {
+        // None when the config didn't come from a file
+        path: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    fn in_file(self, file: PathBuf) -> ConfigError {
+        match self {
+            ConfigError::Invalid {
+                line,
+                column,
+                message,
+                ..
+            } => ConfigError::Invalid {
+                path: Some(file),
+                line,
+                column,
+                message,
+            },
+            err => err,
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Read(_, err) => Some(err),
+            ConfigError::Invalid { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => {
+                write!(f, "Could not read {}: {}", path.display(), err)
+            }
+            ConfigError::Invalid {
+                path,
+                line,
+                column,
+                message,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}:", path.display())?;
+                }
+                // toml's messages can end in a newline
+                write!(f, "{}:{}: {}", line, column, message.trim_end())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_file_matches_the_defaults() {
+        let config: Config = parse(DEFAULT_CONFIG).expect("Default parses");
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn save_slots_can_be_rebound() {
+        let keys = Keys::default();
+        assert_eq!(keys.save_slot(None, Some(Scancode::F1)), Some(1));
+        assert_eq!(keys.save_slot(None, Some(Scancode::F10)), Some(10));
+        assert_eq!(keys.save_slot(None, Some(Scancode::F11)), None);
+
+        let text = "[keys]\nsave_slots = [\"1\", \"2\", \"3\", \"4\", \"5\", \
+                    \"6\", \"7\", \"8\", \"9\", \"0\"]\n";
+        let config: Config = parse(text).expect("Slots parse");
+        let keys = config.keys;
+        assert_eq!(keys.save_slot(None, Some(Scancode::Num0)), Some(10));
+        assert_eq!(keys.save_slot(None, Some(Scancode::F1)), None);
+        assert!(parse::<Config>("[keys]\nsave_slots = [\"F1\"]\n").is_err());
+    }
+
+    #[test]
+    fn invalid_entries_report_where_they_are() {
+        let text = "[window]\nfullscreen = true\nscale = 0\n";
+        match parse::<Config>(text) {
+            Err(ConfigError::Invalid { line, column, .. }) => {
+                assert_eq!((line, column), (3, 9))
+            }
+            other => panic!("Expected an invalid config, got {:?}", other),
+        }
+        let text = "[audio]\n\n  volum = 0.5\n";
+        match parse::<Config>(text) {
+            Err(ConfigError::Invalid { line, column, .. }) => {
+                assert_eq!((line, column), (3, 3))
+            }
+            other => panic!("Expected an invalid config, got {:?}", other),
+        }
+    }
+}
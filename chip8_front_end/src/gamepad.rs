@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use sdl2::controller::Axis;
+use sdl2::controller::Button;
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+use sdl2::Sdl;
+
+const AXIS_MAX: f32 = 32767.0;
+
+// Something on a controller that can press a hex key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    Button(Button),
+    // An axis pushed past the dead zone, in the positive direction if true.
+    // Up and left are negative.
+    Axis(Axis, bool),
+}
+
+// Buttons go by SDL's names, such as "a" or "dpup", and axes by SDL's name
+// and a direction, such as "leftx-". The triggers only go one way, so the
+// direction can be left off.
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Input, String> {
+        let axis = match s.chars().last() {
+            Some('+') => {
+                Axis::from_string(&s[..s.len() - 1]).map(|axis| (axis, true))
+            }
+            Some('-') => {
+                Axis::from_string(&s[..s.len() - 1]).map(|axis| (axis, false))
+            }
+            _ => Axis::from_string(s)
+                .filter(|axis| {
+                    *axis == Axis::TriggerLeft || *axis == Axis::TriggerRight
+                })
+                .map(|axis| (axis, true)),
+        };
+        match (axis, Button::from_string(s)) {
+            (Some((axis, positive)), _) => Ok(Input::Axis(axis, positive)),
+            (None, Some(button)) => Ok(Input::Button(button)),
+            (None, None) => Err(format!("Unknown controller input: {}", s)),
+        }
+    }
+}
+
+// The hex key each input of one player's controller presses
+#[derive(Debug, Clone, Default)]
+pub struct ControllerMap(pub Vec<(Input, usize)>);
+
+// Maps are equal when they press the same keys, whatever order the inputs
+// were listed in
+impl PartialEq for ControllerMap {
+    fn eq(&self, other: &ControllerMap) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().all(|entry| other.0.contains(entry))
+    }
+}
+
+impl ControllerMap {
+    // The D-pad and left stick on 5 7 8 9, the keys most games move with,
+    // and 6 and 4 on the face buttons
+    pub fn first_player() -> ControllerMap {
+        ControllerMap(vec![
+            (Input::Button(Button::DPadUp), 0x5),
+            (Input::Button(Button::DPadLeft), 0x7),
+            (Input::Button(Button::DPadDown), 0x8),
+            (Input::Button(Button::DPadRight), 0x9),
+            (Input::Axis(Axis::LeftY, false), 0x5),
+            (Input::Axis(Axis::LeftX, false), 0x7),
+            (Input::Axis(Axis::LeftY, true), 0x8),
+            (Input::Axis(Axis::LeftX, true), 0x9),
+            (Input::Button(Button::A), 0x6),
+            (Input::Button(Button::B), 0x4),
+        ])
+    }
+
+    // The hex keys pressed while the given inputs are held, one bit per key
+    fn keys(&self, held: &HashSet<Input>) -> u16 {
+        self.0
+            .iter()
+            .filter(|(input, _)| held.contains(input))
+            .fold(0, |keys, (_, key)| keys | 1 << key)
+    }
+}
+
+struct Connected {
+    controller: GameController,
+    player: usize,
+    held: HashSet<Input>,
+}
+
+// Every connected controller, each one playing as the first player that
+// doesn't have one yet
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    players: Vec<ControllerMap>,
+    deadzone: f32,
+    connected: Vec<Connected>,
+}
+
+impl Gamepads {
+    pub fn open(
+        sdl_context: &Sdl,
+        players: Vec<ControllerMap>,
+        deadzone: f32,
+    ) -> Result<Gamepads, String> {
+        Ok(Gamepads {
+            subsystem: sdl_context.game_controller()?,
+            players,
+            deadzone,
+            connected: Vec::new(),
+        })
+    }
+
+    // Follows controllers being plugged in and out and their inputs. SDL
+    // reports the controllers already plugged in at start as being added too.
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => self.add(which),
+            Event::ControllerDeviceRemoved { which, .. } => self.remove(which),
+            Event::ControllerButtonDown { which, button, .. } => {
+                self.set_held(which, Input::Button(button), true)
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                self.set_held(which, Input::Button(button), false)
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                let amount = value as f32 / AXIS_MAX;
+                let deadzone = self.deadzone;
+                self.set_held(
+                    which,
+                    Input::Axis(axis, true),
+                    amount > deadzone,
+                );
+                self.set_held(
+                    which,
+                    Input::Axis(axis, false),
+                    -amount > deadzone,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn add(&mut self, joystick_index: u32) {
+        let controller = match self.subsystem.open(joystick_index) {
+            Ok(controller) => controller,
+            Err(err) => {
+                eprintln!("Could not open controller: {}", err);
+                return;
+            }
+        };
+        let id = controller.instance_id();
+        if self
+            .connected
+            .iter()
+            .any(|c| c.controller.instance_id() == id)
+        {
+            return;
+        }
+        let player = (0..)
+            .find(|player| self.connected.iter().all(|c| c.player != *player))
+            .expect("There is always a free player");
+        println!("{} connected as player {}", controller.name(), player + 1);
+        self.connected.push(Connected {
+            controller,
+            player,
+            held: HashSet::new(),
+        });
+    }
+
+    fn remove(&mut self, instance_id: u32) {
+        if let Some(index) = self
+            .connected
+            .iter()
+            .position(|c| c.controller.instance_id() == instance_id)
+        {
+            let gone = self.connected.remove(index);
+            println!("Player {}'s controller disconnected", gone.player + 1);
+        }
+    }
+
+    fn set_held(&mut self, instance_id: u32, input: Input, held: bool) {
+        let connected = self
+            .connected
+            .iter_mut()
+            .find(|c| c.controller.instance_id() == instance_id);
+        if let Some(connected) = connected {
+            if held {
+                connected.held.insert(input);
+            } else {
+                connected.held.remove(&input);
+            }
+        }
+    }
+
+    // The hex keys held on any controller, one bit per key
+    pub fn pressed(&self) -> u16 {
+        self.connected.iter().fold(0, |keys, connected| {
+            // Players past the last mapping share the first one
+            match self.players.get(connected.player).or(self.players.first()) {
+                Some(map) => keys | map.keys(&connected.held),
+                None => keys,
+            }
+        })
+    }
+}
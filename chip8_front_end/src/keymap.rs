@@ -0,0 +1,306 @@
+use std::fmt;
+use std::str::FromStr;
+
+use sdl2::keyboard::Keycode;
+use sdl2::keyboard::Scancode;
+
+// Bindings written with this in front follow the character a key types in
+// the current layout rather than where the key is
+const CHAR_PREFIX: &str = "char:";
+
+// The hex keys in the order they sit on the COSMAC VIP's keypad, left to
+// right and top to bottom
+pub const VIP_ORDER: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xC,
+    0x4, 0x5, 0x6, 0xD,
+    0x7, 0x8, 0x9, 0xE,
+    0xA, 0x0, 0xB, 0xF,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    // Where the key is, named after what it types on a US keyboard, so the
+    // same key is used on any layout
+    Position(Scancode),
+    // What the key types in the current layout
+    Character(Keycode),
+}
+
+impl Binding {
+    pub fn matches(
+        &self,
+        keycode: Option<Keycode>,
+        scancode: Option<Scancode>,
+    ) -> bool {
+        match self {
+            Binding::Position(bound) => scancode == Some(*bound),
+            Binding::Character(bound) => keycode == Some(*bound),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Position(scancode) => write!(f, "{}", scancode.name()),
+            Binding::Character(keycode) => {
+                write!(f, "{}{}", CHAR_PREFIX, keycode.name())
+            }
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Binding, String> {
+        let binding = match s.strip_prefix(CHAR_PREFIX) {
+            Some(name) => Keycode::from_name(name).map(Binding::Character),
+            None => Scancode::from_name(s).map(Binding::Position),
+        };
+        binding.ok_or_else(|| format!("Unknown key: {}", s))
+    }
+}
+
+// Ready made layouts for the hex keypad
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeypadPreset {
+    // The 1234/QWER/ASDF/ZXCV block, laid out like the COSMAC VIP's keypad
+    Qwerty,
+    // The numeric keypad's digits, with A to F on / * - + Enter and .
+    Numpad,
+    // The keys labelled 0 to 9 and A to F, typed like the digits printed on
+    // the COSMAC VIP's keypad, whatever the layout
+    Vip,
+}
+
+impl KeypadPreset {
+    // Indexed by hex key
+    pub fn bindings(&self) -> [Binding; 16] {
+        match self {
+            KeypadPreset::Qwerty => positions([
+                Scancode::X,
+                Scancode::Num1,
+                Scancode::Num2,
+                Scancode::Num3,
+                Scancode::Q,
+                Scancode::W,
+                Scancode::E,
+                Scancode::A,
+                Scancode::S,
+                Scancode::D,
+                Scancode::Z,
+                Scancode::C,
+                Scancode::Num4,
+                Scancode::R,
+                Scancode::F,
+                Scancode::V,
+            ]),
+            KeypadPreset::Numpad => positions([
+                Scancode::Kp0,
+                Scancode::Kp1,
+                Scancode::Kp2,
+                Scancode::Kp3,
+                Scancode::Kp4,
+                Scancode::Kp5,
+                Scancode::Kp6,
+                Scancode::Kp7,
+                Scancode::Kp8,
+                Scancode::Kp9,
+                Scancode::KpDivide,
+                Scancode::KpMultiply,
+                Scancode::KpMinus,
+                Scancode::KpPlus,
+                Scancode::KpEnter,
+                Scancode::KpPeriod,
+            ]),
+            KeypadPreset::Vip => [
+                Keycode::Num0,
+                Keycode::Num1,
+                Keycode::Num2,
+                Keycode::Num3,
+                Keycode::Num4,
+                Keycode::Num5,
+                Keycode::Num6,
+                Keycode::Num7,
+                Keycode::Num8,
+                Keycode::Num9,
+                Keycode::A,
+                Keycode::B,
+                Keycode::C,
+                Keycode::D,
+                Keycode::E,
+                Keycode::F,
+            ]
+            .map(Binding::Character),
+        }
+    }
+}
+
+fn positions(scancodes: [Scancode; 16]) -> [Binding; 16] {
+    scancodes.map(Binding::Position)
+}
+
+impl FromStr for KeypadPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<KeypadPreset, String> {
+        match s.to_lowercase().as_str() {
+            "qwerty" => Ok(KeypadPreset::Qwerty),
+            "numpad" => Ok(KeypadPreset::Numpad),
+            "vip" | "cosmac-vip" => Ok(KeypadPreset::Vip),
+            _ => Err(format!("Unknown keypad preset: {}", s)),
+        }
+    }
+}
+
+// Asks for a key for every hex key in turn, in the order of the VIP's keypad
+pub struct KeyBinder {
+    keypad: [Binding; 16],
+    next: usize,
+}
+
+impl KeyBinder {
+    pub fn new(keypad: [Binding; 16]) -> KeyBinder {
+        KeyBinder { keypad, next: 0 }
+    }
+
+    // The hex key the next key press is bound to
+    pub fn waiting_for(&self) -> usize {
+        VIP_ORDER[self.next]
+    }
+
+    // Binds a key to the hex key being asked for, returning the whole keypad
+    // once every hex key has one. A key can only be bound once.
+    pub fn bind(
+        &mut self,
+        scancode: Scancode,
+    ) -> Result<Option<[Binding; 16]>, String> {
+        let binding = Binding::Position(scancode);
+        let taken = VIP_ORDER[..self.next]
+            .iter()
+            .find(|hex| self.keypad[**hex] == binding);
+        if let Some(hex) = taken {
+            return Err(format!("{} is already bound to {:X}", binding, hex));
+        }
+        self.keypad[self.waiting_for()] = binding;
+        self.next += 1;
+        if self.next == VIP_ORDER.len() {
+            Ok(Some(self.keypad))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+// The keypad as a line for the config file
+pub fn keypad_line(keypad: &[Binding; 16]) -> String {
+    let names: Vec<String> = keypad
+        .iter()
+        .map(|binding| format!("\"{}\"", binding))
+        .collect();
+    format!("keypad = [{}]", names.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_parse_and_print_back() {
+        let position: Binding = "Q".parse().expect("Known key");
+        assert_eq!(position, Binding::Position(Scancode::Q));
+        let character: Binding = "char:Q".parse().expect("Known key");
+        assert_eq!(character, Binding::Character(Keycode::Q));
+        for name in ["Q", "Keypad 7", "Left Shift", "char:Q"].iter() {
+            let binding: Binding = name.parse().expect("Known key");
+            assert_eq!(binding.to_string(), *name);
+        }
+        assert!("Sapce".parse::<Binding>().is_err());
+        assert!("char:Sapce".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn bindings_match_by_position_or_character() {
+        let position = Binding::Position(Scancode::Q);
+        // Q's position types A on AZERTY
+        assert!(position.matches(Some(Keycode::A), Some(Scancode::Q)));
+        assert!(!position.matches(Some(Keycode::Q), Some(Scancode::A)));
+        let character = Binding::Character(Keycode::Q);
+        assert!(character.matches(Some(Keycode::Q), Some(Scancode::A)));
+        assert!(!character.matches(None, Some(Scancode::Q)));
+    }
+
+    #[test]
+    fn presets_bind_every_hex_key_once() {
+        let presets = [
+            KeypadPreset::Qwerty,
+            KeypadPreset::Numpad,
+            KeypadPreset::Vip,
+        ];
+        for preset in presets.iter() {
+            let keypad = preset.bindings();
+            for (hex, binding) in keypad.iter().enumerate() {
+                assert_eq!(
+                    keypad.iter().position(|other| other == binding),
+                    Some(hex),
+                    "{:?} binds {} twice",
+                    preset,
+                    binding
+                );
+            }
+        }
+        let qwerty = KeypadPreset::Qwerty.bindings();
+        assert_eq!(qwerty[0x0], Binding::Position(Scancode::X));
+        assert_eq!(qwerty[0x1], Binding::Position(Scancode::Num1));
+        assert_eq!(qwerty[0xF], Binding::Position(Scancode::V));
+        let vip = KeypadPreset::Vip.bindings();
+        assert_eq!(vip[0xA], Binding::Character(Keycode::A));
+    }
+
+    #[test]
+    fn presets_parse() {
+        assert_eq!("qwerty".parse(), Ok(KeypadPreset::Qwerty));
+        assert_eq!("Numpad".parse(), Ok(KeypadPreset::Numpad));
+        assert_eq!("cosmac-vip".parse(), Ok(KeypadPreset::Vip));
+        assert!("dvorak".parse::<KeypadPreset>().is_err());
+    }
+
+    #[test]
+    fn binder_asks_in_keypad_order_and_refuses_repeats() {
+        let mut binder = KeyBinder::new(KeypadPreset::Qwerty.bindings());
+        let keys = [
+            Scancode::Num7,
+            Scancode::Num8,
+            Scancode::Num9,
+            Scancode::Num0,
+            Scancode::U,
+            Scancode::I,
+            Scancode::O,
+            Scancode::P,
+            Scancode::J,
+            Scancode::K,
+            Scancode::L,
+            Scancode::Semicolon,
+            Scancode::M,
+            Scancode::Comma,
+            Scancode::Period,
+            Scancode::Slash,
+        ];
+        let mut keypad = None;
+        for (index, key) in keys.iter().enumerate() {
+            assert_eq!(binder.waiting_for(), VIP_ORDER[index]);
+            if index == 1 {
+                assert!(binder.bind(Scancode::Num7).is_err());
+                assert_eq!(binder.waiting_for(), VIP_ORDER[index]);
+            }
+            keypad = binder.bind(*key).expect("Key is free");
+            assert_eq!(keypad.is_some(), index == keys.len() - 1);
+        }
+        let keypad = keypad.expect("Every key is bound");
+        assert_eq!(keypad[0x1], Binding::Position(Scancode::Num7));
+        assert_eq!(keypad[0x0], Binding::Position(Scancode::Comma));
+        assert_eq!(keypad[0xF], Binding::Position(Scancode::Slash));
+        assert!(keypad_line(&keypad).starts_with("keypad = [\",\", \"7\","));
+    }
+}
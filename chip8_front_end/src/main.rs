@@ -1,94 +1,722 @@
 extern crate chip8;
 extern crate sdl2;
+extern crate serde;
+extern crate toml;
+mod cli;
+mod config;
+mod gamepad;
+mod keymap;
+mod movies;
+mod panel;
+mod slots;
+mod sound;
+mod tools;
+
 use std::env;
+use chip8::detect_platform;
 use chip8::Chip8;
+use chip8::Chip8Error;
+use chip8::CrashReport;
+use chip8::Debugger;
+use chip8::Fault;
+use chip8::FaultPolicy;
+use chip8::FRAME_RATE;
+use chip8::MoviePlayer;
+use chip8::MovieRecorder;
+use chip8::Opcode;
+use chip8::Rewind;
+use chip8::Rng;
+use chip8::StopReason;
 use std::error::Error;
-use std::fs::File;
-use std::io::Read;
-use sdl2::keyboard::Keycode;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use sdl2::keyboard::Mod;
+use sdl2::render::BlendMode;
 use sdl2::render::TextureAccess;
+use sdl2::pixels::Color;
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::event::Event;
-// use std::time::{Duration, Instant};
+use sdl2::messagebox::show_simple_message_box;
+use sdl2::messagebox::MessageBoxFlag;
+use sound::Sound;
+use cli::Command;
+use cli::RunOptions;
+use config::Config;
+use config::Keys;
+use config::RomControllers;
+use config::DEFAULT_CONFIG;
+use gamepad::Gamepads;
+use panel::KeypadPanel;
+use panel::KeypadView;
+use keymap::Binding;
+use keymap::KeyBinder;
+use sdl2::keyboard::Keycode;
+use sdl2::keyboard::Scancode;
+
+const LORES_WIDTH: usize = 64;
+const LORES_HEIGHT: usize = 32;
+// The clock speed keys change it in steps of this many instructions per
+// second, down to MIN_CLOCK_SPEED
+const CLOCK_STEP: u32 = 10;
+const MIN_CLOCK_SPEED: u32 = 1;
+// How many frames the emulator runs to catch up after a stall before it
+// gives up and carries on from the current time
+const MAX_FRAMES_BEHIND: u32 = 5;
+
+// Snapshot every frame and keep ten seconds worth of them
+const REWIND_INTERVAL: u32 = 1;
+const REWIND_CAPACITY: usize = 600;
 
-const PIXEL_SIZE: usize = 5;
-// const FRAME_RATE: f64 = 60.0; // Measured in frames per second
+// Fast forwarding runs this many frames for every one shown
+const FAST_FORWARD_SPEED: u32 = 4;
 
-fn handle_event(event: Event, emu: &mut Chip8) -> bool {
-    match event {
+const WINDOW_TITLE: &str = "Res";
+const HALTED_TITLE: &str = "Res - halted";
+// Laid over the last frame once a fault halts the machine
+const HALT_OVERLAY: Color = Color::RGBA(160, 0, 0, 110);
+
+// Front end state that outlives a single event
+struct Controls {
+    rewind: Rewind,
+    rewinding: bool,
+    fast_forwarding: bool,
+    // Set by the frame advance key, runs a single frame while paused
+    advance_frame: bool,
+    recorder: Option<MovieRecorder>,
+    player: Option<MoviePlayer>,
+    debugger: Debugger,
+    // None when there is no audio device to play on
+    sound: Option<Sound>,
+    // Set when a fault halted the machine, until rewinding or resetting gets
+    // it going again
+    halted: bool,
+    // Where crash reports are written
+    report_path: PathBuf,
+    // Kept to start the rom over on reset
+    rom: Vec<u8>,
+    keys: Keys,
+    // Resets reuse a seed given on the command line, otherwise they get a
+    // new one
+    seed: Option<u64>,
+    // Set while asking for keys to bind the keypad to
+    binder: Option<KeyBinder>,
+    // None when game controllers aren't available
+    gamepads: Option<Gamepads>,
+    keypad_panel: KeypadPanel,
+    // Hex keys held on the keyboard, one bit per key
+    keyboard_keys: u16,
+    // Hex keys held on any of the keyboard, controllers and on-screen keypad,
+    // as last handed to the machine
+    held_keys: u16,
+}
+
+impl Controls {
+    // Movies are made of whole frames run in order, rewinding, pausing or
+    // stepping while one is recorded or played would break it.
+    fn movie_active(&self) -> bool {
+        self.recorder.is_some() || self.player.is_some()
+    }
+
+    // The fault that halted the machine also paused the debugger, so both
+    // have to let go for it to run again
+    fn clear_halt(&mut self) {
+        if self.halted {
+            self.halted = false;
+            self.debugger.resume();
+        }
+    }
+}
+
+// Returns true once the emulator should quit
+fn handle_event(
+    event: Event,
+    emu: &mut Chip8,
+    controls: &mut Controls,
+    rom_path: &Path,
+) -> bool {
+    let keys = controls.keys.clone();
+    if let Some(gamepads) = controls.gamepads.as_mut() {
+        gamepads.handle_event(&event);
+    }
+    controls.keypad_panel.handle_event(&event);
+    let quit = match event {
+        Event::Quit { .. } => true,
+        Event::KeyDown {
+            keycode,
+            scancode: Some(scancode),
+            ..
+        } if controls.binder.is_some() => {
+            handle_binding(controls, keycode, scancode);
+            false
+        }
+        Event::KeyDown {
+            keycode, scancode, ..
+        } if keys.quit.matches(keycode, scancode) => true,
         Event::KeyDown {
-            keycode: Some(key), ..
+            keycode,
+            scancode,
+            keymod,
+            ..
         } => {
-            if let Some(u_key) = key_to_usize(key) {
-                emu.set_ctrl_state(u_key, true).expect("Can't get here");
+            let key = |binding: &Binding| binding.matches(keycode, scancode);
+            if let Some(u_key) = keys.keypad_key(keycode, scancode) {
+                controls.keyboard_keys |= 1 << u_key;
+            } else if let Some(slot) = keys.save_slot(keycode, scancode) {
+                handle_save_slot(emu, rom_path, slot, keymod);
+            } else if key(&keys.rewind) {
+                controls.rewinding = true;
+            } else if key(&keys.record) {
+                toggle_recording(emu, controls, rom_path);
+            } else if key(&keys.playback) {
+                start_playback(emu, controls, rom_path);
+            } else if key(&keys.pause)
+                && !controls.movie_active()
+                && !controls.halted
+            {
+                toggle_pause(emu, controls);
+            } else if key(&keys.step) && controls.debugger.is_paused() {
+                step(emu, controls);
+            } else if key(&keys.frame_advance) && controls.debugger.is_paused()
+            {
+                controls.advance_frame = true;
+            } else if key(&keys.fast_forward) {
+                controls.fast_forwarding = true;
+            } else if key(&keys.slower) || key(&keys.faster) {
+                change_clock_speed(emu, controls, key(&keys.faster));
+            } else if key(&keys.reset) {
+                reset(emu, controls);
+            } else if key(&keys.bind) {
+                let binder = KeyBinder::new(keys.keypad());
+                println!("Press the key for {:X}", binder.waiting_for());
+                controls.binder = Some(binder);
+            } else if key(&keys.show_keypad) {
+                let panel = &mut controls.keypad_panel;
+                panel.view = panel.view.next();
+            } else if let Some(sound) = controls.sound.as_mut() {
+                if key(&keys.mute) {
+                    sound.toggle_mute();
+                } else if key(&keys.volume_down) {
+                    sound.change_volume(false);
+                } else if key(&keys.volume_up) {
+                    sound.change_volume(true);
+                }
             }
             false
         }
         Event::KeyUp {
-            keycode: Some(key), ..
+            keycode, scancode, ..
         } => {
-            if let Some(u_key) = key_to_usize(key) {
-                emu.set_ctrl_state(u_key, false).expect("Can't get here");
+            if let Some(u_key) = keys.keypad_key(keycode, scancode) {
+                controls.keyboard_keys &= !(1 << u_key);
+            } else if keys.rewind.matches(keycode, scancode) {
+                controls.rewinding = false;
+            } else if keys.fast_forward.matches(keycode, scancode) {
+                controls.fast_forwarding = false;
             }
             false
         }
         _ => false,
+    };
+    update_keypad(emu, controls);
+    quit
+}
 
+// A hex key stays held while any of the keyboard, the controllers or the
+// on-screen keypad holds it
+fn update_keypad(emu: &mut Chip8, controls: &mut Controls) {
+    let gamepad_keys = controls.gamepads.as_ref().map_or(0, Gamepads::pressed);
+    let held = controls.keyboard_keys
+        | gamepad_keys
+        | controls.keypad_panel.pressed();
+    let changed = held ^ controls.held_keys;
+    controls.held_keys = held;
+    for key in (0..16).filter(|key| changed & 1 << key != 0) {
+        handle_keypad(emu, controls, key, held & 1 << key != 0);
+    }
+}
 
+// While binding, key presses go to the binder and the quit key gives up
+fn handle_binding(
+    controls: &mut Controls,
+    keycode: Option<Keycode>,
+    scancode: Scancode,
+) {
+    if controls.keys.quit.matches(keycode, Some(scancode)) {
+        controls.binder = None;
+        println!("Stopped binding, the keypad is unchanged");
+        return;
+    }
+    let binder = match controls.binder.as_mut() {
+        Some(binder) => binder,
+        None => return,
+    };
+    match binder.bind(scancode) {
+        Ok(Some(keypad)) => {
+            controls.keys.keypad = Some(keypad);
+            controls.binder = None;
+            println!("Keypad bound, add this to the config file to keep it:");
+            println!("{}", keymap::keypad_line(&keypad));
+        }
+        Ok(None) => println!("Press the key for {:X}", binder.waiting_for()),
+        Err(err) => eprintln!("{}", err),
     }
 }
 
-fn key_to_usize(key: Keycode) -> Option<usize> {
-    match key {
-        Keycode::Num1 => Some(1),
-        Keycode::Num2 => Some(2),
-        Keycode::Num3 => Some(3),
-        Keycode::Num4 => Some(0xc),
-        Keycode::Q => Some(4),
-        Keycode::W => Some(5),
-        Keycode::E => Some(6),
-        Keycode::R => Some(0xd),
-        Keycode::A => Some(7),
-        Keycode::S => Some(8),
-        Keycode::D => Some(9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
-        _ => None,
+// While a movie plays back it is the only source of input
+fn handle_keypad(
+    emu: &mut Chip8,
+    controls: &mut Controls,
+    key: usize,
+    pressed: bool,
+) {
+    if controls.player.is_some() {
+        return;
     }
+    match controls.recorder.as_mut() {
+        Some(recorder) => recorder.set_ctrl_state(emu, key, pressed),
+        None => emu.set_ctrl_state(key, pressed),
+    }
+    .expect("Can't get here");
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let path: &str = &env::args().nth(1).ok_or("Did not get a rom")?;
-    let mut raw_bytes = Vec::new();
-    let mut raw_rom = File::open(path)?;
-    raw_rom.read_to_end(&mut raw_bytes)?;
+fn toggle_recording(emu: &mut Chip8, controls: &mut Controls, rom_path: &Path) {
+    match controls.recorder.take() {
+        Some(recorder) => match movies::save(recorder, rom_path) {
+            Ok(frames) => println!("Saved movie of {} frames", frames),
+            Err(err) => eprintln!("Could not save movie: {}", err),
+        },
+        None if !emu.clock_speed().is_multiple_of(FRAME_RATE) => eprintln!(
+            "Movies need a clock speed that is a multiple of {} Hz",
+            FRAME_RATE
+        ),
+        None if controls.player.is_none() => {
+            // Starts the clock on a frame boundary, so every frame runs
+            // the same number of instructions
+            emu.set_clock_speed(emu.clock_speed());
+            let cycles = emu.clock_speed() / FRAME_RATE;
+            controls.recorder = Some(MovieRecorder::start(emu, cycles));
+            controls.debugger.resume();
+            println!("Recording movie");
+        }
+        None => eprintln!("Can't record while a movie is playing"),
+    }
+}
 
-    let mut chip8 = Chip8::new(&raw_bytes);
+fn start_playback(emu: &mut Chip8, controls: &mut Controls, rom_path: &Path) {
+    if controls.recorder.is_some() {
+        eprintln!("Can't play a movie while recording one");
+        return;
+    }
+    match movies::play(emu, rom_path) {
+        Ok(player) if player.is_finished() => {
+            eprintln!("The movie has no frames to play")
+        }
+        Ok(player) => {
+            emu.set_clock_speed(player.cycles_per_frame() * FRAME_RATE);
+            println!("Playing movie at {} Hz", emu.clock_speed());
+            controls.player = Some(player);
+            controls.debugger.resume();
+        }
+        Err(err) => eprintln!("Could not play movie: {}", err),
+    }
+}
 
-    // let mut clock_speed: f64 = 540.0;
+fn change_clock_speed(emu: &mut Chip8, controls: &Controls, faster: bool) {
+    if controls.movie_active() {
+        eprintln!("Can't change the clock speed during a movie");
+        return;
+    }
+    let speed = if faster {
+        emu.clock_speed().saturating_add(CLOCK_STEP)
+    } else {
+        emu.clock_speed().saturating_sub(CLOCK_STEP).max(MIN_CLOCK_SPEED)
+    };
+    emu.set_clock_speed(speed);
+    println!("Clock speed {} Hz", speed);
+}
+
+// Starts the rom over. Breakpoints and the pause state are
+// kept, the rewind history goes with the old run.
+fn reset(emu: &mut Chip8, controls: &mut Controls) {
+    if controls.movie_active() {
+        eprintln!("Can't reset during a movie");
+        return;
+    }
+    let seed = controls.seed.unwrap_or_else(new_seed);
+    match emu.reset(&controls.rom, Rng::new(seed)) {
+        Ok(_) => {
+            controls.clear_halt();
+            controls.rewind.clear();
+            println!("Reset");
+        }
+        Err(err) => eprintln!("Could not reset: {}", err),
+    }
+}
+
+fn toggle_pause(emu: &Chip8, controls: &mut Controls) {
+    if controls.debugger.is_paused() {
+        controls.debugger.resume();
+        println!("Resumed");
+    } else {
+        controls.debugger.pause();
+        print_registers(emu);
+    }
+}
+
+// Shows the instructions around addr, pointing at the one at addr
+fn print_listing(emu: &Chip8, addr: u16) {
+    let start = addr.saturating_sub(4);
+    for at in (start..=addr.saturating_add(4)).step_by(2) {
+        let marker = if at == addr { "=>" } else { "  " };
+        let word = (emu.cpu.ram.peek(at as usize) as u16) << 8
+            | emu.cpu.ram.peek(at as usize + 1) as u16;
+        match Opcode::decode_op(word) {
+            Ok(op) => println!("{} {:03X}  {:?}", marker, at, op),
+            Err(_) => println!("{} {:03X}  {:04X}", marker, at, word),
+        }
+    }
+}
+
+fn print_registers(emu: &Chip8) {
+    let regs = &emu.cpu.regs;
+    println!(
+        "PC {:03X}  I {:03X}  SP {:X}  DT {:02X}  ST {:02X}  V {:02X?}",
+        regs.pc(),
+        regs.i_reg(),
+        regs.sp(),
+        regs.delay,
+        regs.sound,
+        regs.v_regs
+    );
+}
+
+// Checks a played back frame and reports where playback diverged, if it did
+fn verify_playback(emu: &Chip8, controls: &mut Controls) {
+    if let Some(mut player) = controls.player.take() {
+        match player.end_frame(emu) {
+            Ok(_) if player.is_finished() => {
+                println!("Movie verified, all {} frames match", player.frame())
+            }
+            Ok(_) => controls.player = Some(player),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+}
+
+// Runs, rewinds or holds the machine for a single frame
+fn emulate_frame(
+    chip8: &mut Chip8,
+    controls: &mut Controls,
+) -> Result<(), Box<dyn Error>> {
+    if controls.rewinding && !controls.movie_active() {
+        stop_sound(controls);
+        controls.rewind.step_back(chip8);
+        controls.clear_halt();
+        return Ok(());
+    }
+    if controls.advance_frame {
+        // Runs like any other frame, then pauses again once it's done
+        controls.advance_frame = false;
+        controls.debugger.resume();
+        emulate_frame(chip8, controls)?;
+        if !controls.debugger.is_paused() {
+            controls.debugger.pause();
+            print_registers(chip8);
+        }
+        return Ok(());
+    }
+    if controls.halted || controls.debugger.is_paused() {
+        stop_sound(controls);
+        return Ok(());
+    }
+    if let Some(player) = controls.player.as_mut() {
+        if let Err(err) = player.start_frame(chip8) {
+            eprintln!("{}", err);
+            controls.player = None;
+        }
+    }
+    let cycles = chip8.cycles_for_frame();
+    match controls.debugger.run_frame(chip8, cycles) {
+        Some(StopReason::Fault(fault)) => handle_fault(chip8, controls, fault),
+        Some(reason) => {
+            println!("{}", reason);
+            print_registers(chip8);
+        }
+        None => {}
+    }
+    // Fast forwarded audio would only be noise
+    match controls.sound.as_mut() {
+        Some(sound) if !controls.fast_forwarding => sound.play_frame(chip8),
+        _ => stop_sound(controls),
+    }
+    controls.rewind.record(chip8);
+    if let Some(recorder) = controls.recorder.as_mut() {
+        recorder.end_frame(chip8);
+    }
+    verify_playback(chip8, controls);
+    Ok(())
+}
+
+// Runs a single instruction on its own, without starting a new frame, so
+// stepping leaves the timers as running would
+fn step(emu: &mut Chip8, controls: &mut Controls) {
+    controls.debugger.step_into();
+    match controls.debugger.run(emu, 1) {
+        Some(StopReason::Fault(fault)) => handle_fault(emu, controls, fault),
+        Some(reason) => {
+            println!("{}", reason);
+            print_registers(emu);
+        }
+        None => {}
+    }
+}
+
+fn stop_sound(controls: &mut Controls) {
+    if let Some(sound) = controls.sound.as_mut() {
+        sound.stop();
+    }
+}
+
+// The debugger has already paused on the fault. Breaking leaves it paused on
+// the faulting instruction, halting stops the machine until it is rewound or
+// reset.
+fn handle_fault(emu: &Chip8, controls: &mut Controls, fault: Fault) {
+    print_registers(emu);
+    let report = CrashReport::new(emu, &fault).to_json();
+    match fs::write(&controls.report_path, report) {
+        Ok(_) => println!(
+            "Wrote crash report to {}",
+            controls.report_path.display()
+        ),
+        Err(err) => eprintln!("Could not write crash report: {}", err),
+    }
+    match emu.fault_policy(&fault) {
+        FaultPolicy::Break => {
+            println!("Faulted: {}", fault);
+            print_listing(emu, fault.pc);
+        }
+        _ => {
+            controls.halted = true;
+            report_error(&Chip8Error::from(fault));
+        }
+    }
+}
+
+// A function key on its own saves to its slot, holding shift loads from it
+fn handle_save_slot(emu: &mut Chip8, rom_path: &Path, slot: u8, keymod: Mod) {
+    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+        match slots::load(emu, rom_path, slot) {
+            Ok(_) => println!("Loaded state from slot {}", slot),
+            Err(err) => eprintln!("Could not load slot {}: {}", slot, err),
+        }
+    } else {
+        match slots::save(emu, rom_path, slot) {
+            Ok(_) => println!("Saved state to slot {}", slot),
+            Err(err) => eprintln!("Could not save slot {}: {}", slot, err),
+        }
+    }
+}
+
+// SUPER-CHIP's RPL user flags are kept next to the rom so they survive
+// between runs, just like they did on the HP-48's persistent memory.
+fn rpl_path(rom_path: &str) -> PathBuf {
+    Path::new(rom_path).with_extension("rpl")
+}
+
+fn crash_report_path(rom_path: &str) -> PathBuf {
+    Path::new(rom_path).with_extension("crash.json")
+}
+
+fn load_rpl_flags(path: &Path, chip8: &mut Chip8) {
+    if let Ok(bytes) = fs::read(path) {
+        let len = bytes.len().min(chip8.cpu.rpl_flags.len());
+        chip8.cpu.rpl_flags[..len].copy_from_slice(&bytes[..len]);
+    }
+}
+
+// Errors go to the terminal and, as the emulator is just as likely to have
+// been started without one, to a message box as well
+fn report_error(err: &dyn Error) {
+    eprintln!("{}", err);
+    let _ = show_simple_message_box(
+        MessageBoxFlag::ERROR,
+        "Chip 8 error",
+        &err.to_string(),
+        None,
+    );
+}
+
+fn main() {
+    if let Err(err) = run() {
+        report_error(&*err);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    match cli::parse(env::args().skip(1).collect())? {
+        Command::Run(options) => play(options),
+        Command::Info(path) => print_info(&path),
+        Command::Disasm(options) => tools::disasm(options),
+        Command::Headless(args) => {
+            // Keeps the exit codes scripts rely on
+            process::exit(tools::headless(args)?);
+        }
+        Command::Help(usage) => {
+            println!("{}", usage);
+            Ok(())
+        }
+        Command::DumpDefaultConfig => {
+            print!("{}", DEFAULT_CONFIG);
+            Ok(())
+        }
+    }
+}
+
+fn print_info(path: &str) -> Result<(), Box<dyn Error>> {
+    let rom = fs::read(path)
+        .map_err(|err| format!("Could not read {}: {}", path, err))?;
+    let platform = detect_platform(&rom);
+    let quirks = platform.default_quirks();
+    let chip8 = Chip8::new(&rom, platform, quirks, Rng::new(0))
+        .map_err(Chip8Error::from)?;
+    println!("Rom       {}", path);
+    println!("Size      {} bytes ({:#X})", rom.len(), rom.len());
+    println!("Hash      {:016x}", chip8.rom_hash());
+    println!("Platform  {}", platform);
+    Ok(())
+}
+
+fn play(options: RunOptions) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load(options.config.as_deref())?;
+    options.apply(&mut config);
+    let report = match &options.load_report {
+        Some(report_path) => Some(CrashReport::from_json(
+            &fs::read_to_string(report_path)?,
+        )?),
+        None => None,
+    };
+    let path: &str = &options.rom;
+    // The command line wins over a crash report, which brings its own
+    // platform and quirks, which wins over the config file
+    let platform = match (options.platform, &report) {
+        (Some(platform), _) => platform,
+        (None, Some(report)) => report.platform,
+        (None, None) => config.emulation.platform,
+    };
+    let quirks = match (options.quirks, &report, config.emulation.quirks) {
+        (Some(quirks), _, _) => quirks,
+        (None, Some(report), _) => report.quirks,
+        (None, None, Some(quirks)) => quirks,
+        (None, None, None) => platform.default_quirks(),
+    };
+    let raw_bytes = fs::read(path)
+        .map_err(|err| format!("Could not read {}: {}", path, err))?;
+    let seed = options.seed.unwrap_or_else(new_seed);
+    let mut chip8 = Chip8::new(&raw_bytes, platform, quirks, Rng::new(seed))
+        .map_err(Chip8Error::from)?;
+    chip8.set_fault_policies(config.emulation.on_fault);
+    chip8.set_clock_speed(config.emulation.clock_speed);
+    chip8.set_timer_rate(config.emulation.timer_rate);
+    let rpl_path = rpl_path(path);
+    load_rpl_flags(&rpl_path, &mut chip8);
+    if let Some(report) = &report {
+        report.restore(&mut chip8)?;
+    }
+    let mut saved_rpl_flags = chip8.cpu.rpl_flags;
+    // A rom's own controller setup wins over the config file's players
+    let players = match RomControllers::load(Path::new(path))? {
+        Some(rom_controllers) => rom_controllers.player,
+        None => config.controllers.player.clone(),
+    };
 
     let sdl_context = sdl2::init().unwrap();
+    let sound = if config.audio.enabled {
+        match Sound::open(&sdl_context, &config.audio) {
+            Ok(sound) => Some(sound),
+            Err(err) => {
+                eprintln!(
+                    "Could not open audio, running without sound: {}",
+                    err
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let deadzone = config.controllers.deadzone;
+    let gamepads = match Gamepads::open(&sdl_context, players, deadzone) {
+        Ok(gamepads) => Some(gamepads),
+        Err(err) => {
+            eprintln!(
+                "Could not open game controllers, running without them: {}",
+                err
+            );
+            None
+        }
+    };
+    let mut controls = Controls {
+        rewind: Rewind::new(REWIND_INTERVAL, REWIND_CAPACITY),
+        rewinding: false,
+        fast_forwarding: false,
+        advance_frame: false,
+        recorder: None,
+        player: None,
+        debugger: Debugger::new(),
+        sound,
+        halted: false,
+        report_path: crash_report_path(path),
+        rom: raw_bytes,
+        keys: config.keys.clone(),
+        seed: options.seed,
+        binder: None,
+        gamepads,
+        keypad_panel: KeypadPanel::new(config.window.keypad),
+        keyboard_keys: 0,
+        held_keys: 0,
+    };
+    if options.paused {
+        controls.debugger.pause();
+        print_registers(&chip8);
+    }
+    // A loaded crash report starts paused on the instruction that faulted
+    if let Some(report) = &report {
+        controls.debugger.pause();
+        println!("Loaded crash report: {}", report.fault);
+        print_registers(&chip8);
+        print_listing(&chip8, chip8.cpu.regs.pc());
+    }
     let video_subsystem = sdl_context.video().unwrap();
 
-    let window = video_subsystem
-        .window(
-            "Res",
-            (chip8.cpu.screen.width * PIXEL_SIZE) as u32,
-            (chip8.cpu.screen.height * PIXEL_SIZE) as u32,
-        )
-        .position_centered()
-        .build()
-        .unwrap();
+    let scale = config.window.scale;
+    let height = LORES_HEIGHT as u32 * scale;
+    // The side panel is a square next to the game
+    let width = match config.window.keypad {
+        KeypadView::Side => LORES_WIDTH as u32 * scale + height,
+        _ => LORES_WIDTH as u32 * scale,
+    };
+    let mut window_builder =
+        video_subsystem.window(WINDOW_TITLE, width, height);
+    window_builder.position_centered();
+    if config.window.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build().unwrap();
 
-    let mut canvas = window
-        .into_canvas()
-        .present_vsync()
-        .accelerated()
-        .build()
-        .unwrap();
+    let mut canvas_builder = window.into_canvas().accelerated();
+    if config.window.vsync {
+        canvas_builder = canvas_builder.present_vsync();
+    }
+    let mut canvas = canvas_builder.build().unwrap();
+    let palette = config.colours.palette();
 
     let texture_creator = canvas.texture_creator();
     let mut texture = texture_creator
@@ -100,33 +728,113 @@ fn main() -> Result<(), Box<dyn Error>> {
         )
         .unwrap();
     let mut event_pump = sdl_context.event_pump().unwrap();
-    let mut framebuffer = Box::new([0; 64 * 32 * 3]);
-
-    loop {
-        // let clocks_per_frame = clock_speed / FRAME_RATE;
-        // let now = Instant::now();
-
-        for y in 0..chip8.cpu.screen.height {
-            for x in 0..chip8.cpu.screen.width {
-                let index = ((y * 64) + x) * 3;
-                let c = if chip8.cpu.screen.buffer[y][x] { 255 } else { 0 };
-                framebuffer[index] = c;
-                framebuffer[index + 1] = c;
-                framebuffer[index + 2] = c;
+    let mut texture_size = (chip8.cpu.screen.width, chip8.cpu.screen.height);
+    let mut framebuffer = vec![0; texture_size.0 * texture_size.1 * 3];
+    let frame_duration = Duration::from_secs(1) / FRAME_RATE;
+    let mut next_frame = Instant::now();
+    let mut keypad_view = controls.keypad_panel.view;
+
+    'running: while !chip8.cpu.exited {
+        let (width, height) = (chip8.cpu.screen.width, chip8.cpu.screen.height);
+        if (width, height) != texture_size {
+            texture = texture_creator
+                .create_texture(
+                    PixelFormatEnum::RGB24,
+                    TextureAccess::Streaming,
+                    width as u32,
+                    height as u32,
+                )
+                .unwrap();
+            framebuffer = vec![0; width * height * 3];
+            texture_size = (width, height);
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = ((y * width) + x) * 3;
+                let colour = palette[chip8.cpu.screen.get_pixel(x, y) as usize];
+                framebuffer[index..index + 3].copy_from_slice(&colour);
             }
         }
 
-        texture.update(None, &(*framebuffer), 64 * 32 * 3).unwrap();
+        if controls.keypad_panel.view != keypad_view {
+            let view = controls.keypad_panel.view;
+            panel::resize_window(canvas.window_mut(), keypad_view, view);
+            keypad_view = view;
+        }
+        let game_area = controls.keypad_panel.game_area(canvas.output_size()?);
+        texture.update(None, &framebuffer, width * 3).unwrap();
         canvas.clear();
-        canvas.copy(&texture, None, None).unwrap();
+        canvas.copy(&texture, None, game_area).unwrap();
+        if controls.halted {
+            canvas.set_blend_mode(BlendMode::Blend);
+            canvas.set_draw_color(HALT_OVERLAY);
+            canvas.fill_rect(game_area).unwrap();
+        }
+        let held = chip8.cpu.keyboard.key_buffer;
+        controls.keypad_panel.draw(&mut canvas, &held, &palette)?;
         canvas.present();
-        
-        for _ in 0..540 {
-            chip8.run_cycle()?
+        let title = match &controls.binder {
+            Some(binder) => format!(
+                "{} - press the key for {:X}",
+                WINDOW_TITLE,
+                binder.waiting_for()
+            ),
+            None if controls.halted => HALTED_TITLE.to_string(),
+            None => WINDOW_TITLE.to_string(),
+        };
+        if canvas.window().title() != title {
+            canvas.window_mut().set_title(&title).unwrap();
+        }
+
+        // Frames are paced by the clock rather than the display, so the
+        // timers run at the same speed on any refresh rate
+        let mut frames = 0;
+        while Instant::now() >= next_frame {
+            if frames == MAX_FRAMES_BEHIND {
+                next_frame = Instant::now();
+                break;
+            }
+            let speed = if controls.fast_forwarding {
+                FAST_FORWARD_SPEED
+            } else {
+                1
+            };
+            for _ in 0..speed {
+                emulate_frame(&mut chip8, &mut controls)?;
+            }
+            next_frame += frame_duration;
+            frames += 1;
+        }
+
+        if chip8.cpu.rpl_flags != saved_rpl_flags {
+            fs::write(&rpl_path, chip8.cpu.rpl_flags)?;
+            saved_rpl_flags = chip8.cpu.rpl_flags;
         }
 
         for event in event_pump.poll_iter() {
-            handle_event(event, &mut chip8);
+            if handle_event(event, &mut chip8, &mut controls, Path::new(path))
+            {
+                break 'running;
+            }
+        }
+
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
         }
     }
+    // Quitting mid recording still keeps the movie
+    if controls.recorder.is_some() {
+        toggle_recording(&mut chip8, &mut controls, Path::new(path));
+    }
+    Ok(())
+}
+
+// Every run gets a fresh seed, the emulator itself is deterministic
+fn new_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or_default()
 }
@@ -0,0 +1,31 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use chip8::Chip8;
+use chip8::Movie;
+use chip8::MoviePlayer;
+use chip8::MovieRecorder;
+
+// Movies live next to the rom, there is one per rom
+fn movie_path(rom_path: &Path) -> PathBuf {
+    rom_path.with_extension("c8m")
+}
+
+pub fn save(
+    recorder: MovieRecorder,
+    rom_path: &Path,
+) -> Result<u64, Box<dyn Error>> {
+    let movie = recorder.finish();
+    fs::write(movie_path(rom_path), movie.to_bytes())?;
+    Ok(movie.frames())
+}
+
+pub fn play(
+    emu: &mut Chip8,
+    rom_path: &Path,
+) -> Result<MoviePlayer, Box<dyn Error>> {
+    let movie = Movie::from_bytes(&fs::read(movie_path(rom_path))?)?;
+    Ok(MoviePlayer::start(movie, emu)?)
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chip8::FONT;
+use keymap::VIP_ORDER;
+use sdl2::event::Event;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::BlendMode;
+use sdl2::render::Canvas;
+use sdl2::video::FullscreenType;
+use sdl2::video::Window;
+
+// SDL also reports touches as clicks from this mouse, which are left to the
+// touch events
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+// How see-through the keys drawn over the game are
+const OVERLAY_ALPHA: u8 = 80;
+const HELD_OVERLAY_ALPHA: u8 = 176;
+const FONT_WIDTH: u32 = 4;
+const FONT_HEIGHT: u32 = 5;
+
+// Where the clickable keypad goes in the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeypadView {
+    Hidden,
+    // A square as tall as the window, right of the game
+    Side,
+    // See-through keys covering the game, for touch screens
+    Overlay,
+}
+
+impl KeypadView {
+    // The view the toggle key switches to
+    pub fn next(self) -> KeypadView {
+        match self {
+            KeypadView::Hidden => KeypadView::Side,
+            KeypadView::Side => KeypadView::Overlay,
+            KeypadView::Overlay => KeypadView::Hidden,
+        }
+    }
+}
+
+impl FromStr for KeypadView {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<KeypadView, String> {
+        match s.to_lowercase().as_str() {
+            "hidden" => Ok(KeypadView::Hidden),
+            "side" => Ok(KeypadView::Side),
+            "overlay" => Ok(KeypadView::Overlay),
+            _ => Err(format!("Unknown keypad view: {}", s)),
+        }
+    }
+}
+
+// The COSMAC VIP's keypad drawn in the window, showing the keys held down
+// and pressing keys that are clicked or touched
+pub struct KeypadPanel {
+    pub view: KeypadView,
+    // Window size the keypad was last drawn at, clicks are matched to that
+    size: (u32, u32),
+    // Hex key under the left mouse button while it's held
+    mouse: Option<usize>,
+    // Hex key under each finger on a touch screen
+    fingers: HashMap<i64, usize>,
+}
+
+impl KeypadPanel {
+    pub fn new(view: KeypadView) -> KeypadPanel {
+        KeypadPanel {
+            view,
+            size: (0, 0),
+            mouse: None,
+            fingers: HashMap::new(),
+        }
+    }
+
+    // Where the game goes in a window of the given size
+    pub fn game_area(&self, (width, height): (u32, u32)) -> Rect {
+        let width = match self.view {
+            KeypadView::Side => width.saturating_sub(height).max(1),
+            _ => width,
+        };
+        Rect::new(0, 0, width, height)
+    }
+
+    fn keypad_area(&self, (width, height): (u32, u32)) -> Option<Rect> {
+        match self.view {
+            KeypadView::Hidden => None,
+            KeypadView::Side => {
+                let side = height.min(width);
+                Some(Rect::new((width - side) as i32, 0, side, height))
+            }
+            KeypadView::Overlay => Some(Rect::new(0, 0, width, height)),
+        }
+    }
+
+    // The hex key drawn at a point in the window
+    fn key_at(&self, x: i32, y: i32) -> Option<usize> {
+        let area = self.keypad_area(self.size)?;
+        if !area.contains_point((x, y)) {
+            return None;
+        }
+        let column = (x - area.x()) as u32 * 4 / area.width();
+        let row = (y - area.y()) as u32 * 4 / area.height();
+        Some(VIP_ORDER[(row * 4 + column) as usize])
+    }
+
+    // Touches are given as fractions of the window
+    fn finger_key(&self, x: f32, y: f32) -> Option<usize> {
+        let (width, height) = self.size;
+        self.key_at((x * width as f32) as i32, (y * height as f32) as i32)
+    }
+
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        held: &[bool; 16],
+        palette: &[[u8; 3]; 4],
+    ) -> Result<(), String> {
+        self.size = canvas.output_size()?;
+        let area = match self.keypad_area(self.size) {
+            Some(area) => area,
+            None => return Ok(()),
+        };
+        let overlay = self.view == KeypadView::Overlay;
+        canvas.set_blend_mode(BlendMode::Blend);
+        if !overlay {
+            canvas.set_draw_color(colour(palette[0], 255));
+            canvas.fill_rect(area)?;
+        }
+        for (index, &key) in VIP_ORDER.iter().enumerate() {
+            // Held keys light up in the foreground colour
+            let (face, digit, alpha) = if held[key] {
+                (palette[1], palette[0], HELD_OVERLAY_ALPHA)
+            } else {
+                (palette[3], palette[1], OVERLAY_ALPHA)
+            };
+            let alpha = if overlay { alpha } else { 255 };
+            let cell = cell(area, index);
+            canvas.set_draw_color(colour(face, alpha));
+            canvas.fill_rect(cell)?;
+            canvas.set_draw_color(colour(digit, alpha));
+            draw_digit(canvas, cell, key)?;
+        }
+        Ok(())
+    }
+
+    // Follows the mouse and fingers over the keypad
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::MouseButtonDown {
+                which,
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if which != TOUCH_MOUSE_ID => self.mouse = self.key_at(x, y),
+            Event::MouseMotion {
+                which,
+                mousestate,
+                x,
+                y,
+                ..
+            } if which != TOUCH_MOUSE_ID && mousestate.left() => {
+                self.mouse = self.key_at(x, y)
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.mouse = None,
+            Event::FingerDown {
+                finger_id, x, y, ..
+            }
+            | Event::FingerMotion {
+                finger_id, x, y, ..
+            } => match self.finger_key(x, y) {
+                Some(key) => {
+                    self.fingers.insert(finger_id, key);
+                }
+                None => {
+                    self.fingers.remove(&finger_id);
+                }
+            },
+            Event::FingerUp { finger_id, .. } => {
+                self.fingers.remove(&finger_id);
+            }
+            _ => {}
+        }
+    }
+
+    // The hex keys held by the mouse or a finger, one bit per key
+    pub fn pressed(&self) -> u16 {
+        self.fingers
+            .values()
+            .chain(self.mouse.iter())
+            .fold(0, |keys, key| keys | 1 << key)
+    }
+}
+
+// Makes room for the side panel, or gives it back, when the view changes.
+// A fullscreen window stays as it is and the game shrinks instead.
+pub fn resize_window(window: &mut Window, from: KeypadView, to: KeypadView) {
+    if window.fullscreen_state() != FullscreenType::Off {
+        return;
+    }
+    let (width, height) = window.size();
+    let width = match (from == KeypadView::Side, to == KeypadView::Side) {
+        (false, true) => width + height,
+        (true, false) => width.saturating_sub(height).max(1),
+        _ => return,
+    };
+    if let Err(err) = window.set_size(width, height) {
+        eprintln!("Could not resize the window: {}", err);
+    }
+}
+
+// The key at the given place in the VIP's keypad order, with a gap around it
+fn cell(area: Rect, index: usize) -> Rect {
+    let (column, row) = ((index % 4) as u32, (index / 4) as u32);
+    let (width, height) = (area.width() / 4, area.height() / 4);
+    let gap = width.min(height) / 12;
+    Rect::new(
+        area.x() + (column * width + gap) as i32,
+        area.y() + (row * height + gap) as i32,
+        width.saturating_sub(gap * 2).max(1),
+        height.saturating_sub(gap * 2).max(1),
+    )
+}
+
+// Draws the digit from the machine's own font, centred and half as tall as
+// the key
+fn draw_digit(
+    canvas: &mut Canvas<Window>,
+    cell: Rect,
+    digit: usize,
+) -> Result<(), String> {
+    let pixel = (cell.width() / (FONT_WIDTH * 2))
+        .min(cell.height() / (FONT_HEIGHT * 2))
+        .max(1);
+    let left =
+        cell.x() + (cell.width().saturating_sub(FONT_WIDTH * pixel) / 2) as i32;
+    let top = cell.y()
+        + (cell.height().saturating_sub(FONT_HEIGHT * pixel) / 2) as i32;
+    let mut pixels = Vec::new();
+    for (row, bits) in FONT[digit].iter().enumerate() {
+        for column in 0..FONT_WIDTH {
+            if bits & 0x80 >> column != 0 {
+                pixels.push(Rect::new(
+                    left + (column * pixel) as i32,
+                    top + (row as u32 * pixel) as i32,
+                    pixel,
+                    pixel,
+                ));
+            }
+        }
+    }
+    canvas.fill_rects(&pixels)
+}
+
+fn colour([r, g, b]: [u8; 3], alpha: u8) -> Color {
+    Color::RGBA(r, g, b, alpha)
+}
@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use chip8::Chip8;
+
+// The save slots, numbered from 1
+pub const SLOTS: usize = 10;
+
+// Save states live next to the rom, one file per slot
+fn slot_path(rom_path: &Path, slot: u8) -> PathBuf {
+    rom_path.with_extension(format!("state{}", slot))
+}
+
+pub fn save(
+    emu: &Chip8,
+    rom_path: &Path,
+    slot: u8,
+) -> Result<(), Box<dyn Error>> {
+    fs::write(slot_path(rom_path, slot), emu.save_state())?;
+    Ok(())
+}
+
+pub fn load(
+    emu: &mut Chip8,
+    rom_path: &Path,
+    slot: u8,
+) -> Result<(), Box<dyn Error>> {
+    let bytes = fs::read(slot_path(rom_path, slot))?;
+    emu.load_state(&bytes)?;
+    Ok(())
+}
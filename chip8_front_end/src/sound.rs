@@ -0,0 +1,85 @@
+use sdl2::audio::AudioQueue;
+use sdl2::audio::AudioSpecDesired;
+use sdl2::Sdl;
+
+use chip8::Beeper;
+use chip8::Chip8;
+use chip8::DEFAULT_SAMPLE_RATE;
+use chip8::FRAME_RATE;
+use config::Audio;
+
+const VOLUME_STEP: f32 = 0.05;
+// Frames of audio allowed to pile up in the queue before new frames are
+// skipped, which keeps the sound from lagging behind the picture
+const MAX_QUEUED_FRAMES: u32 = 4;
+
+pub struct Sound {
+    queue: AudioQueue<f32>,
+    beeper: Beeper,
+    samples: Vec<f32>,
+}
+
+impl Sound {
+    pub fn open(sdl_context: &Sdl, config: &Audio) -> Result<Sound, String> {
+        let desired = AudioSpecDesired {
+            freq: Some(DEFAULT_SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: None,
+        };
+        let queue =
+            sdl_context.audio()?.open_queue::<f32, _>(None, &desired)?;
+        let mut beeper = Beeper::new(queue.spec().freq as u32);
+        beeper.set_volume(config.volume);
+        beeper.set_muted(config.muted);
+        beeper.set_waveform(config.waveform);
+        beeper.set_frequency(config.frequency);
+        queue.resume();
+        Ok(Sound {
+            queue,
+            beeper,
+            samples: Vec::new(),
+        })
+    }
+
+    // Queues the sound of the frame the machine just ran. A frame the queue
+    // has no room for is never rendered, so the wave carries on unbroken.
+    pub fn play_frame(&mut self, emu: &Chip8) {
+        let frame_bytes = self.beeper.sample_rate() / FRAME_RATE * 4;
+        if self.queue.size() > frame_bytes * MAX_QUEUED_FRAMES {
+            return;
+        }
+        self.samples.clear();
+        self.beeper.render_frame(emu, &mut self.samples);
+        self.queue_samples();
+    }
+
+    // Fades out a beep that's still sounding, for frames where the machine
+    // doesn't run or its sound isn't played
+    pub fn stop(&mut self) {
+        self.samples.clear();
+        self.beeper.render_fade_out(&mut self.samples);
+        self.queue_samples();
+    }
+
+    fn queue_samples(&mut self) {
+        if self.samples.is_empty() {
+            return;
+        }
+        if let Err(err) = self.queue.queue_audio(&self.samples) {
+            eprintln!("Could not queue audio: {}", err);
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        let muted = !self.beeper.is_muted();
+        self.beeper.set_muted(muted);
+        println!("Sound {}", if muted { "muted" } else { "unmuted" });
+    }
+
+    pub fn change_volume(&mut self, louder: bool) {
+        let step = if louder { VOLUME_STEP } else { -VOLUME_STEP };
+        let volume = self.beeper.volume() + step;
+        self.beeper.set_volume(volume);
+        println!("Volume {:.0}%", self.beeper.volume() * 100.0);
+    }
+}
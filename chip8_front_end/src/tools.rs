@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::fs;
+use std::fs::File;
+use std::io;
+
+use chip8::disassemble;
+use chip8::registers_json;
+use chip8::write_screen;
+use chip8::CrashReport;
+use chip8::KeyScript;
+use chip8::Outcome;
+use chip8::ScreenFormat;
+use chip8::EXIT_SUCCESS;
+use cli::DisasmOptions;
+use cli::HeadlessArgs;
+
+// Prints the rom's disassembly
+pub fn disasm(options: DisasmOptions) -> Result<(), Box<dyn Error>> {
+    let rom = fs::read(&options.rom)?;
+    let listing = disassemble(&rom, options.platform)?;
+    print!("{}", listing.render(options.syntax));
+    Ok(())
+}
+
+// Runs the rom without a window and returns the exit code
+pub fn headless(args: HeadlessArgs) -> Result<i32, Box<dyn Error>> {
+    let mut options = args.options;
+    if let Some(path) = &args.keys {
+        options.script = fs::read_to_string(path)?
+            .parse::<KeyScript>()
+            .map_err(|err| format!("{}:{}", path, err))?;
+    }
+    let rom = fs::read(&args.rom)?;
+    if args.bench {
+        let labels = ["Without cache", "With cache"];
+        for (label, result) in labels.iter().zip(options.bench(&rom)?.iter()) {
+            println!(
+                "{:<14}{:>12.0} instructions/s ({} in {:.3?})",
+                label,
+                result.per_second(),
+                result.instructions,
+                result.elapsed
+            );
+            if let Some(err) = &result.fault {
+                eprintln!("Stopped early: {}", err);
+            }
+        }
+        return Ok(EXIT_SUCCESS);
+    }
+
+    let (chip8, outcome, frames_run) = options.run(&rom)?;
+    eprintln!("{} after {} frames", outcome, frames_run);
+    if let (Outcome::Fault(fault), Some(path)) = (&outcome, &args.crash_report)
+    {
+        fs::write(path, CrashReport::new(&chip8, fault).to_json())?;
+    }
+    match args.screen.as_deref() {
+        Some("-") => {
+            let stdout = io::stdout();
+            write_screen(&chip8.cpu.screen, ScreenFormat::Ascii, stdout)?
+        }
+        Some(path) => write_screen(
+            &chip8.cpu.screen,
+            ScreenFormat::from_path(path),
+            File::create(path)?,
+        )?,
+        None => {}
+    }
+    match args.regs.as_deref() {
+        Some("-") => println!("{}", registers_json(&chip8)),
+        Some(path) => fs::write(path, registers_json(&chip8) + "\n")?,
+        None => {}
+    }
+    Ok(outcome.exit_code())
+}